@@ -1,30 +1,277 @@
-use crate::core::Paths;
-use crate::core::ipc::{Request, Response};
-use anyhow::Result;
-use std::path::Path;
+//! Talking to the daemon over its Unix socket.
+
+use crate::core::ipc::{
+    BatchSpec, Envelope, FlowSpec, PROTOCOL_VERSION, Request, Response, RunSpec, read_message,
+    write_message,
+};
+use crate::core::logfile::LogReader;
+use crate::core::{
+    Database, Event, EventKind, Group, Job, Paths, Status, UserError, is_process_alive, process,
+};
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
 use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// How long to wait for a daemon to exit after asking it to
+pub const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How often log and event streams look for more
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Most output returned by one [`LogStream::next`]
+const MAX_LOG_CHUNK: u64 = 64 * 1024;
+
+/// A connection to the jb daemon.
+///
+/// The typed methods ([`run`](Self::run), [`wait`](Self::wait), ...) return a
+/// [`DaemonError`] when the daemon refuses a request, e.g. for an unknown job.
+///
+/// Clones share the connection, and requests from them run concurrently: many
+/// waits can be in flight at once. Dropping a wait before it returns cancels it
+/// on the daemon.
+#[derive(Clone)]
 pub struct DaemonClient {
-    stream: UnixStream,
+    connection: Arc<Connection>,
+}
+
+struct Connection {
+    /// Requests for the writer task
+    outgoing: mpsc::UnboundedSender<Envelope<Request>>,
+    /// Where each in-flight request's response goes, by request ID;
+    /// None once the connection is gone
+    pending: Pending,
+    next_id: AtomicU64,
+    /// Held through each exchange with a daemon that answers one request at a
+    /// time (no `multiplex` capability), whose responses carry no ID
+    turn: Option<tokio::sync::Mutex<()>>,
+    tasks: [JoinHandle<()>; 2],
+    daemon_version: String,
+    capabilities: Vec<String>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+type Pending = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Response>>>>>;
+
+/// Request ID for the exchange with a daemon that doesn't multiplex
+const UNTAGGED: u64 = 0;
+
+/// Unregisters a request whose response never arrived, cancelling it on the
+/// daemon if it was a wait
+struct InFlight<'a> {
+    connection: &'a Connection,
+    id: u64,
+    wait: bool,
+    answered: bool,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.answered {
+            return;
+        }
+        let mut pending = self.connection.pending.lock().unwrap();
+        if self.connection.turn.is_some() {
+            // The late response would be taken for the next request's
+            *pending = None;
+            return;
+        }
+        if let Some(pending) = pending.as_mut() {
+            pending.remove(&self.id);
+        }
+        if self.wait {
+            let cancel = Request::Cancel { id: self.id };
+            let id = self.connection.next_id.fetch_add(1, Ordering::Relaxed);
+            let _ = self
+                .connection
+                .outgoing
+                .send(Envelope::new(Some(id), cancel));
+        }
+    }
+}
+
+/// The daemon refused a request, e.g. `Job not found: abcd`.
+#[derive(Debug)]
+pub struct DaemonError {
+    pub message: String,
+}
+
+impl fmt::Display for DaemonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DaemonError {}
+
+/// Whether `e` is the daemon refusing a request with a message containing `text`
+#[must_use]
+pub fn is_daemon_error(e: &anyhow::Error, text: &str) -> bool {
+    e.downcast_ref::<DaemonError>()
+        .is_some_and(|e| e.message.contains(text))
+}
+
+/// Answer to [`DaemonClient::ping`]
+#[derive(Debug, Clone, Serialize)]
+pub struct DaemonInfo {
+    pub pid: u32,
+    pub uptime_secs: u64,
+    pub running_jobs: usize,
+    pub total_jobs: usize,
+}
+
+/// What is listening on the daemon socket.
+pub enum Probe {
+    NotRunning,
+    Ready(DaemonClient),
+    /// Started from an older jb; `version` is None if it predates the handshake
+    Outdated {
+        version: Option<String>,
+    },
+    /// Started from a newer jb than this one
+    Newer {
+        version: String,
+    },
 }
 
 impl DaemonClient {
-    async fn connect_to(socket_path: impl AsRef<Path>) -> Result<Self> {
-        let stream = UnixStream::connect(socket_path).await?;
-        Ok(Self { stream })
+    /// Connect and exchange versions with whatever daemon owns `socket_path`.
+    pub async fn probe_at(socket_path: impl AsRef<Path>) -> Result<Probe> {
+        let Ok(mut stream) = UnixStream::connect(socket_path).await else {
+            return Ok(Probe::NotRunning);
+        };
+
+        let hello = Request::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_version: CLIENT_VERSION.to_string(),
+        };
+        let reply = match write_message(&mut stream, &Envelope::new(None, hello)).await {
+            Ok(()) => read_message::<Envelope<Response>>(&mut stream).await,
+            Err(e) => Err(e),
+        };
+        match reply {
+            Ok(Some(Envelope {
+                message:
+                    Response::Hello {
+                        protocol_version,
+                        daemon_version,
+                        capabilities,
+                    },
+                ..
+            })) => Ok(if protocol_version < PROTOCOL_VERSION {
+                Probe::Outdated {
+                    version: Some(daemon_version),
+                }
+            } else if protocol_version > PROTOCOL_VERSION {
+                Probe::Newer {
+                    version: daemon_version,
+                }
+            } else {
+                Probe::Ready(Self::start(stream, daemon_version, capabilities))
+            }),
+            Ok(Some(_)) => bail!("Unexpected response from daemon"),
+            // Daemons from before the handshake drop the connection on a message
+            // they can't parse
+            Ok(None) | Err(_) => Ok(Probe::Outdated { version: None }),
+        }
+    }
+
+    /// Hand the connection to a reader and a writer task.
+    fn start(stream: UnixStream, daemon_version: String, capabilities: Vec<String>) -> Self {
+        let (mut reader, mut writer) = stream.into_split();
+        let pending: Pending = Arc::new(Mutex::new(Some(HashMap::new())));
+        let (outgoing, mut requests) = mpsc::unbounded_channel::<Envelope<Request>>();
+
+        let responses = pending.clone();
+        let read = tokio::spawn(async move {
+            while let Ok(Some(Envelope { id, message })) =
+                read_message::<Envelope<Response>>(&mut reader).await
+            {
+                let mut pending = responses.lock().unwrap();
+                // Responses to cancelled waits are no longer wanted
+                if let Some(tx) = pending
+                    .as_mut()
+                    .and_then(|p| p.remove(&id.unwrap_or(UNTAGGED)))
+                {
+                    let _ = tx.send(message);
+                }
+            }
+            *responses.lock().unwrap() = None;
+        });
+        let requests_pending = pending.clone();
+        let write = tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                if write_message(&mut writer, &request).await.is_err() {
+                    break;
+                }
+            }
+            *requests_pending.lock().unwrap() = None;
+        });
+
+        let multiplex = capabilities.iter().any(|c| c == "multiplex");
+        Self {
+            connection: Arc::new(Connection {
+                outgoing,
+                pending,
+                next_id: AtomicU64::new(UNTAGGED + 1),
+                turn: (!multiplex).then(tokio::sync::Mutex::default),
+                tasks: [read, write],
+                daemon_version,
+                capabilities,
+            }),
+        }
+    }
+
+    /// Find out whether a daemon is running and if it speaks our protocol.
+    pub async fn probe() -> Result<Probe> {
+        Self::probe_at(Paths::new().socket()).await
+    }
+
+    /// Connect to a running daemon without starting one
+    pub async fn connect() -> Result<Self> {
+        match Self::probe().await? {
+            Probe::Ready(client) => Ok(client),
+            Probe::NotRunning => bail!(UserError::new("Daemon is not running")),
+            Probe::Outdated { version } => bail!(outdated_error(version.as_deref(), None)),
+            Probe::Newer { version } => bail!(newer_error(&version)),
+        }
     }
 
-    /// Connect to daemon, starting it if not running
+    /// Connect to daemon, starting it if not running.
+    ///
+    /// A daemon left over from an older jb is replaced if it has no running or
+    /// queued jobs; otherwise replacing it is left to `jb daemon restart`.
     pub async fn connect_or_start() -> Result<Self> {
         let paths = Paths::new();
 
-        // Try connecting first
-        if let Ok(client) = Self::connect_to(paths.socket()).await {
-            return Ok(client);
+        match Self::probe_at(paths.socket()).await? {
+            Probe::Ready(client) => return Ok(client),
+            Probe::NotRunning => {}
+            Probe::Newer { version } => bail!(newer_error(&version)),
+            Probe::Outdated { version } => {
+                let busy = busy_jobs(&paths)?;
+                if busy > 0 {
+                    bail!(outdated_error(version.as_deref(), Some(busy)));
+                }
+                stop_by_pid(&paths).await?;
+            }
         }
 
         // Daemon not running, start it
@@ -33,7 +280,7 @@ impl DaemonClient {
         // Wait for daemon to be ready
         for _ in 0..50 {
             tokio::time::sleep(Duration::from_millis(100)).await;
-            if let Ok(client) = Self::connect_to(paths.socket()).await {
+            if let Probe::Ready(client) = Self::probe_at(paths.socket()).await? {
                 return Ok(client);
             }
         }
@@ -41,36 +288,407 @@ impl DaemonClient {
         anyhow::bail!("Daemon failed to start within 5 seconds")
     }
 
-    pub async fn send(&mut self, request: Request) -> Result<Response> {
-        // Write request
-        let data = serde_json::to_vec(&request)?;
-        #[allow(clippy::cast_possible_truncation)] // messages are always < 4GB
-        let len = (data.len() as u32).to_be_bytes();
-        self.stream.write_all(&len).await?;
-        self.stream.write_all(&data).await?;
-        self.stream.flush().await?;
+    /// Version of jb the daemon was started from
+    #[must_use]
+    pub fn daemon_version(&self) -> &str {
+        &self.connection.daemon_version
+    }
+
+    /// Whether the daemon handles an optional request, e.g. "handoff"
+    #[must_use]
+    pub fn supports(&self, capability: &str) -> bool {
+        self.connection.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Start a job.
+    pub async fn run(&self, spec: RunSpec) -> Result<Job> {
+        if spec.priority != 0 {
+            self.require_priority()?;
+        }
+        self.job(Request::Run(Box::new(spec))).await
+    }
+
+    /// Start several jobs as a group. If one can't be created, none are left
+    /// running.
+    pub async fn batch(&self, spec: BatchSpec) -> Result<(Group, Vec<Job>)> {
+        if !self.supports("batch") {
+            bail!(UserError::new(format!(
+                "The running daemon (jb {}) doesn't support batches; run `jb daemon restart`",
+                self.daemon_version()
+            )));
+        }
+        if spec.jobs.iter().any(|job| job.priority != 0) {
+            self.require_priority()?;
+        }
+        match self.send(Request::Batch(spec)).await? {
+            Response::Group { group, jobs } => Ok((group, jobs)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Start a pipeline's steps as a group, each once the steps it needs
+    /// allow.
+    pub async fn flow(&self, spec: FlowSpec) -> Result<(Group, Vec<Job>)> {
+        if !self.supports("flow") {
+            bail!(UserError::new(format!(
+                "The running daemon (jb {}) doesn't support flows; run `jb daemon restart`",
+                self.daemon_version()
+            )));
+        }
+        if spec.steps.iter().any(|step| step.job.priority != 0) {
+            self.require_priority()?;
+        }
+        match self.send(Request::Flow(spec)).await? {
+            Response::Group { group, jobs } => Ok((group, jobs)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// A job by ID (or unique prefix).
+    pub async fn status(&self, id: &str) -> Result<Job> {
+        self.job(Request::Status { id: id.to_string() }).await
+    }
+
+    /// Recent jobs, newest first.
+    pub async fn list(&self, status: Option<Status>, limit: Option<usize>) -> Result<Vec<Job>> {
+        let request = Request::List {
+            status: status.map(|s| s.as_str().to_string()),
+            limit,
+        };
+        match self.send(request).await? {
+            Response::Jobs(jobs) => Ok(jobs),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Stop a running job, or cancel a queued one.
+    pub async fn stop(&self, id: &str, force: bool) -> Result<()> {
+        self.ok(Request::Stop {
+            id: id.to_string(),
+            force,
+        })
+        .await
+    }
 
-        // Read response
-        let mut len_buf = [0u8; 4];
-        self.stream.read_exact(&mut len_buf).await?;
-        let len = u32::from_be_bytes(len_buf) as usize;
+    /// Replace a running service's process.
+    pub async fn restart(&self, id: &str) -> Result<()> {
+        self.ok(Request::Restart { id: id.to_string() }).await
+    }
 
-        if len > 10 * 1024 * 1024 {
-            anyhow::bail!("Response too large: {len} bytes");
+    /// Change a queued job's priority; higher starts first.
+    pub async fn reprioritize(&self, id: &str, priority: i32) -> Result<Job> {
+        self.require_priority()?;
+        self.job(Request::Reprioritize {
+            id: id.to_string(),
+            priority,
+        })
+        .await
+    }
+
+    /// An older daemon would silently drop priorities
+    fn require_priority(&self) -> Result<()> {
+        if !self.supports("priority") {
+            bail!(UserError::new(format!(
+                "The running daemon (jb {}) doesn't support priorities; run `jb daemon restart`",
+                self.daemon_version()
+            )));
         }
+        Ok(())
+    }
 
-        let mut buf = vec![0u8; len];
-        self.stream.read_exact(&mut buf).await?;
+    /// Wait for a job to exit and return it. The daemon counts `timeout` in
+    /// whole seconds, so it is rounded up.
+    pub async fn wait(&self, id: &str, timeout: Option<Duration>) -> Result<Job> {
+        self.wait_for(id, timeout, false).await
+    }
+
+    /// Wait for a job's readiness checks to pass and return it. Fails if the
+    /// job exits first. `timeout` is rounded up to whole seconds.
+    pub async fn wait_ready(&self, id: &str, timeout: Option<Duration>) -> Result<Job> {
+        self.wait_for(id, timeout, true).await
+    }
+
+    async fn wait_for(&self, id: &str, timeout: Option<Duration>, ready: bool) -> Result<Job> {
+        self.job(Request::Wait {
+            id: id.to_string(),
+            timeout_secs: timeout.map(whole_secs),
+            ready,
+        })
+        .await
+    }
 
-        let response: Response = serde_json::from_slice(&buf)?;
+    /// Stop the daemon, interrupting its running and queued jobs.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.ok(Request::Shutdown).await
+    }
+
+    /// Stop the daemon, leaving its jobs for the next daemon to adopt.
+    pub async fn handoff(&self) -> Result<()> {
+        self.ok(Request::Handoff).await
+    }
+
+    /// Re-read the config file; returns what changed.
+    pub async fn reload(&self) -> Result<Vec<String>> {
+        match self.send(Request::Reload).await? {
+            Response::Reloaded { changes } => Ok(changes),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub async fn ping(&self) -> Result<DaemonInfo> {
+        match self.send(Request::Ping).await? {
+            Response::Pong {
+                pid,
+                uptime_secs,
+                running_jobs,
+                total_jobs,
+            } => Ok(DaemonInfo {
+                pid,
+                uptime_secs,
+                running_jobs,
+                total_jobs,
+            }),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Follow a job's output from the oldest part still kept, including
+    /// segments the daemon rotated out of its log.
+    pub async fn logs(&self, id: &str) -> Result<LogStream> {
+        let job = self.status(id).await?;
+        Ok(LogStream {
+            reader: LogReader::new(&Paths::new(), &job.id, 0),
+            job_id: job.id,
+            client: self.clone(),
+        })
+    }
+
+    /// Follow lifecycle events: a job's whole history, or with no job, every
+    /// event from now on.
+    pub async fn events(&self, id: Option<&str>) -> Result<EventStream> {
+        let db = Database::open(&Paths::new())?;
+        let (job_id, last_id) = match id {
+            Some(id) => (Some(self.status(id).await?.id), 0),
+            None => (
+                None,
+                db.events(None, 0, Some(1))?.last().map_or(0, |e| e.id),
+            ),
+        };
+        Ok(EventStream {
+            db,
+            job_id,
+            last_id,
+            pending: VecDeque::new(),
+            finished: false,
+        })
+    }
+
+    async fn job(&self, request: Request) -> Result<Job> {
+        match self.send(request).await? {
+            Response::Job(job) => Ok(*job),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn ok(&self, request: Request) -> Result<()> {
+        match self.send(request).await? {
+            Response::Ok => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Send a request and return the daemon's raw response.
+    pub async fn send(&self, request: Request) -> Result<Response> {
+        let connection = &*self.connection;
+        let _turn = match &connection.turn {
+            Some(turn) => Some(turn.lock().await),
+            None => None,
+        };
+        let id = if connection.turn.is_some() {
+            UNTAGGED
+        } else {
+            connection.next_id.fetch_add(1, Ordering::Relaxed)
+        };
+
+        let (tx, rx) = oneshot::channel();
+        match connection.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(id, tx),
+            None => bail!("Lost connection to the daemon"),
+        };
+        let mut in_flight = InFlight {
+            connection,
+            id,
+            wait: matches!(request, Request::Wait { .. }),
+            answered: false,
+        };
+
+        let envelope = Envelope::new((id != UNTAGGED).then_some(id), request);
+        if connection.outgoing.send(envelope).is_err() {
+            bail!("Lost connection to the daemon");
+        }
+        let response = rx
+            .await
+            .map_err(|_| anyhow::anyhow!("Lost connection to the daemon"))?;
+        in_flight.answered = true;
         Ok(response)
     }
 }
 
-fn start_daemon() -> Result<()> {
-    // Use same binary with "daemon" subcommand
-    let exe = std::env::current_exe()?;
+fn unexpected(response: Response) -> anyhow::Error {
+    match response {
+        Response::Error(message) => DaemonError { message }.into(),
+        _ => anyhow::anyhow!("Unexpected response from daemon"),
+    }
+}
+
+/// A job's output as it is written, from [`DaemonClient::logs`].
+pub struct LogStream {
+    client: DaemonClient,
+    job_id: String,
+    reader: LogReader,
+}
+
+impl LogStream {
+    /// The next piece of output, waiting for more while the job runs.
+    /// Returns None once the job has exited and everything was read.
+    pub async fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            let data = self.read_more().await?;
+            if !data.is_empty() {
+                return Ok(Some(data));
+            }
+            if self.client.status(&self.job_id).await?.status.is_terminal() {
+                // Whatever was written just before it exited
+                let data = self.read_more().await?;
+                return Ok((!data.is_empty()).then_some(data));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Output since the last read, following the log across rotations
+    async fn read_more(&mut self) -> Result<Vec<u8>> {
+        // Left in place at the same position if the read panics
+        let resume = LogReader::new(&Paths::new(), &self.job_id, self.reader.offset());
+        let mut reader = std::mem::replace(&mut self.reader, resume);
+        let (reader, data) = tokio::task::spawn_blocking(move || {
+            let data = reader.read(MAX_LOG_CHUNK);
+            (reader, data)
+        })
+        .await?;
+        self.reader = reader;
+        Ok(data?)
+    }
+}
+
+/// Lifecycle events as they are recorded, from [`DaemonClient::events`].
+pub struct EventStream {
+    db: Database,
+    job_id: Option<String>,
+    last_id: i64,
+    pending: VecDeque<Event>,
+    finished: bool,
+}
 
+impl EventStream {
+    /// The next event, waiting for one if need be. A single job's stream ends
+    /// (None) once the job has exited and its events were read.
+    pub async fn next(&mut self) -> Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.finished |= self.job_id.is_some() && event.kind == EventKind::Finished;
+                return Ok(Some(event));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+            // Checked first so the events it recorded before exiting are read
+            let exited = match &self.job_id {
+                Some(id) => self.db.get(id)?.is_none_or(|job| job.status.is_terminal()),
+                None => false,
+            };
+            let events = self.db.events(self.job_id.as_deref(), self.last_id, None)?;
+            match events.last() {
+                Some(last) => self.last_id = last.id,
+                None if exited => self.finished = true,
+                None => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+            self.pending.extend(events);
+        }
+    }
+}
+
+fn outdated_error(version: Option<&str>, busy: Option<usize>) -> UserError {
+    let from = version.map_or_else(
+        || format!("started before jb {CLIENT_VERSION}"),
+        |v| format!("jb {v}, this is jb {CLIENT_VERSION}"),
+    );
+    let mut message = format!("The running daemon is outdated ({from}); run `jb daemon restart`");
+    if let Some(busy) = busy {
+        message.push_str(&format!(
+            " (its {busy} running or queued jobs will be interrupted)"
+        ));
+    }
+    UserError::new(message)
+}
+
+fn newer_error(version: &str) -> UserError {
+    UserError::new(format!(
+        "The running daemon is from a newer jb ({version}, this is jb {CLIENT_VERSION}); \
+         use the newer jb or run `jb daemon restart` to replace the daemon"
+    ))
+}
+
+/// Running and queued jobs, as recorded in the database.
+fn busy_jobs(paths: &Paths) -> Result<usize> {
+    let db = Database::open(paths)?;
+    Ok(db.count(Some(Status::Running))? + db.count(Some(Status::Pending))?)
+}
+
+/// Stop a daemon we can't talk to with SIGTERM, as on any shutdown, and wait
+/// for it to exit.
+pub async fn stop_by_pid(paths: &Paths) -> Result<()> {
+    let Some(pid) = process::running_pid_from_file(&paths.pid_file()) else {
+        return Ok(());
+    };
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{Signal, kill};
+        use nix::unistd::Pid;
+        #[allow(clippy::cast_possible_wrap)]
+        let _ = kill(Pid::from_raw(pid as i32), Signal::SIGTERM);
+    }
+    wait_for_exit(pid).await
+}
+
+/// `duration` in seconds, rounded up so a sub-second timeout isn't 0
+fn whole_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// Wait up to [`EXIT_TIMEOUT`] for the daemon with `pid` to exit.
+pub async fn wait_for_exit(pid: u32) -> Result<()> {
+    let start = std::time::Instant::now();
+    while is_process_alive(pid) {
+        if start.elapsed() >= EXIT_TIMEOUT {
+            bail!("Daemon (PID {pid}) did not exit");
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Ok(())
+}
+
+/// The `jb` binary: this one, or the one on PATH when the client is part of
+/// another program
+fn jb_binary() -> PathBuf {
+    match std::env::current_exe() {
+        Ok(exe) if exe.file_stem().is_some_and(|name| name == "jb") => exe,
+        _ => PathBuf::from("jb"),
+    }
+}
+
+fn start_daemon() -> Result<()> {
+    let exe = jb_binary();
     // Spawn daemon detached
     Command::new(&exe)
         .arg("daemon")
@@ -78,7 +696,68 @@ fn start_daemon() -> Result<()> {
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .process_group(0)
-        .spawn()?;
+        .spawn()
+        .with_context(|| format!("Failed to start the daemon ({})", exe.display()))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_outdated_error_points_at_restart() {
+        let message = outdated_error(Some("0.0.1"), None).to_string();
+        assert!(message.contains("jb 0.0.1, this is jb"));
+        assert!(message.contains("run `jb daemon restart`"));
+
+        let message = outdated_error(None, Some(2)).to_string();
+        assert!(message.contains("started before jb"));
+        assert!(message.contains("its 2 running or queued jobs"));
+    }
+
+    #[test]
+    fn test_wait_timeout_rounds_up() {
+        assert_eq!(whole_secs(Duration::from_millis(1)), 1);
+        assert_eq!(whole_secs(Duration::from_millis(1500)), 2);
+        assert_eq!(whole_secs(Duration::from_secs(3)), 3);
+        assert_eq!(whole_secs(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn test_refusals_are_daemon_errors() {
+        let e = unexpected(Response::Error("Wait timed out after 5s".to_string()));
+        assert!(is_daemon_error(&e, "timed out"));
+        assert!(is_daemon_error(&e.context("Wait failed"), "timed out"));
+
+        let e = unexpected(Response::Ok);
+        assert!(e.downcast_ref::<DaemonError>().is_none());
+        assert!(!is_daemon_error(&e, "Unexpected"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_treats_pre_handshake_daemon_as_outdated() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let socket = tmp.path().join("daemon.sock");
+        assert!(matches!(
+            DaemonClient::probe_at(&socket).await.unwrap(),
+            Probe::NotRunning
+        ));
+
+        // An old daemon reads the hello, fails to parse it and hangs up
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).await.unwrap();
+            let mut buf = vec![0u8; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut buf).await.unwrap();
+        });
+        assert!(matches!(
+            DaemonClient::probe_at(&socket).await.unwrap(),
+            Probe::Outdated { version: None }
+        ));
+    }
+}
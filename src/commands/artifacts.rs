@@ -0,0 +1,72 @@
+use crate::core::{Database, Paths, UserError, artifact};
+use anyhow::{Result, bail};
+use std::path::Path;
+
+pub fn execute(id: &str, extract: Option<&Path>, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    // Check for orphaned jobs (dead processes still marked running)
+    db.recover_orphans();
+
+    let job = db.resolve(id)?;
+    let artifacts = db.list_artifacts(&job.id)?;
+
+    if let Some(dest) = extract {
+        if artifacts.is_empty() {
+            bail!(UserError::new(format!(
+                "Job {} has no collected artifacts",
+                job.short_id()
+            )));
+        }
+        let written = artifact::extract(&artifacts, &paths.job_artifacts_dir(&job.id), dest)?;
+        if json {
+            println!("{}", serde_json::to_string(&written)?);
+        } else {
+            for path in written {
+                println!("{}", path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&artifacts)?);
+        return Ok(());
+    }
+
+    if artifacts.is_empty() {
+        if job.artifacts.is_empty() {
+            println!("No artifacts declared (use `jb run --artifact <pattern>`)");
+        } else if job.status.is_terminal() {
+            println!("No files matched: {}", job.artifacts.join(", "));
+        } else {
+            println!("Job {} is still {}", job.short_id(), job.status);
+        }
+        return Ok(());
+    }
+
+    println!("{:<10} {:<14} PATH", "SIZE", "SHA256");
+    for a in &artifacts {
+        println!(
+            "{:<10} {:<14} {}",
+            format_size(a.size),
+            &a.sha256[..12],
+            a.path
+        );
+    }
+    println!("\nStored in {}", paths.job_artifacts_dir(&job.id).display());
+
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1}M", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1}K", bytes as f64 / 1024.0)
+    } else {
+        format!("{bytes}B")
+    }
+}
@@ -0,0 +1,182 @@
+use crate::client::DaemonClient;
+use crate::commands::{group, run};
+use crate::core::ipc::{BatchSpec, RunSpec};
+use crate::core::{UserError, parse_duration};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// One job in `jb batch --stdin` input: a command, or an object with settings
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Item {
+    Command(String),
+    Job {
+        command: String,
+        name: Option<String>,
+        /// Relative to the current directory
+        cwd: Option<PathBuf>,
+        /// Duration, e.g. `10m`
+        timeout: Option<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+}
+
+/// Start commands from `commands`, `file` (one per line) and JSON on stdin as
+/// one group, printing the group ID.
+#[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)]
+pub async fn execute(
+    commands: Vec<String>,
+    name: Option<String>,
+    file: Option<PathBuf>,
+    stdin: bool,
+    timeout: Option<String>,
+    priority: Option<String>,
+    wait: bool,
+    json: bool,
+) -> Result<()> {
+    if stdin && file.as_ref().is_some_and(|path| path.as_os_str() == "-") {
+        anyhow::bail!(UserError::new(
+            "--file - and --stdin both read stdin; use one of them"
+        ));
+    }
+    let mut items: Vec<Item> = commands.into_iter().map(Item::Command).collect();
+    if let Some(path) = file {
+        let text = if path.as_os_str() == "-" {
+            read_stdin()?
+        } else {
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+        };
+        items.extend(parse_lines(&text).map(Item::Command));
+    }
+    if stdin {
+        let parsed: Vec<Item> = serde_json::from_str(&read_stdin()?).map_err(|e| {
+            UserError::new(format!(
+                "Invalid JSON on stdin (expected an array of commands or {{\"command\": ...}} objects): {e}"
+            ))
+        })?;
+        items.extend(parsed);
+    }
+    if items.is_empty() {
+        anyhow::bail!(UserError::new(
+            "No commands given (pass them as arguments, with --file or as JSON with --stdin)"
+        ));
+    }
+
+    let timeout_secs = timeout.map(|t| parse_duration(&t)).transpose()?;
+    let priority = run::priority_arg(priority)?;
+    let jobs = items
+        .into_iter()
+        .map(|item| {
+            let spec = RunSpec {
+                priority,
+                ..to_spec(item, timeout_secs)?
+            };
+            spec.validate()?;
+            Ok(spec)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let client = DaemonClient::connect_or_start().await?;
+    let (group, jobs) = client.batch(BatchSpec { name, jobs }).await?;
+
+    if json {
+        println!("{}", serde_json::json!({ "group": group, "jobs": jobs }));
+    } else {
+        println!("{}", group.id);
+        for job in &jobs {
+            eprintln!("  {}  {}", job.short_id(), job.command);
+        }
+    }
+
+    if wait {
+        group::wait(group.id, false, None, json).await?;
+    }
+    Ok(())
+}
+
+fn read_stdin() -> Result<String> {
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .context("Failed to read stdin")?;
+    Ok(text)
+}
+
+/// Commands from a file: one per line, skipping blank lines and `#` comments
+fn parse_lines(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+}
+
+fn to_spec(item: Item, default_timeout: Option<u64>) -> Result<RunSpec> {
+    let (command, name, cwd, timeout, env) = match item {
+        Item::Command(command) => (command, None, None, None, BTreeMap::new()),
+        Item::Job {
+            command,
+            name,
+            cwd,
+            timeout,
+            env,
+        } => (command, name, cwd, timeout, env),
+    };
+    if command.trim().is_empty() {
+        anyhow::bail!(UserError::new("Empty command in batch"));
+    }
+
+    let mut spec = run::spec_in_current_dir(command)?;
+    if let Some(cwd) = cwd {
+        let cwd = std::fs::canonicalize(&cwd)
+            .map_err(|e| UserError::new(format!("cwd {}: {e}", cwd.display())))?;
+        spec.project = crate::core::detect_project(&cwd)
+            .to_string_lossy()
+            .to_string();
+        spec.cwd = cwd.to_string_lossy().to_string();
+    }
+    spec.name = name;
+    spec.timeout_secs = timeout
+        .map(|t| parse_duration(&t))
+        .transpose()?
+        .or(default_timeout);
+    spec.env = env;
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lines_skips_comments() {
+        let text = "# setup\ncargo build\n\n  cargo test -p core  \n#cargo test -p cli\n";
+        assert_eq!(
+            parse_lines(text).collect::<Vec<_>>(),
+            ["cargo build", "cargo test -p core"]
+        );
+    }
+
+    #[test]
+    fn test_stdin_items() {
+        let items: Vec<Item> = serde_json::from_str(
+            r#"["make lint", {"command": "make test", "name": "test", "timeout": "5m", "env": {"CI": "1"}}]"#,
+        )
+        .unwrap();
+        let specs = items
+            .into_iter()
+            .map(|item| to_spec(item, Some(60)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(specs[0].command, "make lint");
+        assert_eq!(specs[0].timeout_secs, Some(60));
+        assert_eq!(specs[1].name.as_deref(), Some("test"));
+        assert_eq!(specs[1].timeout_secs, Some(300));
+        assert_eq!(specs[1].env["CI"], "1");
+
+        assert!(serde_json::from_str::<Vec<Item>>(r#"[{"name": "x"}]"#).is_err());
+    }
+}
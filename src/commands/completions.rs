@@ -1,38 +1,37 @@
 use anyhow::{Result, bail};
-use clap::CommandFactory;
+use clap::Command;
 use clap_complete::Shell;
 use std::fs;
 use std::path::Path;
 
-use crate::Cli;
-
-pub fn execute(shell: Shell, install: bool) -> Result<()> {
+/// `cli` is the `jb` command definition, which lives with the binary
+pub fn execute(mut cli: Command, shell: Shell, install: bool) -> Result<()> {
     if install {
-        install_completions(shell)
+        install_completions(cli, shell)
     } else {
-        clap_complete::generate(shell, &mut Cli::command(), "jb", &mut std::io::stdout());
+        clap_complete::generate(shell, &mut cli, "jb", &mut std::io::stdout());
         Ok(())
     }
 }
 
-fn install_completions(shell: Shell) -> Result<()> {
+fn install_completions(mut cli: Command, shell: Shell) -> Result<()> {
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("could not find home directory"))?;
 
     let (path, content) = match shell {
         Shell::Zsh => {
             let dir = home.join(".zsh/completions");
             fs::create_dir_all(&dir)?;
-            (dir.join("_jb"), generate_completions(shell))
+            (dir.join("_jb"), generate_completions(&mut cli, shell))
         }
         Shell::Bash => {
             let dir = home.join(".local/share/bash-completion/completions");
             fs::create_dir_all(&dir)?;
-            (dir.join("jb"), generate_completions(shell))
+            (dir.join("jb"), generate_completions(&mut cli, shell))
         }
         Shell::Fish => {
             let dir = home.join(".config/fish/completions");
             fs::create_dir_all(&dir)?;
-            (dir.join("jb.fish"), generate_completions(shell))
+            (dir.join("jb.fish"), generate_completions(&mut cli, shell))
         }
         _ => bail!("unsupported shell for --install: {shell}"),
     };
@@ -44,9 +43,9 @@ fn install_completions(shell: Shell) -> Result<()> {
     Ok(())
 }
 
-fn generate_completions(shell: Shell) -> Vec<u8> {
+fn generate_completions(cli: &mut Command, shell: Shell) -> Vec<u8> {
     let mut buf = Vec::new();
-    clap_complete::generate(shell, &mut Cli::command(), "jb", &mut buf);
+    clap_complete::generate(shell, cli, "jb", &mut buf);
     buf
 }
 
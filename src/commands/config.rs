@@ -0,0 +1,75 @@
+use crate::core::config::{KEYS, display_value};
+use crate::core::{Config, Paths};
+use anyhow::Result;
+
+/// Keys read by the daemon rather than the CLI
+fn is_daemon_key(key: &str) -> bool {
+    ["run.", "logs.", "retention.auto", "concurrency.", "stop."]
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+}
+
+pub fn show(json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let config = Config::load(&paths)?;
+
+    if json {
+        println!("{}", serde_json::to_string(&config)?);
+        return Ok(());
+    }
+
+    let source = if paths.config_file().exists() {
+        paths.config_file().display().to_string()
+    } else {
+        format!("{} (not created, defaults)", paths.config_file().display())
+    };
+    println!("# {source}");
+    print!("{}", toml::to_string(&config)?);
+    Ok(())
+}
+
+pub fn get(key: &str, json: bool) -> Result<()> {
+    let config = Config::load(&Paths::new())?;
+    let value = config.get(key)?;
+
+    if json {
+        println!("{}", serde_json::json!({ "key": key, "value": value }));
+    } else if let Some(value) = value {
+        println!("{}", display_value(&value));
+    }
+    Ok(())
+}
+
+pub fn set(key: &str, value: &str, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let config = Config::set(&paths, key, value)?;
+    let value = config.get(key)?;
+
+    if json {
+        println!("{}", serde_json::json!({ "key": key, "value": value }));
+    } else {
+        println!(
+            "{key} = {}",
+            value.as_ref().map_or_else(String::new, display_value)
+        );
+        if is_daemon_key(key) && paths.socket().exists() {
+            eprintln!("Run `jb daemon reload` to apply it to the running daemon");
+        }
+    }
+    Ok(())
+}
+
+pub fn keys(json: bool) -> Result<()> {
+    if json {
+        let keys: Vec<_> = KEYS
+            .iter()
+            .map(|(key, about)| serde_json::json!({ "key": key, "description": about }))
+            .collect();
+        println!("{}", serde_json::to_string(&keys)?);
+    } else {
+        for (key, about) in KEYS {
+            println!("{key:<28} {about}");
+        }
+    }
+    Ok(())
+}
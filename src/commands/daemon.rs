@@ -1,5 +1,77 @@
-use anyhow::Result;
+use crate::client::{self, DaemonClient, Probe};
+use crate::core::{Paths, UserError};
+use anyhow::{Context, Result, bail};
 
 pub async fn execute() -> Result<()> {
     crate::daemon::run().await
 }
+
+/// Ask the running daemon to re-read its config.
+pub async fn reload(json: bool) -> Result<()> {
+    let client = DaemonClient::connect().await?;
+    let changes = client.reload().await.context("Reload failed")?;
+    if json {
+        println!("{}", serde_json::json!({ "changes": changes }));
+    } else if changes.is_empty() {
+        println!("Config reloaded, no changes");
+    } else {
+        for change in changes {
+            println!("{change}");
+        }
+    }
+    Ok(())
+}
+
+/// Replace the running daemon with a fresh one (started from the current binary).
+///
+/// With `keep_jobs` the old daemon exits without touching its jobs and the new
+/// one adopts them; otherwise they are interrupted as on any shutdown.
+pub async fn restart(keep_jobs: bool, json: bool) -> Result<()> {
+    match DaemonClient::probe().await? {
+        Probe::NotRunning => {}
+        Probe::Ready(client) => {
+            if keep_jobs && !client.supports("handoff") {
+                bail!(UserError::new(format!(
+                    "The running daemon (jb {}) can't hand off its jobs; \
+                     use `jb daemon restart` without --keep-jobs",
+                    client.daemon_version()
+                )));
+            }
+            let old_pid = client.ping().await?.pid;
+            if keep_jobs {
+                client.handoff().await
+            } else {
+                client.shutdown().await
+            }
+            .context("Restart failed")?;
+            client::wait_for_exit(old_pid).await?;
+        }
+        // A daemon on another protocol version can only be signalled
+        Probe::Outdated { .. } | Probe::Newer { .. } => {
+            if keep_jobs {
+                bail!(UserError::new(
+                    "The running daemon is from a different jb and can't hand off its jobs; \
+                     use `jb daemon restart` without --keep-jobs"
+                ));
+            }
+            client::stop_by_pid(&Paths::new()).await?;
+        }
+    }
+
+    let client = DaemonClient::connect_or_start().await?;
+    let client::DaemonInfo {
+        pid, running_jobs, ..
+    } = client.ping().await?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "pid": pid, "running_jobs": running_jobs })
+        );
+    } else if keep_jobs {
+        println!("Daemon restarted (PID {pid}), {running_jobs} running jobs adopted");
+    } else {
+        println!("Daemon restarted (PID {pid})");
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::core::{Database, Event, EventKind, Paths};
+use anyhow::Result;
+use std::io::Write;
+use std::time::Duration;
+
+pub async fn execute(id: Option<&str>, limit: usize, follow: bool, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    let job_id = id.map(|id| db.resolve(id)).transpose()?.map(|job| job.id);
+
+    let mut last_id = 0;
+    let mut finished = false;
+    for event in db.events(job_id.as_deref(), 0, Some(limit))? {
+        print_event(&event, json)?;
+        last_id = event.id;
+        finished |= event.kind == EventKind::Finished;
+    }
+
+    // Following a single job ends with its finished event
+    while follow && !(job_id.is_some() && finished) {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        for event in db.events(job_id.as_deref(), last_id, None)? {
+            print_event(&event, json)?;
+            last_id = event.id;
+            finished |= event.kind == EventKind::Finished;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_event(event: &Event, json: bool) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    if json {
+        writeln!(out, "{}", serde_json::to_string(event)?)?;
+    } else {
+        writeln!(
+            out,
+            "{}  {}  {:<9}  {}",
+            event.created_at.format("%Y-%m-%d %H:%M:%S"),
+            event.job_id,
+            event.kind.as_str(),
+            event.detail.as_deref().unwrap_or("")
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use crate::client::DaemonClient;
+use crate::commands::list::format_status;
+use crate::commands::run;
+use crate::core::ipc::{FlowSpec, RunSpec, StepSpec};
+use crate::core::{
+    Database, EventKind, Group, Job, Paths, Pipeline, RunWhen, Status, UserError, detect_project,
+    parse_duration,
+};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tokio::task::JoinSet;
+
+/// Start the steps of the pipeline in `file` as a group, printing its ID.
+/// With `wait`, wait for every step and show how the flow went.
+pub async fn run(
+    file: &Path,
+    name: Option<String>,
+    priority: Option<String>,
+    wait: bool,
+    json: bool,
+) -> Result<()> {
+    let pipeline = Pipeline::load(file).map_err(|e| UserError::new(format!("{e:#}")))?;
+    let dir = std::fs::canonicalize(file)?
+        .parent()
+        .map_or_else(|| PathBuf::from("/"), Path::to_path_buf);
+    let name = name
+        .or_else(|| pipeline.name.clone())
+        .or_else(|| file.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "flow".to_string());
+    let mut spec = flow_spec(&pipeline, &name, &dir)?;
+    let priority = run::priority_arg(priority)?;
+    for step in &mut spec.steps {
+        step.job.priority = priority;
+    }
+
+    let client = DaemonClient::connect_or_start().await?;
+    let (group, jobs) = client.flow(spec).await?;
+
+    if !wait {
+        if json {
+            println!("{}", serde_json::json!({ "group": group, "jobs": jobs }));
+        } else {
+            println!("{}", group.id);
+            for job in &jobs {
+                eprintln!(
+                    "  {}  {}",
+                    job.short_id(),
+                    step_name(&group, job).unwrap_or_default()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    if !json {
+        eprintln!(
+            "Started flow {name} ({}) with {} steps",
+            group.id,
+            jobs.len()
+        );
+    }
+    let mut waits = JoinSet::new();
+    for job in &jobs {
+        let (client, id) = (client.clone(), job.id.clone());
+        waits.spawn(async move { client.wait(&id, None).await });
+    }
+    while let Some(joined) = waits.join_next().await {
+        joined??;
+    }
+
+    let steps = status(&group.id, json)?;
+    if failed(&steps) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Whether a step that ran didn't complete, e.g. failed or timed out. Steps
+/// skipped by their `when` condition don't count.
+fn failed(steps: &[StepState]) -> bool {
+    steps
+        .iter()
+        .any(|step| !step.skipped && step.status != Status::Completed)
+}
+
+/// The steps of `pipeline` as jobs, in an order where each follows the steps
+/// it needs.
+fn flow_spec(pipeline: &Pipeline, name: &str, dir: &Path) -> Result<FlowSpec> {
+    let project = detect_project(&dir.to_path_buf());
+    let mut steps = Vec::new();
+    for step_name in pipeline.order()? {
+        let step = &pipeline.steps[&step_name];
+        let cwd = step
+            .cwd
+            .as_ref()
+            .map_or_else(|| dir.to_path_buf(), |cwd| dir.join(cwd));
+        let mut env = pipeline.env.clone();
+        env.extend(step.env.clone());
+        let job = RunSpec {
+            command: step.command.clone(),
+            cwd: cwd.to_string_lossy().to_string(),
+            project: project.to_string_lossy().to_string(),
+            timeout_secs: step.timeout.as_deref().map(parse_duration).transpose()?,
+            artifacts: step.artifacts.clone(),
+            env,
+            concurrency_group: step.group.clone(),
+            run_when: step.when,
+            ..RunSpec::default()
+        };
+        job.validate()?;
+        steps.push(StepSpec {
+            needs: pipeline.needs(&step_name),
+            artifacts_from: pipeline
+                .ancestors(&step_name)
+                .into_iter()
+                .filter(|dep| !pipeline.steps[dep].artifacts.is_empty())
+                .collect(),
+            name: step_name,
+            job,
+        });
+    }
+    Ok(FlowSpec {
+        name: name.to_string(),
+        steps,
+    })
+}
+
+/// A flow's step as `jb flow status` shows it
+#[derive(Debug, Serialize)]
+pub struct StepState {
+    pub step: String,
+    /// 1 for steps that need nothing, else one more than the deepest step needed
+    pub level: usize,
+    pub status: Status,
+    /// Not run because of how the steps it needs finished
+    pub skipped: bool,
+    pub exit_code: Option<i32>,
+    pub needs: Vec<String>,
+    pub when: RunWhen,
+    pub job_id: String,
+}
+
+/// Show a flow's steps level by level with what each needs, and return them.
+pub fn status(flow: &str, json: bool) -> Result<Vec<StepState>> {
+    let db = Database::open(&Paths::new())?;
+    db.recover_orphans();
+    let group = db.resolve_group(flow)?;
+    let jobs = db.group_jobs(&group.id)?;
+    let steps = step_states(&db, &group, &jobs)?;
+
+    if json {
+        println!("{}", serde_json::json!({ "group": group, "steps": steps }));
+        return Ok(steps);
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for step in &steps {
+        *counts.entry(state_label(step)).or_default() += 1;
+    }
+    let summary: Vec<String> = counts.iter().map(|(s, n)| format!("{n} {s}")).collect();
+    println!(
+        "Flow {}: {}\n",
+        match &group.name {
+            Some(name) => format!("{name} ({})", group.id),
+            None => group.id.clone(),
+        },
+        summary.join(", ")
+    );
+
+    let width = steps
+        .iter()
+        .map(|s| s.step.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "{:<3} {:<width$}  {:<12} {:<6} {:<10} NEEDS",
+        "", "STEP", "STATUS", "EXIT", "JOB"
+    );
+    let mut previous_level = 0;
+    for step in &steps {
+        let level = if step.level == previous_level {
+            String::new()
+        } else {
+            step.level.to_string()
+        };
+        previous_level = step.level;
+        let status = if step.skipped {
+            format!("{:<12}", "skipped").dimmed().to_string()
+        } else {
+            format_status(step.status)
+        };
+        let exit = step
+            .exit_code
+            .map_or_else(|| "-".to_string(), |c| c.to_string());
+        let mut needs = if step.needs.is_empty() {
+            "-".to_string()
+        } else {
+            step.needs.join(", ")
+        };
+        if step.when != RunWhen::OnSuccess {
+            needs.push_str(&format!(" ({})", step.when));
+        }
+        println!(
+            "{level:<3} {:<width$}  {status} {exit:<6} {:<10} {needs}",
+            step.step, step.job_id,
+        );
+    }
+    Ok(steps)
+}
+
+/// Each job of the group as a step, ordered by level and then creation.
+fn step_states(db: &Database, group: &Group, jobs: &[Job]) -> Result<Vec<StepState>> {
+    let names: HashMap<&str, String> = jobs
+        .iter()
+        .map(|job| {
+            let step = step_name(group, job).unwrap_or_else(|| job.short_id().to_string());
+            (job.id.as_str(), step)
+        })
+        .collect();
+
+    // Jobs are created after the jobs they depend on
+    let mut levels: HashMap<&str, usize> = HashMap::new();
+    let mut steps = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let level = 1 + job
+            .depends_on
+            .iter()
+            .filter_map(|dep| levels.get(dep.as_str()))
+            .max()
+            .unwrap_or(&0);
+        levels.insert(&job.id, level);
+
+        let skipped = job.status == Status::Stopped
+            && db
+                .events(Some(&job.id), 0, None)?
+                .iter()
+                .rev()
+                .find(|e| e.kind == EventKind::Finished)
+                .and_then(|e| e.detail.as_deref())
+                .is_some_and(|detail| detail.starts_with("skipped"));
+        steps.push(StepState {
+            step: names[job.id.as_str()].clone(),
+            level,
+            status: job.status,
+            skipped,
+            exit_code: job.exit_code,
+            needs: job
+                .depends_on
+                .iter()
+                .map(|dep| {
+                    names
+                        .get(dep.as_str())
+                        .cloned()
+                        .unwrap_or_else(|| dep.clone())
+                })
+                .collect(),
+            when: job.run_when,
+            job_id: job.short_id().to_string(),
+        });
+    }
+    steps.sort_by_key(|step| step.level);
+    Ok(steps)
+}
+
+/// The step a flow's job runs: its name without the group's prefix
+fn step_name(group: &Group, job: &Job) -> Option<String> {
+    let name = job.name.as_deref()?;
+    let step = name
+        .strip_prefix(group.id.as_str())
+        .and_then(|rest| rest.strip_prefix('.'))
+        .unwrap_or(name);
+    Some(step.to_string())
+}
+
+fn state_label(step: &StepState) -> &'static str {
+    if step.skipped {
+        "skipped"
+    } else {
+        step.status.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flow_spec_links_steps() {
+        let pipeline = Pipeline::parse(
+            r#"
+            env = { CI = "1" }
+
+            [steps.build]
+            command = "make"
+            artifacts = ["out/*"]
+
+            [steps.test]
+            command = "make test"
+            depends_on = ["build"]
+            env = { CI = "2" }
+            cwd = "tests"
+
+            [steps.report]
+            command = "./report.sh"
+            depends_on = ["test"]
+            when = "always"
+            "#,
+        )
+        .unwrap();
+        let spec = flow_spec(&pipeline, "ci", Path::new("/repo")).unwrap();
+
+        let names: Vec<&str> = spec.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["build", "test", "report"]);
+        let test = &spec.steps[1];
+        assert_eq!(test.needs, ["build"]);
+        assert_eq!(test.artifacts_from, ["build"]);
+        assert_eq!(test.job.name, None);
+        assert_eq!(test.job.cwd, "/repo/tests");
+        assert_eq!(test.job.env["CI"], "2");
+        assert_eq!(spec.steps[2].artifacts_from, ["build"]);
+        assert_eq!(spec.steps[2].job.run_when, RunWhen::Always);
+    }
+
+    #[test]
+    fn test_timed_out_step_fails_flow() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let db = Database::open(&Paths::with_root(tmp.path().to_path_buf())).unwrap();
+        let group = Group::new("gab12".to_string(), PathBuf::from("/repo")).with_name("ci");
+        db.insert_group(&group).unwrap();
+
+        let job = |id: &str, status: Status, needs: &[&str], when: RunWhen| {
+            let mut job = Job::new(
+                id.to_string(),
+                "true".to_string(),
+                PathBuf::from("/repo"),
+                PathBuf::from("/repo"),
+            )
+            .with_name(format!("gab12.{id}"))
+            .with_group("gab12")
+            .with_depends_on(needs.iter().map(ToString::to_string).collect())
+            .with_run_when(when);
+            job.status = status;
+            db.insert(&job).unwrap();
+        };
+        let finished = |id: &str, detail: &str| {
+            db.record_event(id, EventKind::Finished, Some(detail))
+                .unwrap();
+        };
+        job("build", Status::Completed, &[], RunWhen::OnSuccess);
+        job("test", Status::Stopped, &["build"], RunWhen::OnSuccess);
+        finished("test", "timed out");
+        job("publish", Status::Stopped, &["test"], RunWhen::OnSuccess);
+        finished("publish", "skipped: a dependency didn't complete");
+
+        let steps = step_states(&db, &group, &db.group_jobs("gab12").unwrap()).unwrap();
+        assert!(!steps[1].skipped);
+        assert!(steps[2].skipped);
+        assert!(failed(&steps));
+
+        // Skipped steps alone don't fail a flow
+        let mut steps = steps;
+        steps[1].status = Status::Completed;
+        assert!(!failed(&steps));
+    }
+}
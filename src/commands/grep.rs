@@ -0,0 +1,128 @@
+use crate::commands::logs::should_colorize;
+use crate::core::{
+    Database, Job, JobFilter, Paths, Status, detect_project, logfile, parse_duration,
+};
+use anyhow::Result;
+use colored::Colorize;
+use regex::{Regex, RegexBuilder};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+pub fn execute(
+    pattern: &str,
+    ignore_case: bool,
+    project: Option<PathBuf>,
+    name: Option<String>,
+    status: Option<String>,
+    since: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()?;
+
+    let project = project
+        .map(|p| -> Result<PathBuf> { Ok(detect_project(&std::fs::canonicalize(p)?)) })
+        .transpose()?;
+    let since = since
+        .map(|s| -> Result<_> {
+            #[allow(clippy::cast_possible_wrap)] // durations won't exceed i64::MAX
+            let secs = parse_duration(&s)? as i64;
+            Ok(chrono::Utc::now() - chrono::Duration::seconds(secs))
+        })
+        .transpose()?;
+
+    let filter = JobFilter {
+        status: status.map(|s| s.parse::<Status>()).transpose()?,
+        name,
+        project,
+        since,
+        limit: None,
+    };
+
+    let colorize = !json && should_colorize();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut found = false;
+
+    for job in db.find(&filter)? {
+        found |= grep_job(&paths, &job, &regex, colorize, json, &mut out)?;
+    }
+
+    out.flush()?;
+    drop(out);
+
+    if !found {
+        // Match grep: exit 1 when nothing matched
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Stream every segment of one job's log, printing matching lines.
+fn grep_job(
+    paths: &Paths,
+    job: &Job,
+    regex: &Regex,
+    colorize: bool,
+    json: bool,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    let label = match &job.name {
+        Some(name) => format!("{}[{name}]", job.id),
+        None => job.id.clone(),
+    };
+    let mut line_no = 0usize;
+    let mut found = false;
+
+    for segment in logfile::segments(paths, &job.id) {
+        let reader = logfile::open(&segment)?;
+        for line in reader.split(b'\n') {
+            let line = line?;
+            line_no += 1;
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches('\r');
+            if !regex.is_match(text) {
+                continue;
+            }
+            found = true;
+
+            if json {
+                let record = serde_json::json!({
+                    "id": job.id,
+                    "name": job.name,
+                    "line": line_no,
+                    "text": text,
+                });
+                writeln!(out, "{record}")?;
+            } else if colorize {
+                writeln!(
+                    out,
+                    "{}:{}:{}",
+                    label.magenta(),
+                    line_no.to_string().green(),
+                    highlight(regex, text)
+                )?;
+            } else {
+                writeln!(out, "{label}:{line_no}:{text}")?;
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+fn highlight(regex: &Regex, text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in regex.find_iter(text) {
+        result.push_str(&text[last..m.start()]);
+        result.push_str(&m.as_str().red().bold().to_string());
+        last = m.end();
+    }
+    result.push_str(&text[last..]);
+    result
+}
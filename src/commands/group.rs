@@ -0,0 +1,237 @@
+use crate::client::{self, DaemonClient};
+use crate::commands::logs::should_colorize;
+use crate::commands::wait::{self, Targets};
+use crate::core::logfile::LogReader;
+use crate::core::{Database, Job, Paths, Status};
+use anyhow::Result;
+use colored::{Color, Colorize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Show a group's jobs and how many are in each state.
+pub fn status(group: &str, json: bool) -> Result<()> {
+    let db = Database::open(&Paths::new())?;
+    db.recover_orphans();
+    let group = db.resolve_group(group)?;
+    let jobs = db.group_jobs(&group.id)?;
+
+    let mut counts: BTreeMap<Status, usize> = BTreeMap::new();
+    for job in &jobs {
+        *counts.entry(job.status).or_default() += 1;
+    }
+
+    if json {
+        let counts: BTreeMap<&str, usize> = counts.iter().map(|(s, n)| (s.as_str(), *n)).collect();
+        println!(
+            "{}",
+            serde_json::json!({ "group": group, "counts": counts, "jobs": jobs })
+        );
+        return Ok(());
+    }
+
+    match &group.name {
+        Some(name) => println!("Group {name} ({})", group.id),
+        None => println!("Group {}", group.id),
+    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(status, n)| format!("{n} {status}"))
+        .collect();
+    println!("{} jobs: {}\n", jobs.len(), summary.join(", "));
+    wait::print_table(&jobs, false);
+    Ok(())
+}
+
+/// Stop a group's running jobs and cancel its queued ones.
+pub async fn stop(group: &str, force: bool, json: bool) -> Result<()> {
+    let db = Database::open(&Paths::new())?;
+    let group = db.resolve_group(group)?;
+    let mut active: Vec<Job> = db
+        .group_jobs(&group.id)?
+        .into_iter()
+        .filter(|job| !job.status.is_terminal())
+        .collect();
+    // Queued jobs first, so none starts as the running ones stop
+    active.sort_by_key(|job| job.status != Status::Pending);
+
+    let client = DaemonClient::connect_or_start().await?;
+    let mut stopped = Vec::new();
+    for job in active {
+        match client.stop(&job.id, force).await {
+            Ok(()) => stopped.push(job.id),
+            // Finished meanwhile
+            Err(e) if client::is_daemon_error(&e, "not running") => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "group": group, "stopped": stopped })
+        );
+    } else {
+        println!("Stopped {} jobs in {}", stopped.len(), group.label());
+    }
+    Ok(())
+}
+
+/// Wait for a group's jobs, as `jb wait` does for several jobs.
+pub async fn wait(group: String, any: bool, timeout: Option<String>, json: bool) -> Result<()> {
+    let targets = Targets {
+        group: Some(group),
+        ..Targets::default()
+    };
+    wait::execute_many(targets, any, timeout, false, json).await
+}
+
+/// One job's output, read as it grows
+struct Tail {
+    job_id: String,
+    reader: LogReader,
+    /// Start of a line not yet terminated
+    partial: Vec<u8>,
+    done: bool,
+}
+
+/// Print a group's output, each line prefixed by its job. With `follow`, keep
+/// printing lines from all jobs as they are written until every job has exited.
+pub async fn logs(group: &str, follow: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+    let group = db.resolve_group(group)?;
+    let jobs = db.group_jobs(&group.id)?;
+
+    let width = jobs.iter().map(|job| label(job).len()).max().unwrap_or(0);
+    let colorize = should_colorize();
+    let colors = [
+        Color::Cyan,
+        Color::Green,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Blue,
+        Color::Red,
+    ];
+    let prefixes: Vec<String> = jobs
+        .iter()
+        .enumerate()
+        .map(|(i, job)| {
+            let prefix = format!("{:<width$} |", label(job));
+            if colorize {
+                prefix.color(colors[i % colors.len()]).to_string()
+            } else {
+                prefix
+            }
+        })
+        .collect();
+
+    let stdout = std::io::stdout();
+    let mut tails: Vec<Tail> = jobs
+        .iter()
+        .map(|job| Tail {
+            job_id: job.id.clone(),
+            reader: LogReader::new(&paths, &job.id, 0),
+            partial: Vec::new(),
+            done: false,
+        })
+        .collect();
+    loop {
+        for (tail, prefix) in tails.iter_mut().zip(&prefixes) {
+            if tail.done {
+                continue;
+            }
+            // Checked before reading, so output written just before exit is read
+            let exited = !follow
+                || db
+                    .get(&tail.job_id)?
+                    .is_none_or(|job| job.status.is_terminal());
+            read_tail(tail, prefix, &mut stdout.lock())?;
+            if exited {
+                if !tail.partial.is_empty() {
+                    print_line(&mut stdout.lock(), prefix, &tail.partial)?;
+                }
+                tail.done = true;
+            }
+        }
+        if tails.iter().all(|tail| tail.done) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    stdout.lock().flush()?;
+    Ok(())
+}
+
+fn label(job: &Job) -> &str {
+    job.name.as_deref().unwrap_or(&job.id)
+}
+
+/// Largest amount of a job's output read at once
+const READ_CHUNK: u64 = 64 * 1024;
+
+/// Print the complete lines added to a job's log since the last read,
+/// including any rotated away in between.
+fn read_tail(tail: &mut Tail, prefix: &str, out: &mut impl Write) -> Result<()> {
+    loop {
+        let data = tail.reader.read(READ_CHUNK)?;
+        if data.is_empty() {
+            return Ok(());
+        }
+        tail.partial.extend_from_slice(&data);
+        if let Some(end) = tail.partial.iter().rposition(|&b| b == b'\n') {
+            let rest = tail.partial.split_off(end + 1);
+            for line in tail.partial[..end].split(|&b| b == b'\n') {
+                print_line(out, prefix, line)?;
+            }
+            tail.partial = rest;
+        }
+    }
+}
+
+fn print_line(out: &mut impl Write, prefix: &str, line: &[u8]) -> Result<()> {
+    let text = String::from_utf8_lossy(line);
+    writeln!(out, "{prefix} {}", text.trim_end_matches('\r'))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_tail_follows_rotation() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        let append = |text: &str| {
+            let mut log = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(paths.log_file("abc1"))
+                .unwrap();
+            log.write_all(text.as_bytes()).unwrap();
+        };
+        let mut tail = Tail {
+            job_id: "abc1".to_string(),
+            reader: LogReader::new(&paths, "abc1", 0),
+            partial: Vec::new(),
+            done: false,
+        };
+        let mut out = Vec::new();
+
+        append("one\ntw");
+        read_tail(&mut tail, "a |", &mut out).unwrap();
+        // Written and rotated away between reads, then outgrown by the new log
+        append("o\nthree\n");
+        crate::core::logfile::rotate(&paths, "abc1", 2).unwrap();
+        append("a much longer line than before\n");
+        read_tail(&mut tail, "a |", &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a | one\na | two\na | three\na | a much longer line than before\n"
+        );
+    }
+}
@@ -1,9 +1,7 @@
-use crate::core::{Database, Paths, Status};
+use crate::core::{Config, Database, Paths, Status, format_priority};
 use anyhow::Result;
 use colored::Colorize;
 
-const DEFAULT_LIMIT: usize = 10;
-
 pub fn execute(
     status_filter: Option<String>,
     failed: bool,
@@ -26,7 +24,7 @@ pub fn execute(
     let effective_limit = if all {
         None
     } else {
-        Some(limit.unwrap_or(DEFAULT_LIMIT))
+        Some(limit.unwrap_or(Config::load(&paths)?.list.limit))
     };
 
     let jobs = db.list(status, effective_limit)?;
@@ -42,8 +40,8 @@ pub fn execute(
     }
 
     println!(
-        "{:<10} {:<12} {:<6} {:<12} {:<30} STARTED",
-        "ID", "STATUS", "EXIT", "NAME", "COMMAND"
+        "{:<10} {:<12} {:<6} {:<7} {:<12} {:<30} STARTED",
+        "ID", "STATUS", "EXIT", "PRI", "NAME", "COMMAND"
     );
 
     for job in jobs {
@@ -56,12 +54,19 @@ pub fn execute(
             .exit_code
             .map_or_else(|| "-".to_string(), |c| c.to_string());
 
+        let priority = if job.priority == 0 {
+            "-".to_string()
+        } else {
+            format_priority(job.priority)
+        };
+
         let status_colored = format_status(job.status);
         println!(
-            "{:<10} {} {:<6} {:<12} {:<30} {}",
+            "{:<10} {} {:<6} {:<7} {:<12} {:<30} {}",
             job.short_id(),
             status_colored,
             exit,
+            truncate(&priority, 7),
             truncate(name, 10),
             cmd,
             started
@@ -71,7 +76,7 @@ pub fn execute(
     Ok(())
 }
 
-fn format_status(status: Status) -> String {
+pub fn format_status(status: Status) -> String {
     // Pad to 12 chars before colorizing to preserve alignment
     let s = format!("{:<12}", status.as_str());
     match status {
@@ -84,7 +89,7 @@ fn format_status(status: Status) -> String {
     }
 }
 
-fn truncate(s: &str, max: usize) -> String {
+pub fn truncate(s: &str, max: usize) -> String {
     let char_count = s.chars().count();
     if char_count > max {
         let truncated: String = s.chars().take(max.saturating_sub(3)).collect();
@@ -94,7 +99,7 @@ fn truncate(s: &str, max: usize) -> String {
     }
 }
 
-fn format_relative_time(t: chrono::DateTime<chrono::Utc>) -> String {
+pub fn format_relative_time(t: chrono::DateTime<chrono::Utc>) -> String {
     let now = chrono::Utc::now();
     let diff = now.signed_duration_since(t);
 
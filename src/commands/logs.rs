@@ -1,8 +1,10 @@
-use crate::core::{Database, Paths};
-use anyhow::Result;
+use crate::core::config::ColorMode;
+use crate::core::{Config, Database, Job, Paths, UserError, logfile};
+use anyhow::{Result, bail};
 use colored::Colorize;
+use std::collections::VecDeque;
 use std::io::{BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -10,11 +12,33 @@ use std::time::Duration;
 
 type WriterFn = Box<dyn FnOnce(&mut dyn Write) -> Result<()>>;
 
-fn should_colorize() -> bool {
-    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+pub fn should_colorize() -> bool {
+    match Config::load(&Paths::new()).map(|c| c.ui.color) {
+        Ok(ColorMode::Always) => true,
+        Ok(ColorMode::Never) => false,
+        _ => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// Where an incremental read starts
+pub enum Cursor {
+    /// Token returned by a previous `--since-cursor` read
+    Token(String),
+    /// Offset stored in the DB for this caller (`--new`)
+    Caller(String),
 }
 
-pub fn execute(id: &str, tail: Option<usize>, follow: bool, pager: bool) -> Result<()> {
+/// Most bytes returned by one cursor read; the next cursor continues from there
+const MAX_CURSOR_READ: u64 = 1024 * 1024;
+
+pub fn execute(
+    id: &str,
+    tail: Option<usize>,
+    follow: bool,
+    pager: bool,
+    cursor: Option<Cursor>,
+    json: bool,
+) -> Result<()> {
     let paths = Paths::new();
     let db = Database::open(&paths)?;
 
@@ -27,6 +51,10 @@ pub fn execute(id: &str, tail: Option<usize>, follow: bool, pager: bool) -> Resu
     let job = db.resolve(id)?;
     let log_path = paths.log_file(&job.id);
 
+    if let Some(cursor) = cursor {
+        return read_from_cursor(&db, &paths, &job, cursor, json);
+    }
+
     if follow {
         return follow_logs(&db, &paths, &job.id, &log_path);
     }
@@ -43,41 +71,185 @@ pub fn execute(id: &str, tail: Option<usize>, follow: bool, pager: bool) -> Resu
     if let Some(n) = tail {
         // Efficient tail: read last N lines without loading entire file
         if use_pager {
-            output_with_pager(|| tail_lines_to_writer(&log_path, n, colorize))?;
+            output_with_pager(|| tail_lines_to_writer(&paths, &job.id, n, colorize))?;
         } else {
             let stdout = std::io::stdout();
             let mut writer = stdout.lock();
-            tail_lines_to_writer(&log_path, n, colorize)(&mut writer)?;
+            tail_lines_to_writer(&paths, &job.id, n, colorize)(&mut writer)?;
         }
     } else {
-        // Stream entire file
+        // Stream the whole log, including segments rotated out by the daemon
+        let segments = logfile::segments(&paths, &job.id);
         if use_pager {
-            output_with_pager(|| stream_file_to_writer(&log_path, colorize))?;
+            output_with_pager(|| stream_files_to_writer(&segments, colorize))?;
         } else {
             let stdout = std::io::stdout();
             let mut writer = stdout.lock();
-            stream_file_to_writer(&log_path, colorize)(&mut writer)?;
+            stream_files_to_writer(&segments, colorize)(&mut writer)?;
         }
     }
 
     Ok(())
 }
 
-fn colorize_line(line: &str) -> String {
+/// Severity guessed from a log line's keywords
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Plain,
+}
+
+pub fn classify_line(line: &str) -> LineLevel {
     let lower = line.to_lowercase();
     if lower.contains("error") || lower.contains("fatal") || lower.contains("panic") {
-        line.red().to_string()
+        LineLevel::Error
     } else if lower.contains("warn") {
-        line.yellow().to_string()
+        LineLevel::Warn
     } else if lower.contains("info") {
-        line.blue().to_string()
+        LineLevel::Info
     } else if lower.contains("debug") || lower.contains("trace") {
-        line.dimmed().to_string()
+        LineLevel::Debug
     } else {
-        line.to_string()
+        LineLevel::Plain
+    }
+}
+
+pub fn colorize_line(line: &str) -> String {
+    match classify_line(line) {
+        LineLevel::Error => line.red().to_string(),
+        LineLevel::Warn => line.yellow().to_string(),
+        LineLevel::Info => line.blue().to_string(),
+        LineLevel::Debug => line.dimmed().to_string(),
+        LineLevel::Plain => line.to_string(),
+    }
+}
+
+/// Print log output written since a cursor, then the cursor for the next call.
+fn read_from_cursor(
+    db: &Database,
+    paths: &Paths,
+    job: &Job,
+    cursor: Cursor,
+    json: bool,
+) -> Result<()> {
+    let chunk = read_cursor(db, paths, job, &cursor)?;
+
+    if json {
+        println!("{}", cursor_json(job, &chunk));
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&chunk.data)?;
+    stdout.flush()?;
+    if matches!(cursor, Cursor::Token(_)) {
+        eprintln!("cursor: {}", chunk.next);
+    }
+    Ok(())
+}
+
+/// Output written after `cursor`, advancing the stored cursor of a caller.
+pub fn read_cursor(db: &Database, paths: &Paths, job: &Job, cursor: &Cursor) -> Result<Chunk> {
+    let start = match cursor {
+        Cursor::Token(token) => parse_cursor(token)?,
+        Cursor::Caller(caller) => db.get_log_cursor(&job.id, caller)?.unwrap_or(0),
+    };
+
+    // Once the job is done no more output is coming, so return partial lines too
+    let chunk = read_since(
+        paths,
+        &job.id,
+        start,
+        job.status.is_terminal(),
+        MAX_CURSOR_READ,
+    )?;
+
+    if let Cursor::Caller(caller) = cursor {
+        db.set_log_cursor(&job.id, caller, chunk.next)?;
+    }
+    Ok(chunk)
+}
+
+/// The `--json` form of a cursor read
+#[must_use]
+pub fn cursor_json(job: &Job, chunk: &Chunk) -> serde_json::Value {
+    serde_json::json!({
+        "id": job.id,
+        "status": job.status,
+        "cursor": chunk.next.to_string(),
+        "output": String::from_utf8_lossy(&chunk.data),
+        "more": chunk.more,
+        "done": job.status.is_terminal() && !chunk.more,
+    })
+}
+
+/// The last `n` lines of a job's output, uncolored
+pub fn tail(paths: &Paths, job_id: &str, n: usize) -> Result<String> {
+    let mut out = Vec::new();
+    tail_output_to_writer(paths, job_id, n, false, &mut out)?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn parse_cursor(token: &str) -> Result<u64> {
+    match token.trim().parse() {
+        Ok(offset) => Ok(offset),
+        Err(_) => bail!(UserError::new(format!("Invalid log cursor '{token}'"))),
     }
 }
 
+/// Bytes read by [`read_since`] and the offset to resume from.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Chunk {
+    pub data: Vec<u8>,
+    pub next: u64,
+    /// More output was available than `max` allowed
+    pub more: bool,
+}
+
+/// Read up to `max` bytes of the job's output after `offset`, a position
+/// that counts output since rotated out of its log (see [`logfile`]).
+/// Unless `partial_lines`, a trailing incomplete line is left for the next read.
+/// An offset past the end (log replaced) restarts from the oldest output kept.
+fn read_since(
+    paths: &Paths,
+    job_id: &str,
+    offset: u64,
+    partial_lines: bool,
+    max: u64,
+) -> Result<Chunk> {
+    let mut reader = logfile::LogReader::new(paths, job_id, offset);
+    // One byte past `max` tells whether there is more
+    let mut data = Vec::new();
+    while data.len() as u64 <= max {
+        let bytes = reader.read(max + 1 - data.len() as u64)?;
+        if bytes.is_empty() {
+            break;
+        }
+        data.extend(bytes);
+    }
+    let start = reader.offset() - data.len() as u64;
+    let more = data.len() as u64 > max;
+    data.truncate(usize::try_from(max).unwrap_or(usize::MAX));
+
+    if (more || !partial_lines)
+        && let Some(last_newline) = data.iter().rposition(|&b| b == b'\n')
+    {
+        data.truncate(last_newline + 1);
+    } else if !more && !partial_lines {
+        // Only an incomplete line so far
+        data.clear();
+    }
+
+    Ok(Chunk {
+        next: start + data.len() as u64,
+        data,
+        more,
+    })
+}
+
 fn output_with_pager<F>(content_fn: F) -> Result<()>
 where
     F: FnOnce() -> Box<dyn FnOnce(&mut dyn Write) -> Result<()>>,
@@ -111,26 +283,70 @@ where
     Ok(())
 }
 
-fn stream_file_to_writer(path: &Path, colorize: bool) -> WriterFn {
-    let path = path.to_path_buf();
+fn stream_files_to_writer(paths: &[PathBuf], colorize: bool) -> WriterFn {
+    let paths = paths.to_vec();
     Box::new(move |writer: &mut dyn Write| {
-        let file = std::fs::File::open(&path)?;
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            let line = line?;
-            if colorize {
-                writeln!(writer, "{}", colorize_line(&line))?;
-            } else {
-                writeln!(writer, "{}", line)?;
+        for path in &paths {
+            for line in logfile::open(path)?.lines() {
+                let line = line?;
+                if colorize {
+                    writeln!(writer, "{}", colorize_line(&line))?;
+                } else {
+                    writeln!(writer, "{}", line)?;
+                }
             }
         }
         Ok(())
     })
 }
 
-fn tail_lines_to_writer(path: &Path, n: usize, colorize: bool) -> WriterFn {
-    let path = path.to_path_buf();
-    Box::new(move |writer: &mut dyn Write| tail_last_n_lines_to_writer(&path, n, colorize, writer))
+fn tail_lines_to_writer(paths: &Paths, job_id: &str, n: usize, colorize: bool) -> WriterFn {
+    let (paths, job_id) = (paths.clone(), job_id.to_string());
+    Box::new(move |writer: &mut dyn Write| {
+        tail_output_to_writer(&paths, &job_id, n, colorize, writer)
+    })
+}
+
+/// Write the last `n` lines of a job's output, going back into the segments
+/// rotated out of its log if the log itself has fewer.
+fn tail_output_to_writer(
+    paths: &Paths,
+    job_id: &str,
+    n: usize,
+    colorize: bool,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let log_path = paths.log_file(job_id);
+    let mut recent = Vec::new();
+    if log_path.exists() {
+        tail_last_n_lines_to_writer(&log_path, n, false, &mut recent)?;
+    }
+    let recent = String::from_utf8_lossy(&recent);
+
+    let missing = n.saturating_sub(recent.lines().count());
+    let mut earlier: VecDeque<String> = VecDeque::with_capacity(missing);
+    if missing > 0 {
+        for segment in logfile::segments(paths, job_id) {
+            if segment == log_path {
+                continue;
+            }
+            for line in logfile::open(&segment)?.split(b'\n') {
+                if earlier.len() == missing {
+                    earlier.pop_front();
+                }
+                earlier.push_back(String::from_utf8_lossy(&line?).into_owned());
+            }
+        }
+    }
+
+    for line in earlier.iter().map(String::as_str).chain(recent.lines()) {
+        if colorize {
+            writeln!(writer, "{}", colorize_line(line))?;
+        } else {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    Ok(())
 }
 
 /// Read last N lines from a file without loading the entire file into memory.
@@ -203,7 +419,10 @@ fn tail_last_n_lines_to_writer(
     Ok(())
 }
 
-fn follow_logs(db: &Database, _paths: &Paths, job_id: &str, log_path: &Path) -> Result<()> {
+/// Most bytes printed at a time by `--follow`
+const FOLLOW_CHUNK: u64 = 64 * 1024;
+
+fn follow_logs(db: &Database, paths: &Paths, job_id: &str, log_path: &Path) -> Result<()> {
     let colorize = should_colorize();
 
     // Set up Ctrl+C handler - on interrupt, just exit cleanly (job continues)
@@ -236,77 +455,57 @@ fn follow_logs(db: &Database, _paths: &Paths, job_id: &str, log_path: &Path) ->
         std::thread::sleep(Duration::from_millis(100));
     }
 
-    let mut file = std::fs::File::open(log_path)?;
-    let mut position = 0u64;
-    let mut buf = vec![0u8; 8192];
+    let mut reader = logfile::LogReader::new(paths, job_id, 0);
     let mut line_buf = String::new();
+    let mut print = |data: &[u8]| -> Result<()> {
+        if colorize {
+            // Buffer partial lines for colorization
+            for c in String::from_utf8_lossy(data).chars() {
+                if c == '\n' {
+                    println!("{}", colorize_line(&line_buf));
+                    line_buf.clear();
+                } else {
+                    line_buf.push(c);
+                }
+            }
+        } else {
+            std::io::stdout().write_all(data)?;
+        }
+        Ok(())
+    };
 
     loop {
         if interrupted.load(Ordering::SeqCst) {
             return Ok(());
         }
 
-        // Read new content from current position
-        file.seek(SeekFrom::Start(position))?;
-        let bytes_read = file.read(&mut buf)?;
-        if bytes_read > 0 {
-            let chunk = String::from_utf8_lossy(&buf[..bytes_read]);
-            if colorize {
-                // Buffer partial lines for colorization
-                for c in chunk.chars() {
-                    if c == '\n' {
-                        println!("{}", colorize_line(&line_buf));
-                        line_buf.clear();
-                    } else {
-                        line_buf.push(c);
-                    }
-                }
-            } else {
-                std::io::stdout().write_all(&buf[..bytes_read])?;
+        // Checked before reading so output written just before exit is shown
+        let Some(job) = db.get(job_id)? else {
+            anyhow::bail!("Job disappeared from database");
+        };
+
+        // Read everything new, following the log across rotations
+        loop {
+            let data = reader.read(FOLLOW_CHUNK)?;
+            if data.is_empty() {
+                break;
             }
-            std::io::stdout().flush()?;
-            position += bytes_read as u64;
+            print(&data)?;
         }
+        std::io::stdout().flush()?;
 
-        // Check job status
-        if let Some(job) = db.get(job_id)? {
-            if job.status.is_terminal() {
-                // Final read to catch any remaining output
-                loop {
-                    file.seek(SeekFrom::Start(position))?;
-                    let bytes_read = file.read(&mut buf)?;
-                    if bytes_read == 0 {
-                        break;
-                    }
-                    let chunk = String::from_utf8_lossy(&buf[..bytes_read]);
-                    if colorize {
-                        for c in chunk.chars() {
-                            if c == '\n' {
-                                println!("{}", colorize_line(&line_buf));
-                                line_buf.clear();
-                            } else {
-                                line_buf.push(c);
-                            }
-                        }
-                    } else {
-                        std::io::stdout().write_all(&buf[..bytes_read])?;
-                    }
-                    position += bytes_read as u64;
-                }
-                // Print any remaining partial line
-                if colorize && !line_buf.is_empty() {
-                    println!("{}", colorize_line(&line_buf));
-                }
-                std::io::stdout().flush()?;
+        if job.status.is_terminal() {
+            // Print any remaining partial line
+            if colorize && !line_buf.is_empty() {
+                println!("{}", colorize_line(&line_buf));
+            }
+            std::io::stdout().flush()?;
 
-                // Exit with job's exit code
-                if let Some(code) = job.exit_code {
-                    std::process::exit(code);
-                }
-                return Ok(());
+            // Exit with job's exit code
+            if let Some(code) = job.exit_code {
+                std::process::exit(code);
             }
-        } else {
-            anyhow::bail!("Job disappeared from database");
+            return Ok(());
         }
 
         // Small sleep before next poll
@@ -430,6 +629,98 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_classify_line() {
+        assert_eq!(classify_line("error[E0308]: mismatched"), LineLevel::Error);
+        assert_eq!(classify_line("warning: unused"), LineLevel::Warn);
+        assert_eq!(classify_line("INFO starting"), LineLevel::Info);
+        assert_eq!(classify_line("trace: x"), LineLevel::Debug);
+        assert_eq!(classify_line("hello"), LineLevel::Plain);
+    }
+
+    fn write_log(content: &str) -> (tempfile::TempDir, Paths) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(dir.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        std::fs::write(paths.log_file("abc1"), content).unwrap();
+        (dir, paths)
+    }
+
+    #[test]
+    fn test_read_since_complete_lines_only() {
+        let (_dir, paths) = write_log("one\ntwo\nthr");
+        let chunk = read_since(&paths, "abc1", 0, false, 1024).unwrap();
+        assert_eq!(chunk.data, b"one\ntwo\n");
+        assert_eq!(chunk.next, 8);
+        assert!(!chunk.more);
+
+        // Nothing new until the partial line is finished
+        let chunk = read_since(&paths, "abc1", 8, false, 1024).unwrap();
+        assert!(chunk.data.is_empty());
+        assert_eq!(chunk.next, 8);
+    }
+
+    #[test]
+    fn test_read_since_partial_lines_when_done() {
+        let (_dir, paths) = write_log("one\ntwo\nthr");
+        let chunk = read_since(&paths, "abc1", 4, true, 1024).unwrap();
+        assert_eq!(chunk.data, b"two\nthr");
+        assert_eq!(chunk.next, 11);
+    }
+
+    #[test]
+    fn test_read_since_respects_max() {
+        let (_dir, paths) = write_log("aaaa\nbbbb\ncccc\n");
+        let chunk = read_since(&paths, "abc1", 0, true, 12).unwrap();
+        assert_eq!(chunk.data, b"aaaa\nbbbb\n");
+        assert!(chunk.more);
+
+        let chunk = read_since(&paths, "abc1", chunk.next, true, 12).unwrap();
+        assert_eq!(chunk.data, b"cccc\n");
+        assert!(!chunk.more);
+    }
+
+    #[test]
+    fn test_read_since_offset_past_end_restarts() {
+        let (_dir, paths) = write_log("new\n");
+        let chunk = read_since(&paths, "abc1", 500, false, 1024).unwrap();
+        assert_eq!(chunk.data, b"new\n");
+        assert_eq!(chunk.next, 4);
+    }
+
+    #[test]
+    fn test_read_since_across_rotation() {
+        let (_dir, paths) = write_log("one\ntwo\n");
+        let chunk = read_since(&paths, "abc1", 0, false, 1024).unwrap();
+        assert_eq!(chunk.next, 8);
+
+        std::fs::write(paths.log_file("abc1"), "one\ntwo\nthree\n").unwrap();
+        logfile::rotate(&paths, "abc1", 3).unwrap();
+        std::fs::write(paths.log_file("abc1"), "four\n").unwrap();
+
+        // The rest of the rotated segment, then the new log
+        let chunk = read_since(&paths, "abc1", chunk.next, false, 1024).unwrap();
+        assert_eq!(chunk.data, b"three\nfour\n");
+        assert_eq!(chunk.next, 19);
+    }
+
+    #[test]
+    fn test_tail_reaches_into_rotated_segments() {
+        let (_dir, paths) = write_log("one\ntwo\nthree\n");
+        logfile::rotate(&paths, "abc1", 3).unwrap();
+        std::fs::write(paths.log_file("abc1"), "four\n").unwrap();
+
+        assert_eq!(tail(&paths, "abc1", 1).unwrap(), "four\n");
+        assert_eq!(tail(&paths, "abc1", 3).unwrap(), "two\nthree\nfour\n");
+        assert_eq!(tail(&paths, "abc1", 9).unwrap(), "one\ntwo\nthree\nfour\n");
+    }
+
+    #[test]
+    fn test_parse_cursor() {
+        assert_eq!(parse_cursor("42").unwrap(), 42);
+        assert!(parse_cursor("abc").is_err());
+    }
+
     #[test]
     fn test_colorize_line_normal() {
         // Without color override, should return unchanged
@@ -0,0 +1,320 @@
+use crate::client::DaemonClient;
+use crate::commands::run;
+use crate::core::ipc::{BatchSpec, RunSpec};
+use crate::core::{Job, Status, UserError, parse_duration};
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use tokio::task::JoinSet;
+
+/// A `--var name=a,b,c` option: a placeholder and the values it takes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Var {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl Var {
+    pub fn parse(s: &str) -> Result<Self> {
+        let Some((name, values)) = s.split_once('=') else {
+            anyhow::bail!(UserError::new(format!(
+                "--var {s}: expected NAME=VALUE[,VALUE...]"
+            )));
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            anyhow::bail!(UserError::new(format!(
+                "--var {s}: the name may only contain letters, digits and '_'"
+            )));
+        }
+        let values: Vec<String> = values
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+        if values.is_empty() {
+            anyhow::bail!(UserError::new(format!("--var {s}: no values")));
+        }
+        for (i, value) in values.iter().enumerate() {
+            if values[..i].contains(value) {
+                anyhow::bail!(UserError::new(format!("--var {s}: '{value}' given twice")));
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+/// Start `template` once per combination of `vars` as one group, then wait
+/// for every job and print which combinations passed.
+pub async fn execute(
+    template: String,
+    vars: Vec<String>,
+    name: Option<String>,
+    timeout: Option<String>,
+    priority: Option<String>,
+    no_wait: bool,
+    json: bool,
+) -> Result<()> {
+    let vars = vars
+        .iter()
+        .map(|s| Var::parse(s))
+        .collect::<Result<Vec<_>>>()?;
+    for (i, var) in vars.iter().enumerate() {
+        if vars[..i].iter().any(|v| v.name == var.name) {
+            anyhow::bail!(UserError::new(format!("--var {} given twice", var.name)));
+        }
+        if !template.contains(&format!("{{{}}}", var.name)) {
+            anyhow::bail!(UserError::new(format!(
+                "--var {0} isn't used in the command (add {{{0}}} where it goes)",
+                var.name
+            )));
+        }
+    }
+
+    let timeout_secs = timeout.map(|t| parse_duration(&t)).transpose()?;
+    let priority = run::priority_arg(priority)?;
+    let combos = combinations(&vars);
+    let jobs = combos
+        .iter()
+        .map(|combo| {
+            let spec = RunSpec {
+                priority,
+                ..spec_for(&template, name.as_deref(), &vars, combo, timeout_secs)?
+            };
+            spec.validate()?;
+            Ok(spec)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let client = DaemonClient::connect_or_start().await?;
+    let (group, jobs) = client.batch(BatchSpec { name, jobs }).await?;
+
+    if no_wait {
+        if json {
+            println!("{}", serde_json::json!({ "group": group, "jobs": jobs }));
+        } else {
+            println!("{}", group.id);
+            for job in &jobs {
+                eprintln!("  {}  {}", job.short_id(), job.command);
+            }
+        }
+        return Ok(());
+    }
+
+    if !json {
+        eprintln!("Started {} jobs in group {}", jobs.len(), group.id);
+    }
+    let mut waits = JoinSet::new();
+    for (i, job) in jobs.iter().enumerate() {
+        let (client, id) = (client.clone(), job.id.clone());
+        waits.spawn(async move { (i, client.wait(&id, None).await) });
+    }
+    let mut finished = jobs;
+    while let Some(joined) = waits.join_next().await {
+        let (i, result) = joined?;
+        finished[i] = result?;
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "group": group, "vars": vars, "jobs": finished })
+        );
+    } else {
+        print_matrix(&vars, &finished);
+    }
+    if finished.iter().any(|job| job.status != Status::Completed) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Every combination of one value per variable, the last varying fastest
+fn combinations(vars: &[Var]) -> Vec<Vec<&str>> {
+    vars.iter().fold(vec![Vec::new()], |combos, var| {
+        combos
+            .iter()
+            .flat_map(|combo| {
+                var.values.iter().map(move |value| {
+                    let mut combo = combo.clone();
+                    combo.push(value.as_str());
+                    combo
+                })
+            })
+            .collect()
+    })
+}
+
+/// The job for one combination, named `<name>-<values>` when the matrix has a
+/// name. Unnamed matrices leave their jobs unnamed, so two can run at once.
+fn spec_for(
+    template: &str,
+    name: Option<&str>,
+    vars: &[Var],
+    combo: &[&str],
+    timeout_secs: Option<u64>,
+) -> Result<RunSpec> {
+    let mut context = serde_json::Map::new();
+    for (var, value) in vars.iter().zip(combo) {
+        context.insert(var.name.clone(), (*value).into());
+    }
+
+    let mut spec = run::spec_in_current_dir(substitute(template, vars, combo))?;
+    spec.name = name.map(|name| format!("{name}-{}", combo.join("-")));
+    spec.context = Some(context.into());
+    spec.timeout_secs = timeout_secs;
+    Ok(spec)
+}
+
+/// `template` with each `{name}` replaced by its value in `combo`, in one pass,
+/// so a value that looks like a placeholder is left as it is
+fn substitute(template: &str, vars: &[Var], combo: &[&str]) -> String {
+    let mut command = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        command.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest[1..].find('}').and_then(|close| {
+            let placeholder = &rest[1..=close];
+            let i = vars.iter().position(|var| var.name == placeholder)?;
+            Some((combo[i], close + 2))
+        });
+        match value {
+            Some((value, len)) => {
+                command.push_str(value);
+                rest = &rest[len..];
+            }
+            None => {
+                command.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    command.push_str(rest);
+    command
+}
+
+/// Pass/fail grid: a row per combination of all but the last variable, a
+/// column per value of the last. `jobs` are in `combinations` order.
+fn print_matrix(vars: &[Var], jobs: &[Job]) {
+    let (row_vars, columns) = match vars.split_last() {
+        Some((last, [])) => (std::slice::from_ref(last), vec!["result".to_string()]),
+        Some((last, rest)) => (rest, last.values.clone()),
+        None => return,
+    };
+    let row_labels: Vec<String> = combinations(row_vars)
+        .iter()
+        .map(|combo| combo.join(" "))
+        .collect();
+    let cells: Vec<String> = jobs.iter().map(cell).collect();
+
+    let corner = match vars.split_last() {
+        Some((last, [])) => last.name.clone(),
+        Some((last, rest)) => {
+            let names: Vec<&str> = rest.iter().map(|v| v.name.as_str()).collect();
+            format!("{} \\ {}", names.join(" "), last.name)
+        }
+        None => String::new(),
+    };
+    let label_width = row_labels
+        .iter()
+        .map(|l| l.chars().count())
+        .chain([corner.chars().count()])
+        .max()
+        .unwrap_or(0);
+    let cell_width = cells
+        .iter()
+        .chain(&columns)
+        .map(|c| c.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    print!("{corner:<label_width$}");
+    for column in &columns {
+        print!("  {column:<cell_width$}");
+    }
+    println!();
+    let rows = jobs.chunks(columns.len()).zip(cells.chunks(columns.len()));
+    for (label, (row, texts)) in row_labels.iter().zip(rows) {
+        print!("{label:<label_width$}");
+        for (job, text) in row.iter().zip(texts) {
+            // Pad before colorizing to preserve alignment
+            let text = format!("{text:<cell_width$}");
+            let text = match job.status {
+                Status::Completed => text.green(),
+                Status::Failed => text.red(),
+                _ => text.yellow(),
+            };
+            print!("  {text}");
+        }
+        println!();
+    }
+}
+
+fn cell(job: &Job) -> String {
+    match (job.status, job.exit_code) {
+        (Status::Completed, _) => "pass".to_string(),
+        (Status::Failed, Some(code)) => format!("fail ({code})"),
+        (status, _) => status.as_str().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_var() {
+        let var = Var::parse("py=3.11, 3.12,").unwrap();
+        assert_eq!(var.name, "py");
+        assert_eq!(var.values, ["3.11", "3.12"]);
+
+        assert!(Var::parse("py").is_err());
+        assert!(Var::parse("py=").is_err());
+        assert!(Var::parse("p y=1").is_err());
+        assert!(Var::parse("py=3.11,3.11").is_err());
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let vars = [
+            Var::parse("feat=a,b").unwrap(),
+            Var::parse("py=3.11,3.12").unwrap(),
+        ];
+        let combos = combinations(&vars);
+        assert_eq!(
+            combos,
+            [["a", "3.11"], ["a", "3.12"], ["b", "3.11"], ["b", "3.12"]]
+        );
+
+        let spec = spec_for(
+            "test --features {feat} -p {py}",
+            Some("test"),
+            &vars,
+            &combos[2],
+            None,
+        )
+        .unwrap();
+        assert_eq!(spec.command, "test --features b -p 3.11");
+        assert_eq!(spec.name.as_deref(), Some("test-b-3.11"));
+        assert_eq!(
+            spec.context,
+            Some(serde_json::json!({"feat": "b", "py": "3.11"}))
+        );
+
+        let spec = spec_for("test {feat}", None, &vars, &combos[0], None).unwrap();
+        assert_eq!(spec.name, None);
+    }
+
+    #[test]
+    fn test_substitute_in_one_pass() {
+        let vars = [Var::parse("a={b}").unwrap(), Var::parse("b=x").unwrap()];
+        assert_eq!(
+            substitute("echo {a} {b} {c} {", &vars, &["{b}", "x"]),
+            "echo {b} x {c} {"
+        );
+    }
+}
@@ -0,0 +1,546 @@
+//! `jb mcp`: a Model Context Protocol server on stdin/stdout.
+//!
+//! Messages are JSON-RPC 2.0, one per line. Most tools map onto a daemon
+//! request and take that request's fields as arguments, so `stop_job` takes
+//! `{"id": "...", "force": true}` just like [`Request::Stop`]. Each job's log
+//! is also a resource, `jb://jobs/<id>/log`.
+
+use crate::client::DaemonClient;
+use crate::commands::logs::{self, Cursor};
+use crate::commands::retry;
+use crate::core::ipc::{Request, Response};
+use crate::core::{Config, Database, Paths, logfile};
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+/// Protocol revisions we can speak, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Most of a log returned when it is read as a resource
+const MAX_RESOURCE_BYTES: u64 = 1024 * 1024;
+
+const LOG_URI_PREFIX: &str = "jb://jobs/";
+const LOG_URI_SUFFIX: &str = "/log";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub async fn execute() -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+
+    // Requests are answered concurrently (a wait_job can take minutes), so
+    // replies go through one writer
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = rx.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut handlers = JoinSet::new();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        // Forget requests already answered
+        while handlers.try_join_next().is_some() {}
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = tx.send(error_reply(&Value::Null, PARSE_ERROR, &e.to_string()));
+                continue;
+            }
+        };
+        let tx = tx.clone();
+        handlers.spawn(async move {
+            if let Some(reply) = handle_message(message).await {
+                let _ = tx.send(reply);
+            }
+        });
+    }
+
+    // The client closed stdin; answer what it already asked, then let the
+    // writer drain the replies
+    while handlers.join_next().await.is_some() {}
+    drop(tx);
+    let _ = writer.await;
+    Ok(())
+}
+
+/// Answer one JSON-RPC message; notifications get no reply.
+async fn handle_message(message: Value) -> Option<Value> {
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        // A response to something we never send, or garbage
+        return message
+            .get("id")
+            .map(|id| error_reply(id, INVALID_REQUEST, "Expected a request"));
+    };
+    let id = message.get("id")?.clone();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => call_tool(&params).await,
+        "resources/list" => list_resources(),
+        "resources/templates/list" => Ok(json!({
+            "resourceTemplates": [{
+                "uriTemplate": format!("{LOG_URI_PREFIX}{{id}}{LOG_URI_SUFFIX}"),
+                "name": "Job log",
+                "description": "Output of a job (the last 1 MiB)",
+                "mimeType": "text/plain",
+            }]
+        })),
+        "resources/read" => read_resource(&params),
+        _ => Err(RpcError(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {method}"),
+        )),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError(code, message)) => error_reply(&id, code, &message),
+    })
+}
+
+struct RpcError(i64, String);
+
+fn invalid_params(message: impl Into<String>) -> RpcError {
+    RpcError(INVALID_PARAMS, message.into())
+}
+
+fn error_reply(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params
+        .get("protocolVersion")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {}, "resources": {} },
+        "serverInfo": { "name": "jb", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Run shell commands as background jobs that outlive this session. \
+            Start with run_job, poll with job_status or read_logs (pass back the returned \
+            cursor to get only new output), or block with wait_job.",
+    })
+}
+
+/// Tool definitions. Arguments of the tools backed by a daemon request are
+/// that request's fields; `test_schemas_match_requests` keeps them in step.
+fn tools() -> Value {
+    let id = json!({ "type": "string", "description": "Job ID (or unique prefix)" });
+    json!([
+        {
+            "name": "run_job",
+            "description": "Start a shell command as a background job and return the job. \
+                The job keeps running after this call; use wait_job or read_logs to follow it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "Shell command to run" },
+                    "cwd": { "type": "string", "description": "Absolute working directory (default: where jb mcp was started)" },
+                    "name": { "type": "string", "description": "Name, unique among running jobs" },
+                    "timeout_secs": { "type": "integer", "minimum": 1 },
+                    "env": { "type": "object", "additionalProperties": { "type": "string" } },
+                    "context": { "type": "object", "description": "Arbitrary metadata kept with the job" },
+                    "idempotency_key": { "type": "string", "description": "Return the existing job if one was started with this key" },
+                    "artifacts": { "type": "array", "items": { "type": "string" }, "description": "Globs (relative to cwd) collected when the job exits" },
+                    "ports": { "type": "array", "items": { "type": "string" }, "description": "Env var names to receive free local ports" },
+                    "concurrency_group": { "type": "string", "description": "Run one at a time with other jobs in this group" },
+                    "depends_on": { "type": "array", "items": { "type": "string" }, "description": "Job IDs that must complete successfully first" },
+                    "run_when": { "type": "string", "enum": ["on_success", "on_failure", "always"], "description": "Run once depends_on finish: if all completed (default), if any didn't, or either way" },
+                    "priority": { "type": "integer", "description": "Queued jobs with a higher priority start first (high is 10, low is -10)" },
+                },
+                "required": ["command"],
+            },
+        },
+        {
+            "name": "list_jobs",
+            "description": "List recent jobs, newest first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "status": { "type": "string", "enum": ["pending", "running", "completed", "failed", "stopped", "interrupted"] },
+                    "limit": { "type": "integer", "minimum": 1 },
+                },
+            },
+        },
+        {
+            "name": "job_status",
+            "description": "Get a job's status, exit code, timing and settings.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": id },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "read_logs",
+            "description": "Read a job's output. Without `tail`, returns output after `cursor` \
+                (from the start if omitted) and the cursor to pass next time; `done` is true once \
+                the job has exited and everything was read.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": id,
+                    "cursor": { "type": "string", "description": "Cursor from a previous read_logs call" },
+                    "tail": { "type": "integer", "minimum": 1, "description": "Return only the last N lines" },
+                },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "wait_job",
+            "description": "Block until a job exits (or, with `ready`, passes its readiness checks) and return it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": id,
+                    "timeout_secs": { "type": "integer", "minimum": 1 },
+                    "ready": { "type": "boolean" },
+                },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "stop_job",
+            "description": "Stop a running job (SIGTERM, then SIGKILL after the grace period) or cancel a queued one.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": id,
+                    "force": { "type": "boolean", "description": "SIGKILL immediately" },
+                },
+                "required": ["id"],
+            },
+        },
+        {
+            "name": "retry_job",
+            "description": "Run a job's command again with the same settings, as a new job.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": id },
+                "required": ["id"],
+            },
+        },
+    ])
+}
+
+/// The daemon request kind behind a tool, as tagged in [`Request`]
+fn request_kind(tool: &str) -> Option<&'static str> {
+    Some(match tool {
+        "run_job" => "run",
+        "list_jobs" => "list",
+        "job_status" => "status",
+        "wait_job" => "wait",
+        "stop_job" => "stop",
+        _ => return None,
+    })
+}
+
+async fn call_tool(params: &Value) -> Result<Value, RpcError> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_params("Missing tool name"))?;
+    let mut args = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    if !args.is_object() {
+        return Err(invalid_params("Tool arguments must be an object"));
+    }
+
+    let result = match name {
+        "read_logs" => read_logs(&args),
+        "retry_job" => retry_job(&args).await,
+        _ => {
+            let kind = request_kind(name)
+                .ok_or_else(|| invalid_params(format!("Unknown tool: {name}")))?;
+            if kind == "run" && args.get("cwd").is_none() {
+                args["cwd"] = std::env::current_dir()
+                    .map(|dir| dir.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+                    .into();
+            }
+            match to_request(kind, args) {
+                Ok(request) => send(request).await,
+                Err(e) => Err(e),
+            }
+        }
+    };
+
+    Ok(match result {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "structuredContent": value,
+            "isError": false,
+        }),
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": format!("{e:#}") }],
+            "isError": true,
+        }),
+    })
+}
+
+/// Build the daemon request for a tool from its arguments.
+fn to_request(kind: &str, args: Value) -> Result<Request> {
+    let request: Request = serde_json::from_value(json!({ "type": kind, "data": args }))
+        .map_err(|e| anyhow::anyhow!("Invalid arguments: {e}"))?;
+    if let Request::Run(spec) = &request {
+        if !std::path::Path::new(&spec.cwd).is_absolute() {
+            bail!("cwd must be an absolute path");
+        }
+        spec.validate()?;
+    }
+    Ok(request)
+}
+
+async fn send(request: Request) -> Result<Value> {
+    let client = DaemonClient::connect_or_start().await?;
+    match client.send(request).await? {
+        Response::Job(job) => Ok(serde_json::to_value(job)?),
+        Response::Jobs(jobs) => Ok(json!({ "jobs": jobs })),
+        Response::Ok => Ok(json!({ "ok": true })),
+        Response::Error(e) => bail!("{e}"),
+        _ => bail!("Unexpected response from daemon"),
+    }
+}
+
+fn required_id(args: &Value) -> Result<&str> {
+    args.get("id")
+        .and_then(Value::as_str)
+        .context("Missing job id")
+}
+
+fn read_logs(args: &Value) -> Result<Value> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+    db.recover_orphans();
+    let job = db.resolve(required_id(args)?)?;
+
+    if let Some(n) = args.get("tail") {
+        let n = n.as_u64().context("tail must be a positive integer")?;
+        let n = usize::try_from(n)?;
+        return Ok(json!({
+            "id": job.id,
+            "status": job.status,
+            "output": logs::tail(&paths, &job.id, n)?,
+        }));
+    }
+
+    let cursor = args
+        .get("cursor")
+        .and_then(Value::as_str)
+        .unwrap_or("0")
+        .to_string();
+    let chunk = logs::read_cursor(&db, &paths, &job, &Cursor::Token(cursor))?;
+    Ok(logs::cursor_json(&job, &chunk))
+}
+
+async fn retry_job(args: &Value) -> Result<Value> {
+    let job = Database::open(&Paths::new())?.resolve(required_id(args)?)?;
+    send(Request::Run(Box::new(retry::retry_spec(&job)))).await
+}
+
+fn list_resources() -> Result<Value, RpcError> {
+    let paths = Paths::new();
+    let limit = Config::load(&paths).map_or(10, |c| c.list.limit);
+    let jobs = Database::open(&paths)
+        .and_then(|db| db.list(None, Some(limit)))
+        .map_err(|e| RpcError(INVALID_REQUEST, format!("{e:#}")))?;
+
+    let resources: Vec<Value> = jobs
+        .iter()
+        .map(|job| {
+            json!({
+                "uri": format!("{LOG_URI_PREFIX}{}{LOG_URI_SUFFIX}", job.id),
+                "name": format!("{} log", job.name.as_deref().unwrap_or(&job.id)),
+                "description": format!("{} ({})", job.command, job.status),
+                "mimeType": "text/plain",
+            })
+        })
+        .collect();
+    Ok(json!({ "resources": resources }))
+}
+
+fn read_resource(params: &Value) -> Result<Value, RpcError> {
+    let uri = params
+        .get("uri")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_params("Missing uri"))?;
+    let id = uri
+        .strip_prefix(LOG_URI_PREFIX)
+        .and_then(|rest| rest.strip_suffix(LOG_URI_SUFFIX))
+        .ok_or_else(|| invalid_params(format!("Unknown resource: {uri}")))?;
+
+    let text = read_log_end(id).map_err(|e| invalid_params(format!("{e:#}")))?;
+    Ok(json!({
+        "contents": [{ "uri": uri, "mimeType": "text/plain", "text": text }],
+    }))
+}
+
+/// The last [`MAX_RESOURCE_BYTES`] of a job's output, from a line boundary.
+fn read_log_end(id: &str) -> Result<String> {
+    let paths = Paths::new();
+    let job = Database::open(&paths)?.resolve(id)?;
+    let (first, end) = logfile::span(&paths, &job.id)?;
+    let start = end.saturating_sub(MAX_RESOURCE_BYTES).max(first);
+
+    // Across the segments rotated out of the log, if it starts in one
+    let mut reader = logfile::LogReader::new(&paths, &job.id, start);
+    let mut data = Vec::new();
+    while (data.len() as u64) < end - start {
+        let bytes = reader.read(end - start - data.len() as u64)?;
+        if bytes.is_empty() {
+            break;
+        }
+        data.extend(bytes);
+    }
+    if start > first
+        && let Some(newline) = data.iter().position(|&b| b == b'\n')
+    {
+        data.drain(..=newline);
+    }
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(schema: &Value) -> Value {
+        match schema["type"].as_str() {
+            Some("string") => schema["enum"]
+                .get(0)
+                .cloned()
+                .unwrap_or_else(|| "/tmp".into()),
+            Some("integer") => 5.into(),
+            Some("boolean") => true.into(),
+            Some("array") => json!(["x"]),
+            _ => json!({}),
+        }
+    }
+
+    /// Request fields a tool deliberately leaves out of its schema
+    fn unexposed(kind: &str) -> &'static [&'static str] {
+        match kind {
+            // Derived from cwd; services are started from a task file or `jb run`
+            "run" => &["project", "ready_when", "restart", "health"],
+            _ => &[],
+        }
+    }
+
+    /// An instance of the request behind `kind`, to list its fields
+    fn empty_request(kind: &str) -> Request {
+        let id = String::new();
+        match kind {
+            "run" => Request::Run(Box::default()),
+            "list" => Request::List {
+                status: None,
+                limit: None,
+            },
+            "status" => Request::Status { id },
+            "wait" => Request::Wait {
+                id,
+                timeout_secs: None,
+                ready: false,
+            },
+            "stop" => Request::Stop { id, force: false },
+            _ => panic!("no request for {kind}"),
+        }
+    }
+
+    #[test]
+    fn test_schemas_match_requests() {
+        for tool in tools().as_array().unwrap() {
+            let Some(kind) = request_kind(tool["name"].as_str().unwrap()) else {
+                continue;
+            };
+            let properties = tool["inputSchema"]["properties"].as_object().unwrap();
+            let args: serde_json::Map<_, _> = properties
+                .iter()
+                .map(|(name, schema)| (name.clone(), sample(schema)))
+                .collect();
+
+            // Every property is a field of the request and survives the round trip
+            let request = to_request(kind, Value::Object(args)).unwrap();
+            let data = &serde_json::to_value(request).unwrap()["data"];
+            for name in properties.keys() {
+                assert!(!data[name].is_null(), "{kind}: {name} was dropped");
+            }
+
+            // Every field of the request is in the schema, unless left out on purpose
+            let fields = serde_json::to_value(empty_request(kind)).unwrap()["data"].clone();
+            for field in fields.as_object().unwrap().keys() {
+                assert!(
+                    properties.contains_key(field) || unexposed(kind).contains(&field.as_str()),
+                    "{kind}: {field} is missing from the schema"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protocol_messages() {
+        let reply = handle_message(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} },
+        }))
+        .await
+        .unwrap();
+        assert_eq!(reply["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(reply["result"]["serverInfo"]["name"], "jb");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_message(notification).await.is_none());
+
+        let reply = handle_message(json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+            .await
+            .unwrap();
+        assert_eq!(reply["result"]["tools"].as_array().unwrap().len(), 7);
+
+        let reply = handle_message(json!({ "jsonrpc": "2.0", "id": 3, "method": "nope" }))
+            .await
+            .unwrap();
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+
+        let reply = handle_message(json!({
+            "jsonrpc": "2.0", "id": 4, "method": "tools/call",
+            "params": { "name": "stop_job", "arguments": { "force": true } },
+        }))
+        .await
+        .unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert!(
+            reply["result"]["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .contains("missing field `id`")
+        );
+    }
+}
@@ -1,10 +1,25 @@
+pub mod artifacts;
+pub mod batch;
 pub mod clean;
 pub mod completions;
+pub mod config;
 pub mod daemon;
+pub mod events;
+pub mod flow;
+pub mod grep;
+pub mod group;
 pub mod list;
 pub mod logs;
+pub mod matrix;
+pub mod mcp;
+pub mod port;
+pub mod reprioritize;
+pub mod result;
 pub mod retry;
 pub mod run;
+pub mod service;
 pub mod status;
 pub mod stop;
+pub mod summary;
+pub mod task;
 pub mod wait;
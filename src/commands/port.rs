@@ -0,0 +1,55 @@
+use crate::core::{Database, Paths, UserError};
+use anyhow::{Result, bail};
+
+pub fn execute(id: &str, name: Option<&str>, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    // Check for orphaned jobs (dead processes still marked running)
+    db.recover_orphans();
+
+    let job = db.resolve(id)?;
+
+    if job.ports.is_empty() {
+        bail!(UserError::new(format!(
+            "Job {} has no allocated ports (use --port NAME on jb run)",
+            job.short_id()
+        )));
+    }
+    if job.status.is_terminal() {
+        bail!(UserError::new(format!(
+            "Job {} is {}, its ports were released",
+            job.short_id(),
+            job.status
+        )));
+    }
+
+    match name {
+        Some(name) => {
+            let Some(port) = job.ports.get(name) else {
+                let names: Vec<_> = job.ports.keys().map(String::as_str).collect();
+                bail!(UserError::new(format!(
+                    "Job {} has no port named {name} (has: {})",
+                    job.short_id(),
+                    names.join(", ")
+                )));
+            };
+            if json {
+                println!("{}", serde_json::json!({ "name": name, "port": port }));
+            } else {
+                println!("{port}");
+            }
+        }
+        None => {
+            if json {
+                println!("{}", serde_json::to_string(&job.ports)?);
+            } else {
+                for (name, port) in &job.ports {
+                    println!("{name}={port}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::client::DaemonClient;
+use crate::core::{Database, Paths, Status, UserError, format_priority, parse_priority};
+use anyhow::Result;
+
+pub async fn execute(id: String, priority: &str, json: bool) -> Result<()> {
+    let priority = parse_priority(priority).map_err(|e| UserError::new(e.to_string()))?;
+    let db = Database::open(&Paths::new())?;
+    let job = db.resolve(&id)?;
+
+    if job.status != Status::Pending {
+        anyhow::bail!(UserError::new(format!(
+            "Job {} is {}; only queued jobs can be reprioritized",
+            job.short_id(),
+            job.status
+        )));
+    }
+
+    let client = DaemonClient::connect_or_start().await?;
+    let job = client.reprioritize(&job.id, priority).await?;
+
+    if json {
+        println!("{}", serde_json::to_string(&job)?);
+    } else if job.status == Status::Pending {
+        println!(
+            "Job {} priority: {}",
+            job.short_id(),
+            format_priority(job.priority)
+        );
+    } else {
+        println!(
+            "Job {} priority: {} ({} now)",
+            job.short_id(),
+            format_priority(job.priority),
+            job.status
+        );
+    }
+    Ok(())
+}
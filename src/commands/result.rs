@@ -0,0 +1,34 @@
+use crate::core::{Database, Paths, UserError};
+use anyhow::{Result, bail};
+
+pub fn execute(id: &str, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    // Check for orphaned jobs (dead processes still marked running)
+    db.recover_orphans();
+
+    let job = db.resolve(id)?;
+
+    let Some(result) = &job.result else {
+        if job.status.is_terminal() {
+            bail!(UserError::new(format!(
+                "Job {} did not write a result",
+                job.short_id()
+            )));
+        }
+        bail!(UserError::new(format!(
+            "Job {} is still {}, result is captured on exit",
+            job.short_id(),
+            job.status
+        )));
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(result)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    }
+
+    Ok(())
+}
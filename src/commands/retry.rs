@@ -1,6 +1,6 @@
 use crate::client::DaemonClient;
-use crate::core::ipc::{Request, Response};
-use crate::core::{Database, Paths};
+use crate::core::ipc::RunSpec;
+use crate::core::{Database, Job, Paths, RunWhen};
 use anyhow::Result;
 
 pub async fn execute(id: String, json: bool) -> Result<()> {
@@ -9,9 +9,22 @@ pub async fn execute(id: String, json: bool) -> Result<()> {
     let job = db.resolve(&id)?;
 
     // Send to daemon
-    let mut client = DaemonClient::connect_or_start().await?;
+    let client = DaemonClient::connect_or_start().await?;
 
-    let request = Request::Run {
+    let new_job = client.run(retry_spec(&job)).await?;
+
+    if json {
+        println!("{}", serde_json::to_string(&new_job)?);
+    } else {
+        println!("{}", new_job.short_id());
+    }
+    Ok(())
+}
+
+/// A fresh run of `job`'s command with the same settings.
+#[must_use]
+pub fn retry_spec(job: &Job) -> RunSpec {
+    RunSpec {
         command: job.command.clone(),
         name: job.name.clone(),
         cwd: job.cwd.to_string_lossy().to_string(),
@@ -19,18 +32,15 @@ pub async fn execute(id: String, json: bool) -> Result<()> {
         timeout_secs: job.timeout_secs,
         context: job.context.clone(),
         idempotency_key: None, // Don't reuse idempotency key
-    };
-
-    match client.send(request).await? {
-        Response::Job(new_job) => {
-            if json {
-                println!("{}", serde_json::to_string(&new_job)?);
-            } else {
-                println!("{}", new_job.short_id());
-            }
-            Ok(())
-        }
-        Response::Error(e) => anyhow::bail!("{e}"),
-        _ => anyhow::bail!("Unexpected response from daemon"),
+        artifacts: job.artifacts.clone(),
+        ready_when: job.ready_when.clone(),
+        restart: job.restart,
+        health: job.health.clone(),
+        ports: job.ports.keys().cloned().collect(), // fresh ports, same names
+        env: job.env.clone(),
+        concurrency_group: job.concurrency_group.clone(),
+        depends_on: Vec::new(), // A retry runs on its own
+        run_when: RunWhen::OnSuccess,
+        priority: job.priority,
     }
 }
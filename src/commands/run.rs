@@ -1,7 +1,7 @@
 use crate::client::DaemonClient;
-use crate::core::ipc::{Request, Response};
-use crate::core::{Paths, detect_project, parse_duration};
-use anyhow::Result;
+use crate::core::ipc::RunSpec;
+use crate::core::{Paths, ReadyCheck, UserError, detect_project, parse_duration, parse_priority};
+use anyhow::{Context, Result};
 use std::env;
 
 #[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)]
@@ -11,90 +11,95 @@ pub async fn execute(
     timeout: Option<String>,
     context: Option<String>,
     key: Option<String>,
+    priority: Option<String>,
+    artifacts: Vec<String>,
+    ports: Vec<String>,
+    ready_when: Vec<ReadyCheck>,
     wait: bool,
     follow: bool,
     json: bool,
 ) -> Result<()> {
-    let paths = Paths::new();
-    paths.ensure_dirs()?;
-
-    let cwd = env::current_dir()?;
-    let project = detect_project(&cwd);
-
     let timeout_secs = timeout.as_ref().map(|t| parse_duration(t)).transpose()?;
     let context_json: Option<serde_json::Value> = context
         .as_ref()
         .map(|c| serde_json::from_str(c))
         .transpose()?;
 
-    // Connect to daemon (auto-starts if not running)
-    let mut client = DaemonClient::connect_or_start().await?;
-
-    let request = Request::Run {
-        command,
+    let overrides = RunSpec {
         name,
-        cwd: cwd.to_string_lossy().to_string(),
-        project: project.to_string_lossy().to_string(),
         timeout_secs,
         context: context_json,
         idempotency_key: key,
+        priority: priority_arg(priority)?,
+        artifacts,
+        ports,
+        ready_when,
+        ..spec_in_current_dir(command.clone())?
     };
 
-    match client.send(request).await? {
-        Response::Job(job) => {
-            let job_id = job.id.clone();
+    // `jb run @test` runs the project task instead, with these flags layered on top
+    if let Some(task) = command.strip_prefix('@') {
+        return crate::commands::task::run(task, overrides, wait, follow, json).await;
+    }
+    submit(overrides, wait, follow, json).await
+}
 
-            if json && !follow {
-                println!("{}", serde_json::to_string(&job)?);
-            } else if !follow {
-                println!("{}", job.short_id());
-            }
+/// A spec for `command` run from the current directory and its project.
+pub fn spec_in_current_dir(command: String) -> Result<RunSpec> {
+    let cwd = env::current_dir()?;
+    let project = detect_project(&cwd);
+    Ok(RunSpec {
+        command,
+        cwd: cwd.to_string_lossy().to_string(),
+        project: project.to_string_lossy().to_string(),
+        ..RunSpec::default()
+    })
+}
 
-            if follow {
-                // Follow implies waiting, so use logs --follow
-                crate::commands::logs::execute(&job_id, None, true, false)?;
-            } else if wait {
-                wait_for_job(&mut client, &job_id, json).await?;
-            }
+/// The value of a `--priority` flag, normal if it wasn't given.
+pub fn priority_arg(priority: Option<String>) -> Result<i32> {
+    priority.map_or(Ok(0), |p| {
+        parse_priority(&p).map_err(|e| UserError::new(e.to_string()).into())
+    })
+}
 
-            Ok(())
-        }
-        Response::Error(e) => {
-            // User-recoverable errors should exit cleanly
-            if e.starts_with("Name '") && e.contains("is in use") {
-                anyhow::bail!(crate::core::UserError::new(e));
-            }
-            anyhow::bail!("{e}");
-        }
-        _ => {
-            anyhow::bail!("Unexpected response from daemon");
-        }
+/// Validate a spec, start it via the daemon and print the new job.
+pub async fn submit(spec: RunSpec, wait: bool, follow: bool, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    paths.ensure_dirs()?;
+    spec.validate()?;
+
+    // Connect to daemon (auto-starts if not running)
+    let client = DaemonClient::connect_or_start().await?;
+
+    let job = client.run(spec).await?;
+
+    if json && !follow {
+        println!("{}", serde_json::to_string(&job)?);
+    } else if !follow {
+        println!("{}", job.short_id());
+    }
+
+    if follow {
+        // Follow implies waiting, so use logs --follow
+        crate::commands::logs::execute(&job.id, None, true, false, None, false)?;
+    } else if wait {
+        wait_for_job(&client, &job.id, json).await?;
     }
+
+    Ok(())
 }
 
-async fn wait_for_job(client: &mut DaemonClient, job_id: &str, json: bool) -> Result<()> {
-    let request = Request::Wait {
-        id: job_id.to_string(),
-        timeout_secs: None,
-    };
+async fn wait_for_job(client: &DaemonClient, job_id: &str, json: bool) -> Result<()> {
+    let job = client.wait(job_id, None).await.context("Wait failed")?;
 
-    match client.send(request).await? {
-        Response::Job(job) => {
-            if json {
-                println!("{}", serde_json::to_string(&job)?);
-            } else {
-                eprintln!("Job {} finished: {}", job.short_id(), job.status);
-                if let Some(code) = job.exit_code {
-                    std::process::exit(code);
-                }
-            }
-            Ok(())
-        }
-        Response::Error(e) => {
-            anyhow::bail!("Wait failed: {e}");
-        }
-        _ => {
-            anyhow::bail!("Unexpected response");
+    if json {
+        println!("{}", serde_json::to_string(&job)?);
+    } else {
+        eprintln!("Job {} finished: {}", job.short_id(), job.status);
+        if let Some(code) = job.exit_code {
+            std::process::exit(code);
         }
     }
+    Ok(())
 }
@@ -0,0 +1,121 @@
+use crate::client::DaemonClient;
+use crate::commands::list::{format_relative_time, format_status, truncate};
+use crate::commands::run;
+use crate::core::ipc::RunSpec;
+use crate::core::{
+    Database, HealthCheck, Paths, ReadyCheck, RestartMode, RestartPolicy, Status, UserError,
+    parse_duration,
+};
+use anyhow::Result;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn start(
+    name: String,
+    command: String,
+    restart: &str,
+    max_restarts: Option<u32>,
+    restart_delay: &str,
+    ports: Vec<String>,
+    ready_when: Vec<ReadyCheck>,
+    health: Option<HealthCheck>,
+    json: bool,
+) -> Result<()> {
+    let mode = restart
+        .parse::<RestartMode>()
+        .map_err(|e| UserError::new(e.to_string()))?;
+    let policy = RestartPolicy {
+        mode,
+        max_restarts,
+        delay_secs: parse_duration(restart_delay)?,
+    };
+
+    let spec = RunSpec {
+        name: Some(name),
+        ports,
+        ready_when,
+        restart: Some(policy),
+        health,
+        ..run::spec_in_current_dir(command)?
+    };
+    run::submit(spec, false, false, json).await
+}
+
+pub async fn restart(name: &str, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+    let job = db.resolve(name)?;
+
+    if !job.is_service() {
+        anyhow::bail!(UserError::new(format!(
+            "Job {} is not a service (start one with jb service start)",
+            job.short_id()
+        )));
+    }
+    if job.status != Status::Running {
+        anyhow::bail!(UserError::new(format!(
+            "Service {} is not running ({})",
+            job.short_id(),
+            job.status
+        )));
+    }
+
+    let client = DaemonClient::connect_or_start().await?;
+    client.restart(&job.id).await?;
+
+    if json {
+        let updated = db.get(&job.id)?.unwrap();
+        println!("{}", serde_json::to_string(&updated)?);
+    } else {
+        println!("Restarting {}", job.short_id());
+    }
+    Ok(())
+}
+
+pub fn list(all: bool, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    // Check for orphaned jobs (dead processes still marked running)
+    db.recover_orphans();
+
+    let services: Vec<_> = db
+        .list(None, None)?
+        .into_iter()
+        .filter(|j| j.is_service() && (all || !j.status.is_terminal()))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string(&services)?);
+        return Ok(());
+    }
+
+    if services.is_empty() {
+        println!("No services {}", if all { "found" } else { "running" });
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<10} {:<12} {:<9} {:<11} {:<30} STARTED",
+        "NAME", "ID", "STATUS", "RESTARTS", "POLICY", "COMMAND"
+    );
+
+    for job in services {
+        let policy = job.restart.map_or("-", |p| p.mode.as_str());
+        let started = job
+            .started_at
+            .map_or_else(|| "-".to_string(), format_relative_time);
+
+        println!(
+            "{:<12} {:<10} {} {:<9} {:<11} {:<30} {}",
+            truncate(job.name.as_deref().unwrap_or("-"), 12),
+            job.short_id(),
+            format_status(job.status),
+            job.restarts,
+            policy,
+            truncate(&job.command, 28),
+            started
+        );
+    }
+
+    Ok(())
+}
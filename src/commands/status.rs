@@ -1,6 +1,9 @@
-use crate::core::{Database, Paths, Status};
+use crate::core::{Database, EventKind, Paths, RunWhen, Status, format_priority};
 use anyhow::Result;
 
+/// Most recent crashes listed for a service
+const MAX_CRASHES_SHOWN: usize = 5;
+
 pub fn execute(id: Option<String>, json: bool) -> Result<()> {
     let paths = Paths::new();
     let db = Database::open(&paths)?;
@@ -26,26 +29,102 @@ fn show_job_status(db: &Database, paths: &Paths, id: &str, json: bool) -> Result
     if let Some(name) = &job.name {
         println!("Name:     {name}");
     }
-    println!("Status:   {}", job.status);
+    if job.status == Status::Running && job.ready_at.is_some() {
+        println!("Status:   {} (ready)", job.status);
+    } else {
+        println!("Status:   {}", job.status);
+    }
     println!("Command:  {}", job.command);
+    if job.priority != 0 {
+        println!("Priority: {}", format_priority(job.priority));
+    }
     println!("Project:  {}", job.project.display());
     println!("CWD:      {}", job.cwd.display());
+    if let Some(group) = &job.concurrency_group {
+        println!("Group:    {group}");
+    }
+    if !job.depends_on.is_empty() {
+        match job.run_when {
+            RunWhen::OnSuccess => println!("Needs:    {}", job.depends_on.join(", ")),
+            when => println!("Needs:    {} ({when})", job.depends_on.join(", ")),
+        }
+    }
     println!("Created:  {}", job.created_at);
     if let Some(started) = job.started_at {
         println!("Started:  {started}");
     }
+    if let Some(ready) = job.ready_at {
+        println!("Ready:    {ready}");
+    } else if !job.ready_when.is_empty() {
+        let checks: Vec<_> = job.ready_when.iter().map(ToString::to_string).collect();
+        println!("Ready:    waiting for {}", checks.join(", "));
+    }
     if let Some(finished) = job.finished_at {
         println!("Finished: {finished}");
     }
     if let Some(pid) = job.pid {
         println!("PID:      {pid}");
     }
+    if !job.ports.is_empty() {
+        let ports: Vec<_> = job.ports.iter().map(|(n, p)| format!("{n}={p}")).collect();
+        let note = if job.status.is_terminal() {
+            " (released)"
+        } else {
+            ""
+        };
+        println!("Ports:    {}{note}", ports.join(", "));
+    }
     if let Some(code) = job.exit_code {
         println!("Exit:     {code}");
     }
+    if let Some(policy) = job.restart {
+        let max = policy
+            .max_restarts
+            .map_or_else(|| "unlimited".to_string(), |m| m.to_string());
+        println!(
+            "Service:  restart {}, max {max}, delay {}s ({} restarts)",
+            policy.mode, policy.delay_secs, job.restarts
+        );
+        let crashes: Vec<_> = db
+            .events(Some(&job.id), 0, None)?
+            .into_iter()
+            .filter(|e| e.kind == EventKind::Crashed)
+            .collect();
+        for crash in crashes.iter().rev().take(MAX_CRASHES_SHOWN) {
+            println!(
+                "Crashed:  {} ({})",
+                crash.created_at,
+                crash.detail.as_deref().unwrap_or("-")
+            );
+        }
+    }
+    if let Some(check) = &job.health {
+        let state = job.health_status.map_or("unknown", |h| h.as_str());
+        println!(
+            "Health:   {state} ({}, every {}s, {} after {} failures)",
+            check.probe, check.interval_secs, check.action, check.threshold
+        );
+        if let Some(output) = &job.health_output {
+            let checked = job
+                .health_checked_at
+                .map(|t| format!(" at {t}"))
+                .unwrap_or_default();
+            println!("Checked:  {output}{checked}");
+        }
+    }
     if let Some(ctx) = &job.context {
         println!("Context:  {ctx}");
     }
+    if let Some(result) = &job.result {
+        println!("Result:   {result}");
+    }
+    if !job.artifacts.is_empty() {
+        let collected = db.list_artifacts(&job.id)?.len();
+        println!(
+            "Artifacts: {} ({collected} collected)",
+            job.artifacts.join(", ")
+        );
+    }
 
     let log_path = paths.log_file(&job.id);
     if log_path.exists() {
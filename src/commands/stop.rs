@@ -1,6 +1,5 @@
-use crate::client::DaemonClient;
-use crate::core::ipc::{Request, Response};
-use crate::core::{Database, Paths, Status, kill_process_group};
+use crate::client::{self, DaemonClient};
+use crate::core::{Database, Paths, Status, process};
 use anyhow::Result;
 
 pub async fn execute(id: String, force: bool, json: bool) -> Result<()> {
@@ -18,14 +17,9 @@ pub async fn execute(id: String, force: bool, json: bool) -> Result<()> {
     }
 
     // Try to stop via daemon
-    if let Ok(mut client) = DaemonClient::connect_or_start().await {
-        let request = Request::Stop {
-            id: job.id.clone(),
-            force,
-        };
-
-        match client.send(request).await? {
-            Response::Ok => {
+    if let Ok(client) = DaemonClient::connect_or_start().await {
+        match client.stop(&job.id, force).await {
+            Ok(()) => {
                 if json {
                     let updated = db.get(&job.id)?.unwrap();
                     println!("{}", serde_json::to_string(&updated)?);
@@ -34,13 +28,9 @@ pub async fn execute(id: String, force: bool, json: bool) -> Result<()> {
                 }
                 return Ok(());
             }
-            Response::Error(e) => {
-                // Job might not be running in daemon, fall back to direct kill
-                if !e.contains("not running") {
-                    anyhow::bail!("{e}");
-                }
-            }
-            _ => {}
+            // Job might not be running in daemon, fall back to direct kill
+            Err(e) if !client::is_daemon_error(&e, "not running") => return Err(e),
+            Err(_) => {}
         }
     }
 
@@ -48,7 +38,8 @@ pub async fn execute(id: String, force: bool, json: bool) -> Result<()> {
     if job.status == Status::Pending {
         db.update_status(&job.id, Status::Stopped)?;
     } else if let Some(pid) = job.pid {
-        kill_process_group(pid, force);
+        // Leaves alone whatever now has the PID if the job's process is gone
+        process::kill_if_same(pid, job.pid_fingerprint.as_deref(), force);
         db.update_finished(&job.id, Status::Stopped, None)?;
     }
 
@@ -0,0 +1,602 @@
+use crate::commands::logs::{LineLevel, classify_line, colorize_line, should_colorize};
+use crate::core::{Database, Job, Paths, logfile};
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+/// Keep at most this many error excerpts (the last ones, closest to the failure)
+const MAX_EXCERPTS: usize = 20;
+
+/// Lines longer than this are cut in the summary (minified JS, progress bars, ...)
+const MAX_LINE_CHARS: usize = 400;
+
+/// Failure keywords beyond the error/fatal/panic heuristics used for colorizing
+static EXTRA_ERROR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(failed|failure|traceback|exception|assertionerror)\b").unwrap()
+});
+
+/// Counters like "0 failed" or "0 errors" are not errors
+static ZERO_COUNT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b0 (failed|failures|errors?)\b").unwrap());
+
+static RUSTC_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(error|warning)(?:\[([A-Z]\d+)\])?: (.+)$").unwrap());
+static RUSTC_LOCATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*--> (.+):(\d+):(\d+)$").unwrap());
+static PYTEST_FAILED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(FAILED|ERROR) ([^\s:]+)(?:::(\S+))?(?: - (.+))?$").unwrap());
+static TSC_PAREN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+): (.+)$").unwrap());
+static TSC_PRETTY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+?):(\d+):(\d+) - (error|warning) (TS\d+): (.+)$").unwrap());
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    id: String,
+    name: Option<String>,
+    command: String,
+    status: String,
+    exit_code: Option<i32>,
+    duration_secs: Option<i64>,
+    total_lines: usize,
+    diagnostics: Vec<Diagnostic>,
+    errors: Vec<Excerpt>,
+    tail: Vec<String>,
+}
+
+/// A contiguous run of log lines around one or more error matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Excerpt {
+    start_line: usize,
+    end_line: usize,
+    /// Line numbers (1-based) that matched an error pattern
+    matches: Vec<usize>,
+    lines: Vec<String>,
+}
+
+impl Excerpt {
+    fn push(&mut self, line_no: usize, line: String) {
+        self.end_line = line_no;
+        self.lines.push(line);
+    }
+}
+
+/// A compiler/test-runner diagnostic recognised in the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Diagnostic {
+    tool: &'static str,
+    severity: String,
+    code: Option<String>,
+    message: String,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    log_line: usize,
+}
+
+pub fn execute(id: &str, lines: usize, context: usize, json: bool) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+
+    // Check for orphaned jobs (dead processes still marked running)
+    db.recover_orphans();
+
+    let job = db.resolve(id)?;
+    let scan = scan_log(&logfile::segments(&paths, &job.id), lines, context)?;
+
+    let summary = Summary {
+        id: job.id.clone(),
+        name: job.name.clone(),
+        command: job.command.clone(),
+        status: job.status.to_string(),
+        exit_code: job.exit_code,
+        duration_secs: duration_secs(&job),
+        total_lines: scan.total_lines,
+        diagnostics: scan.diagnostics,
+        errors: scan.excerpts,
+        tail: scan.tail.into_iter().collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        print_summary(&summary);
+    }
+
+    Ok(())
+}
+
+fn duration_secs(job: &Job) -> Option<i64> {
+    let started = job.started_at?;
+    let finished = job.finished_at.unwrap_or_else(chrono::Utc::now);
+    Some(finished.signed_duration_since(started).num_seconds())
+}
+
+fn print_summary(summary: &Summary) {
+    let colorize = should_colorize();
+    let paint = |line: &str| {
+        if colorize {
+            colorize_line(line)
+        } else {
+            line.to_string()
+        }
+    };
+
+    let name = summary
+        .name
+        .as_ref()
+        .map(|n| format!(" ({n})"))
+        .unwrap_or_default();
+    let exit = summary
+        .exit_code
+        .map(|c| format!(" (exit {c})"))
+        .unwrap_or_default();
+    let duration = summary
+        .duration_secs
+        .map(|d| format!(" in {}", format_duration(d)))
+        .unwrap_or_default();
+    println!(
+        "Job {}{name}: {}{exit}{duration}",
+        summary.id, summary.status
+    );
+    println!("Command: {}", summary.command);
+    println!("Output:  {} lines", summary.total_lines);
+
+    if !summary.diagnostics.is_empty() {
+        println!("\nDiagnostics:");
+        for d in &summary.diagnostics {
+            let code = d
+                .code
+                .as_ref()
+                .map(|c| format!("[{c}]"))
+                .unwrap_or_default();
+            let location = match (&d.file, d.line, d.column) {
+                (Some(f), Some(l), Some(c)) => format!("{f}:{l}:{c}"),
+                (Some(f), Some(l), None) => format!("{f}:{l}"),
+                (Some(f), None, _) => f.clone(),
+                _ => "-".to_string(),
+            };
+            println!(
+                "  {:<7} {}  {}  {}",
+                d.tool,
+                paint(&format!("{}{code}", d.severity)),
+                location,
+                d.message
+            );
+        }
+    }
+
+    if !summary.errors.is_empty() {
+        let matches: usize = summary.errors.iter().map(|e| e.matches.len()).sum();
+        println!("\nErrors ({matches} matching lines):");
+        for excerpt in &summary.errors {
+            println!(
+                "  --- lines {}-{} ---",
+                excerpt.start_line, excerpt.end_line
+            );
+            for (offset, line) in excerpt.lines.iter().enumerate() {
+                let line_no = excerpt.start_line + offset;
+                let marker = if excerpt.matches.contains(&line_no) {
+                    '>'
+                } else {
+                    '|'
+                };
+                println!("  {line_no:>6} {marker} {}", paint(line));
+            }
+        }
+    }
+
+    if !summary.tail.is_empty() {
+        println!("\nLast {} lines:", summary.tail.len());
+        for line in &summary.tail {
+            println!("  {}", paint(line));
+        }
+    }
+}
+
+fn format_duration(secs: i64) -> String {
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+fn is_error_line(line: &str) -> bool {
+    if ZERO_COUNT.is_match(line) {
+        return false;
+    }
+    classify_line(line) == LineLevel::Error || EXTRA_ERROR.is_match(line)
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() > MAX_LINE_CHARS {
+        let cut: String = line.chars().take(MAX_LINE_CHARS).collect();
+        format!("{cut}...")
+    } else {
+        line.to_string()
+    }
+}
+
+#[derive(Default)]
+struct Scan {
+    total_lines: usize,
+    tail: VecDeque<String>,
+    excerpts: Vec<Excerpt>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Single streaming pass over a job's log segments, oldest first, as one log:
+/// keeps a tail ring buffer, merges error matches with surrounding context into
+/// excerpts, and extracts diagnostics.
+fn scan_log(segments: &[PathBuf], tail_lines: usize, context: usize) -> Result<Scan> {
+    let mut scanner = Scanner::new(tail_lines, context);
+    for segment in segments {
+        for line in logfile::open(segment)?.split(b'\n') {
+            let line = line?;
+            let line = String::from_utf8_lossy(&line);
+            scanner.feed(line.trim_end_matches('\r'));
+        }
+    }
+    Ok(scanner.finish())
+}
+
+struct Scanner {
+    tail_lines: usize,
+    context: usize,
+    scan: Scan,
+    before: VecDeque<(usize, String)>,
+    after_remaining: usize,
+    /// Index of the last rustc header still waiting for its `-->` location
+    pending_rustc: Option<usize>,
+}
+
+impl Scanner {
+    fn new(tail_lines: usize, context: usize) -> Self {
+        Self {
+            tail_lines,
+            context,
+            scan: Scan::default(),
+            before: VecDeque::with_capacity(context + 1),
+            after_remaining: 0,
+            pending_rustc: None,
+        }
+    }
+
+    fn feed(&mut self, raw: &str) {
+        self.scan.total_lines += 1;
+        let line_no = self.scan.total_lines;
+        let line = truncate_line(raw);
+
+        self.parse_diagnostic(line_no, raw);
+
+        if is_error_line(raw) {
+            self.start_or_extend(line_no, &line);
+            self.after_remaining = self.context;
+        } else if self.after_remaining > 0
+            && let Some(last) = self.scan.excerpts.last_mut()
+        {
+            last.push(line_no, line.clone());
+            self.after_remaining -= 1;
+        }
+
+        if self.context > 0 {
+            if self.before.len() == self.context {
+                self.before.pop_front();
+            }
+            self.before.push_back((line_no, line.clone()));
+        }
+
+        if self.tail_lines > 0 {
+            if self.scan.tail.len() == self.tail_lines {
+                self.scan.tail.pop_front();
+            }
+            self.scan.tail.push_back(line);
+        }
+    }
+
+    fn start_or_extend(&mut self, line_no: usize, line: &str) {
+        let first_context = line_no.saturating_sub(self.before.len());
+
+        // Merge with the previous excerpt when the context windows touch
+        if let Some(last) = self.scan.excerpts.last_mut()
+            && last.end_line + 1 >= first_context
+        {
+            for (n, l) in &self.before {
+                if *n > last.end_line {
+                    last.push(*n, l.clone());
+                }
+            }
+            last.push(line_no, line.to_string());
+            last.matches.push(line_no);
+            return;
+        }
+
+        let mut excerpt = Excerpt {
+            start_line: line_no,
+            end_line: line_no,
+            matches: vec![line_no],
+            lines: Vec::new(),
+        };
+        if let Some((n, _)) = self.before.front() {
+            excerpt.start_line = *n;
+        }
+        for (n, l) in &self.before {
+            excerpt.push(*n, l.clone());
+        }
+        excerpt.push(line_no, line.to_string());
+
+        if self.scan.excerpts.len() == MAX_EXCERPTS {
+            self.scan.excerpts.remove(0);
+        }
+        self.scan.excerpts.push(excerpt);
+    }
+
+    fn parse_diagnostic(&mut self, line_no: usize, line: &str) {
+        if let Some(d) = parse_line_diagnostic(line_no, line) {
+            self.pending_rustc = (d.tool == "rustc").then_some(self.scan.diagnostics.len());
+            if !self.scan.diagnostics.iter().any(|e| same_diagnostic(e, &d)) {
+                self.scan.diagnostics.push(d);
+            } else {
+                self.pending_rustc = None;
+            }
+            return;
+        }
+
+        // rustc prints the location on a following `--> file:line:col` line
+        if let Some(caps) = RUSTC_LOCATION.captures(line)
+            && let Some(idx) = self.pending_rustc.take()
+        {
+            let file = caps[1].to_string();
+            let line = caps[2].parse().ok();
+            let column = caps[3].parse().ok();
+            let is_duplicate = self.scan.diagnostics.iter().enumerate().any(|(i, e)| {
+                i != idx
+                    && e.tool == "rustc"
+                    && e.message == self.scan.diagnostics[idx].message
+                    && e.file.as_deref() == Some(file.as_str())
+                    && e.line == line
+            });
+            if is_duplicate {
+                self.scan.diagnostics.remove(idx);
+            } else {
+                let d = &mut self.scan.diagnostics[idx];
+                d.file = Some(file);
+                d.line = line;
+                d.column = column;
+            }
+        }
+    }
+
+    fn finish(self) -> Scan {
+        self.scan
+    }
+}
+
+fn same_diagnostic(a: &Diagnostic, b: &Diagnostic) -> bool {
+    a.tool == b.tool
+        && a.severity == b.severity
+        && a.message == b.message
+        && a.file == b.file
+        && a.line == b.line
+        && (a.file.is_some() || a.tool != "rustc")
+}
+
+/// Recognise single-line diagnostics from rustc/cargo, pytest and tsc.
+fn parse_line_diagnostic(line_no: usize, line: &str) -> Option<Diagnostic> {
+    if let Some(caps) = TSC_PAREN
+        .captures(line)
+        .or_else(|| TSC_PRETTY.captures(line))
+    {
+        return Some(Diagnostic {
+            tool: "tsc",
+            severity: caps[4].to_string(),
+            code: Some(caps[5].to_string()),
+            message: caps[6].to_string(),
+            file: Some(caps[1].to_string()),
+            line: caps[2].parse().ok(),
+            column: caps[3].parse().ok(),
+            log_line: line_no,
+        });
+    }
+
+    if let Some(caps) = PYTEST_FAILED.captures(line) {
+        let file = caps[2].to_string();
+        if !file.ends_with(".py") {
+            return None;
+        }
+        let test = caps.get(3).map(|m| m.as_str().to_string());
+        let message = match (caps.get(4), &test) {
+            (Some(m), Some(t)) => format!("{t}: {}", m.as_str()),
+            (Some(m), None) => m.as_str().to_string(),
+            (None, Some(t)) => t.clone(),
+            (None, None) => caps[1].to_lowercase(),
+        };
+        return Some(Diagnostic {
+            tool: "pytest",
+            severity: if &caps[1] == "FAILED" {
+                "failed".to_string()
+            } else {
+                "error".to_string()
+            },
+            code: None,
+            message,
+            file: Some(file),
+            line: None,
+            column: None,
+            log_line: line_no,
+        });
+    }
+
+    if let Some(caps) = RUSTC_HEADER.captures(line) {
+        let message = caps[3].to_string();
+        // cargo's trailing summaries aren't diagnostics of their own
+        if message.starts_with("aborting due to")
+            || message.starts_with("could not compile")
+            || (&caps[1] == "warning" && message.contains(" generated "))
+        {
+            return None;
+        }
+        return Some(Diagnostic {
+            tool: "rustc",
+            severity: caps[1].to_string(),
+            code: caps.get(2).map(|m| m.as_str().to_string()),
+            message,
+            file: None,
+            line: None,
+            column: None,
+            log_line: line_no,
+        });
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(text: &str, tail: usize, context: usize) -> Scan {
+        let mut scanner = Scanner::new(tail, context);
+        for line in text.lines() {
+            scanner.feed(line);
+        }
+        scanner.finish()
+    }
+
+    #[test]
+    fn test_is_error_line() {
+        assert!(is_error_line("error: linker failed"));
+        assert!(is_error_line("FAILED tests/test_a.py::test_x"));
+        assert!(is_error_line("Traceback (most recent call last):"));
+        assert!(!is_error_line("test result: ok. 57 passed; 0 failed"));
+        assert!(!is_error_line("Compiling foo v0.1.0"));
+    }
+
+    #[test]
+    fn test_tail_keeps_last_lines() {
+        let s = scan("a\nb\nc\nd", 2, 0);
+        assert_eq!(s.total_lines, 4);
+        assert_eq!(s.tail, ["c", "d"]);
+    }
+
+    #[test]
+    fn test_scan_includes_rotated_segments() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        std::fs::write(paths.log_file("abc1"), "error: early\nok\n").unwrap();
+        logfile::rotate(&paths, "abc1", 3).unwrap();
+        std::fs::write(paths.log_file("abc1"), "done\n").unwrap();
+
+        let s = scan_log(&logfile::segments(&paths, "abc1"), 1, 0).unwrap();
+        assert_eq!(s.total_lines, 3);
+        assert_eq!(s.excerpts[0].matches, [1]);
+        assert_eq!(s.tail, ["done"]);
+    }
+
+    #[test]
+    fn test_excerpt_with_context() {
+        let s = scan("1\n2\n3\nerror: boom\n5\n6\n7\n8", 0, 2);
+        assert_eq!(s.excerpts.len(), 1);
+        let e = &s.excerpts[0];
+        assert_eq!((e.start_line, e.end_line), (2, 6));
+        assert_eq!(e.matches, [4]);
+        assert_eq!(e.lines, ["2", "3", "error: boom", "5", "6"]);
+    }
+
+    #[test]
+    fn test_nearby_errors_merge() {
+        let s = scan(
+            "error: a\n2\n3\n4\nerror: b\n6\n7\n8\n9\n10\nerror: c",
+            0,
+            2,
+        );
+        assert_eq!(s.excerpts.len(), 2);
+        assert_eq!(s.excerpts[0].matches, [1, 5]);
+        assert_eq!((s.excerpts[0].start_line, s.excerpts[0].end_line), (1, 7));
+        assert_eq!(
+            s.excerpts[0].lines,
+            ["error: a", "2", "3", "4", "error: b", "6", "7"]
+        );
+        assert_eq!((s.excerpts[1].start_line, s.excerpts[1].end_line), (9, 11));
+    }
+
+    #[test]
+    fn test_rustc_diagnostics() {
+        let log = "\
+   Compiling foo v0.1.0
+error[E0308]: mismatched types
+  --> src/main.rs:10:5
+   |
+warning: unused variable: `x`
+ --> src/lib.rs:3:9
+error: aborting due to 1 previous error
+warning: `foo` (bin \"foo\") generated 1 warning
+error: could not compile `foo`";
+        let s = scan(log, 0, 0);
+        assert_eq!(s.diagnostics.len(), 2);
+        let e = &s.diagnostics[0];
+        assert_eq!(e.tool, "rustc");
+        assert_eq!(e.severity, "error");
+        assert_eq!(e.code.as_deref(), Some("E0308"));
+        assert_eq!(e.file.as_deref(), Some("src/main.rs"));
+        assert_eq!((e.line, e.column), (Some(10), Some(5)));
+        assert_eq!(s.diagnostics[1].severity, "warning");
+        assert_eq!(s.diagnostics[1].file.as_deref(), Some("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_rustc_duplicate_diagnostics_collapse() {
+        let log = "\
+warning: unused import
+ --> src/a.rs:1:5
+warning: unused import
+ --> src/a.rs:1:5";
+        let s = scan(log, 0, 0);
+        assert_eq!(s.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_pytest_diagnostics() {
+        let log = "\
+FAILED tests/test_api.py::test_login - AssertionError: assert 401 == 200
+ERROR tests/test_db.py - ModuleNotFoundError: No module named 'psycopg'";
+        let s = scan(log, 0, 0);
+        assert_eq!(s.diagnostics.len(), 2);
+        assert_eq!(s.diagnostics[0].tool, "pytest");
+        assert_eq!(s.diagnostics[0].severity, "failed");
+        assert_eq!(s.diagnostics[0].file.as_deref(), Some("tests/test_api.py"));
+        assert_eq!(
+            s.diagnostics[0].message,
+            "test_login: AssertionError: assert 401 == 200"
+        );
+        assert_eq!(s.diagnostics[1].severity, "error");
+    }
+
+    #[test]
+    fn test_tsc_diagnostics() {
+        let log = "\
+src/app.ts(12,7): error TS2322: Type 'string' is not assignable to type 'number'.
+src/util.ts:3:1 - error TS2304: Cannot find name 'foo'.";
+        let s = scan(log, 0, 0);
+        assert_eq!(s.diagnostics.len(), 2);
+        assert_eq!(s.diagnostics[0].code.as_deref(), Some("TS2322"));
+        assert_eq!(s.diagnostics[0].line, Some(12));
+        assert_eq!(s.diagnostics[1].file.as_deref(), Some("src/util.ts"));
+        assert_eq!(s.diagnostics[1].column, Some(1));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(5), "5s");
+        assert_eq!(format_duration(83), "1m 23s");
+        assert_eq!(format_duration(7320), "2h 2m");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::client::DaemonClient;
+use crate::commands::list::truncate;
+use crate::commands::run;
+use crate::core::ipc::RunSpec;
+use crate::core::{Task, TaskFile, UserError, detect_project, parse_duration};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Load the current project's `.jb.toml`.
+fn load() -> Result<(PathBuf, TaskFile)> {
+    let project = detect_project(&std::env::current_dir()?);
+    let Some(file) = TaskFile::load(&project).map_err(|e| UserError::new(format!("{e:#}")))? else {
+        bail!(UserError::new(format!(
+            "No {} in {}",
+            crate::core::taskfile::FILE_NAME,
+            project.display()
+        )));
+    };
+    Ok((project, file))
+}
+
+/// Spec for one task, run from its cwd within `project`.
+fn task_spec(project: &Path, task: &Task) -> Result<RunSpec> {
+    let cwd = task
+        .cwd
+        .as_ref()
+        .map_or_else(|| project.to_path_buf(), |dir| project.join(dir));
+    Ok(RunSpec {
+        command: task.command.clone(),
+        cwd: cwd.to_string_lossy().to_string(),
+        project: project.to_string_lossy().to_string(),
+        timeout_secs: task.timeout.as_deref().map(parse_duration).transpose()?,
+        artifacts: task.artifacts.clone(),
+        env: task.env.clone(),
+        concurrency_group: task.group.clone(),
+        ..RunSpec::default()
+    })
+}
+
+/// Run task `name` after its dependencies.
+///
+/// Each dependency is submitted as its own job and the target waits on them
+/// in the daemon, so this returns as soon as everything is queued. Fields set
+/// in `overrides` (from `jb run @name` flags) apply to the target task only,
+/// except the priority, which the dependencies share so they aren't left behind.
+pub async fn run(
+    name: &str,
+    overrides: RunSpec,
+    wait: bool,
+    follow: bool,
+    json: bool,
+) -> Result<()> {
+    let (project, file) = load()?;
+    let plan = file.plan(name).map_err(|_| {
+        let names: Vec<_> = file.tasks.keys().map(String::as_str).collect();
+        UserError::new(format!(
+            "Unknown task '{name}' (available: {})",
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        ))
+    })?;
+
+    let (target, deps) = plan.split_last().expect("plan includes the task itself");
+
+    // Check every spec, the target's overrides included, before starting anything
+    let task = &file.tasks[target];
+    let base = task_spec(&project, task)?;
+    let mut artifacts = base.artifacts.clone();
+    artifacts.extend(overrides.artifacts);
+    let mut spec = RunSpec {
+        name: overrides.name,
+        timeout_secs: overrides.timeout_secs.or(base.timeout_secs),
+        context: overrides.context,
+        idempotency_key: overrides.idempotency_key,
+        priority: overrides.priority,
+        artifacts,
+        ports: overrides.ports,
+        ready_when: overrides.ready_when,
+        ..base
+    };
+    spec.validate()?;
+    let dep_specs = deps
+        .iter()
+        .map(|dep| {
+            let spec = RunSpec {
+                priority: overrides.priority,
+                ..task_spec(&project, &file.tasks[dep])?
+            };
+            spec.validate()?;
+            Ok(spec)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut job_ids: HashMap<&str, String> = HashMap::new();
+    if !deps.is_empty() {
+        let client = DaemonClient::connect_or_start().await?;
+        for (dep, dep_spec) in deps.iter().zip(dep_specs) {
+            let dep_spec = RunSpec {
+                depends_on: file.tasks[dep]
+                    .depends_on
+                    .iter()
+                    .map(|d| job_ids[d.as_str()].clone())
+                    .collect(),
+                ..dep_spec
+            };
+            let job = client
+                .run(dep_spec)
+                .await
+                .with_context(|| format!("Failed to start task '{dep}'"))?;
+            eprintln!("{dep}: {}", job.short_id());
+            job_ids.insert(dep, job.id);
+        }
+    }
+
+    spec.depends_on = task
+        .depends_on
+        .iter()
+        .map(|d| job_ids[d.as_str()].clone())
+        .collect();
+    run::submit(spec, wait, follow, json).await
+}
+
+/// Print the tasks defined in the current project.
+pub fn list(json: bool) -> Result<()> {
+    let (_, file) = load()?;
+
+    if json {
+        let tasks: Vec<_> = file
+            .tasks
+            .iter()
+            .map(|(name, task)| {
+                let mut value = serde_json::to_value(task).unwrap_or_default();
+                value["name"] = name.clone().into();
+                value
+            })
+            .collect();
+        println!("{}", serde_json::to_string(&tasks)?);
+        return Ok(());
+    }
+
+    if file.tasks.is_empty() {
+        println!("No tasks defined");
+        return Ok(());
+    }
+
+    println!(
+        "{:<14} {:<30} {:<20} {:<10} DESCRIPTION",
+        "NAME", "COMMAND", "DEPENDS", "GROUP"
+    );
+    for (name, task) in &file.tasks {
+        let depends = if task.depends_on.is_empty() {
+            "-".to_string()
+        } else {
+            task.depends_on.join(",")
+        };
+        println!(
+            "{:<14} {:<30} {:<20} {:<10} {}",
+            truncate(name, 14),
+            truncate(&task.command, 28),
+            truncate(&depends, 20),
+            truncate(task.group.as_deref().unwrap_or("-"), 10),
+            task.description.as_deref().unwrap_or("")
+        );
+    }
+
+    Ok(())
+}
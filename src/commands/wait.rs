@@ -1,10 +1,36 @@
-use crate::client::DaemonClient;
-use crate::core::ipc::{Request, Response};
-use crate::core::{Database, Job, Paths, parse_duration};
+use crate::client::{self, DaemonClient};
+use crate::commands::list::{format_status, truncate};
+use crate::core::{
+    Database, Job, JobFilter, Paths, Status, UserError, detect_project, parse_duration,
+};
 use anyhow::Result;
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
 
-pub async fn execute(id: String, timeout: Option<String>) -> Result<()> {
+/// Jobs for `jb wait` to wait on: explicit IDs plus any matching the filters
+#[derive(Debug, Default)]
+pub struct Targets {
+    pub ids: Vec<String>,
+    /// The latest job of each name matching this glob
+    pub name: Option<String>,
+    /// Running and queued jobs in this project (the latest of each name if
+    /// `name` is also given)
+    pub project: Option<PathBuf>,
+    /// Every job of a `jb batch` group
+    pub group: Option<String>,
+}
+
+impl Targets {
+    /// Whether this is a single job, waited on the way `jb wait <id>` always has
+    #[must_use]
+    pub fn is_single(&self) -> bool {
+        self.ids.len() == 1 && self.name.is_none() && self.project.is_none() && self.group.is_none()
+    }
+}
+
+pub async fn execute(id: String, timeout: Option<String>, ready: bool, json: bool) -> Result<()> {
     let paths = Paths::new();
     let db = Database::open(&paths)?;
 
@@ -13,34 +39,43 @@ pub async fn execute(id: String, timeout: Option<String>) -> Result<()> {
 
     let job = db.resolve(&id)?;
 
-    // If already terminal, return immediately
-    if job.status.is_terminal() {
-        handle_terminal(&job);
+    if ready && job.ready_when.is_empty() {
+        anyhow::bail!(UserError::new(format!(
+            "Job {} has no readiness checks (use --ready-when-* on jb run)",
+            job.short_id()
+        )));
+    }
+
+    // If already done waiting, return immediately
+    if check_done(&job, ready, json)? {
         return Ok(());
     }
 
     let timeout_secs = timeout.map(|t| parse_duration(&t)).transpose()?;
 
     // Wait via daemon
-    if let Ok(mut client) = DaemonClient::connect_or_start().await {
-        let request = Request::Wait {
-            id: job.id.clone(),
-            timeout_secs,
+    if let Ok(client) = DaemonClient::connect_or_start().await {
+        let timeout = timeout_secs.map(Duration::from_secs);
+        let result = if ready {
+            client.wait_ready(&job.id, timeout).await
+        } else {
+            client.wait(&job.id, timeout).await
         };
 
-        match client.send(request).await? {
-            Response::Job(completed) => {
-                handle_terminal(&completed);
+        match result {
+            Ok(completed) => {
+                if ready {
+                    handle_ready(&completed, json)?;
+                } else {
+                    handle_terminal(&completed, json)?;
+                }
                 return Ok(());
             }
-            Response::Error(e) => {
-                if e.contains("timed out") {
-                    eprintln!("Timeout - job still running");
-                    std::process::exit(124);
-                }
-                anyhow::bail!("{e}");
+            Err(e) if client::is_daemon_error(&e, "timed out") => {
+                eprintln!("Timeout - job still running");
+                std::process::exit(124);
             }
-            _ => {}
+            Err(e) => return Err(e),
         }
     }
 
@@ -50,8 +85,7 @@ pub async fn execute(id: String, timeout: Option<String>) -> Result<()> {
     loop {
         let current = db.get(&job.id)?.unwrap();
 
-        if current.status.is_terminal() {
-            handle_terminal(&current);
+        if check_done(&current, ready, json)? {
             return Ok(());
         }
 
@@ -66,7 +100,42 @@ pub async fn execute(id: String, timeout: Option<String>) -> Result<()> {
     }
 }
 
-fn handle_terminal(job: &Job) {
+/// Report the job and return true if the wait condition is already met.
+fn check_done(job: &Job, ready: bool, json: bool) -> Result<bool> {
+    if ready {
+        if job.ready_at.is_some() {
+            handle_ready(job, json)?;
+            return Ok(true);
+        }
+        if job.status.is_terminal() {
+            anyhow::bail!(UserError::new(format!(
+                "Job {} exited before becoming ready ({})",
+                job.short_id(),
+                job.status
+            )));
+        }
+    } else if job.status.is_terminal() {
+        handle_terminal(job, json)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn handle_ready(job: &Job, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(job)?);
+    } else {
+        println!("Ready");
+    }
+    Ok(())
+}
+
+fn handle_terminal(job: &Job, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(job)?);
+        return Ok(());
+    }
+
     match job.exit_code {
         Some(0) => {
             println!("Completed (exit 0)");
@@ -79,4 +148,243 @@ fn handle_terminal(job: &Job) {
             println!("{}", job.status);
         }
     }
+    Ok(())
+}
+
+/// Wait on several jobs, until all of them are done or (with `any`) the first
+/// one is. Prints a table of the jobs and exits 1 if any that finished failed,
+/// or 124 on timeout.
+pub async fn execute_many(
+    targets: Targets,
+    any: bool,
+    timeout: Option<String>,
+    ready: bool,
+    json: bool,
+) -> Result<()> {
+    let paths = Paths::new();
+    let db = Database::open(&paths)?;
+    db.recover_orphans();
+
+    let jobs = select(&db, targets)?;
+    if ready && let Some(job) = jobs.iter().find(|job| job.ready_when.is_empty()) {
+        anyhow::bail!(UserError::new(format!(
+            "Job {} has no readiness checks (use --ready-when-* on jb run)",
+            job.short_id()
+        )));
+    }
+    let deadline = timeout
+        .map(|t| parse_duration(&t))
+        .transpose()?
+        .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+
+    // One connection, one wait per job; dropping the set cancels those left
+    let client = DaemonClient::connect_or_start().await?;
+    let mut waits = JoinSet::new();
+    for (i, job) in jobs.iter().enumerate() {
+        let (client, id) = (client.clone(), job.id.clone());
+        waits.spawn(async move {
+            let result = if ready {
+                client.wait_ready(&id, None).await
+            } else {
+                client.wait(&id, None).await
+            };
+            (i, result)
+        });
+    }
+
+    let mut done = vec![false; jobs.len()];
+    let mut failed = false;
+    let mut timed_out = false;
+    loop {
+        let next = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, waits.join_next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    timed_out = true;
+                    break;
+                }
+            },
+            None => waits.join_next().await,
+        };
+        let Some(joined) = next else { break };
+        let (i, result) = joined?;
+        match result {
+            Ok(job) => failed |= !ready && job.status != Status::Completed,
+            // Exited before becoming ready
+            Err(e) if ready && e.downcast_ref::<client::DaemonError>().is_some() => failed = true,
+            Err(e) => return Err(e),
+        }
+        done[i] = true;
+        if any {
+            break;
+        }
+    }
+    drop(waits);
+
+    // Fresh from the DB, so jobs still being waited on show where they got to
+    let jobs = jobs
+        .into_iter()
+        .map(|job| Ok(db.get(&job.id)?.unwrap_or(job)))
+        .collect::<Result<Vec<_>>>()?;
+    if json {
+        println!("{}", serde_json::to_string(&jobs)?);
+    } else {
+        print_table(&jobs, ready);
+    }
+
+    if timed_out {
+        let waiting = done.iter().filter(|done| !**done).count();
+        eprintln!("Timeout - {waiting} of {} jobs still running", jobs.len());
+        std::process::exit(124);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The jobs `targets` names, without duplicates, in the order given.
+fn select(db: &Database, targets: Targets) -> Result<Vec<Job>> {
+    let mut jobs = targets
+        .ids
+        .iter()
+        .map(|id| db.resolve(id))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(group) = targets.group {
+        let group = db.resolve_group(&group)?;
+        jobs.extend(db.group_jobs(&group.id)?);
+    }
+
+    let project = targets
+        .project
+        .map(|p| -> Result<PathBuf> { Ok(detect_project(&std::fs::canonicalize(p)?)) })
+        .transpose()?;
+    if let Some(name) = targets.name {
+        let filter = JobFilter {
+            name: Some(name.clone()),
+            project,
+            ..JobFilter::default()
+        };
+        // Newest first, so the first job seen with a name is its latest run
+        let mut names = HashSet::new();
+        let mut latest: Vec<Job> = db
+            .find(&filter)?
+            .into_iter()
+            .filter(|job| names.insert(job.name.clone()))
+            .collect();
+        if latest.is_empty() {
+            anyhow::bail!(UserError::new(format!("No jobs named '{name}'")));
+        }
+        latest.reverse();
+        jobs.extend(latest);
+    } else if let Some(project) = project {
+        let mut active = Vec::new();
+        for status in [Status::Running, Status::Pending] {
+            active.extend(db.find(&JobFilter {
+                status: Some(status),
+                project: Some(project.clone()),
+                ..JobFilter::default()
+            })?);
+        }
+        if active.is_empty() {
+            anyhow::bail!(UserError::new(format!(
+                "No running or queued jobs in {}",
+                project.display()
+            )));
+        }
+        active.sort_by_key(|job| job.created_at);
+        jobs.extend(active);
+    }
+
+    let mut seen = HashSet::new();
+    jobs.retain(|job| seen.insert(job.id.clone()));
+    Ok(jobs)
+}
+
+/// Jobs as a table, with a READY column if `ready`
+pub fn print_table(jobs: &[Job], ready: bool) {
+    let ready_header = if ready {
+        format!("{:<6} ", "READY")
+    } else {
+        String::new()
+    };
+    println!(
+        "{:<10} {:<12} {:<6} {:<12} {ready_header}COMMAND",
+        "ID", "STATUS", "EXIT", "NAME"
+    );
+    for job in jobs {
+        let exit = job
+            .exit_code
+            .map_or_else(|| "-".to_string(), |c| c.to_string());
+        let ready_column = match (ready, job.ready_at) {
+            (false, _) => String::new(),
+            (true, Some(_)) => format!("{:<6} ", "yes"),
+            (true, None) => format!("{:<6} ", "-"),
+        };
+        println!(
+            "{:<10} {} {:<6} {:<12} {ready_column}{}",
+            job.short_id(),
+            format_status(job.status),
+            exit,
+            truncate(job.name.as_deref().unwrap_or("-"), 10),
+            truncate(&job.command, 40),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_by_name_and_project() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let db = Database::open(&Paths::with_root(tmp.path().to_path_buf())).unwrap();
+        let project = detect_project(&std::fs::canonicalize(tmp.path()).unwrap());
+        let start = chrono::Utc::now();
+        for (i, (id, name, status)) in [
+            ("old1", Some("test-a"), Status::Failed),
+            ("new1", Some("test-a"), Status::Running),
+            ("new2", Some("test-b"), Status::Completed),
+            ("lint", Some("lint"), Status::Pending),
+            ("anon", None, Status::Running),
+            ("done", None, Status::Completed),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut job = Job::new(id.into(), "true".into(), project.clone(), project.clone());
+            job.name = name.map(Into::into);
+            job.status = status;
+            job.created_at = start + chrono::Duration::seconds(i64::try_from(i).unwrap());
+            db.insert(&job).unwrap();
+        }
+        let ids = |targets| -> Vec<String> {
+            select(&db, targets)
+                .unwrap()
+                .into_iter()
+                .map(|job| job.id)
+                .collect()
+        };
+
+        // Latest run of each name, oldest first
+        let by_name = Targets {
+            name: Some("test-*".into()),
+            ..Targets::default()
+        };
+        assert_eq!(ids(by_name), ["new1", "new2"]);
+
+        let by_project = Targets {
+            ids: vec!["done".into(), "anon".into()],
+            project: Some(tmp.path().to_path_buf()),
+            ..Targets::default()
+        };
+        assert_eq!(ids(by_project), ["done", "anon", "new1", "lint"]);
+
+        let nothing = Targets {
+            name: Some("build-*".into()),
+            ..Targets::default()
+        };
+        assert!(select(&db, nothing).is_err());
+    }
 }
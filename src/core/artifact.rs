@@ -0,0 +1,244 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+/// A file copied out of a job's working directory after it finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub job_id: String,
+    /// Path relative to the job's cwd (and to its artifact directory)
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub collected_at: DateTime<Utc>,
+}
+
+/// Reject patterns that could match files outside the job's cwd.
+pub fn validate_pattern(pattern: &str) -> Result<()> {
+    let path = Path::new(pattern);
+    if path.is_absolute() || path.components().any(|c| matches!(c, Component::ParentDir)) {
+        bail!("Artifact pattern must be relative to the job directory: {pattern}");
+    }
+    glob::Pattern::new(pattern)?;
+    Ok(())
+}
+
+/// What `collect` copied, and the files it couldn't
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub artifacts: Vec<Artifact>,
+    /// `path: reason` for each matching file that was skipped
+    pub errors: Vec<String>,
+}
+
+/// Copy files matching `patterns` from `cwd` into `dest`, preserving relative paths.
+/// Patterns that match nothing are skipped; directories are ignored, as are
+/// symlinks that lead outside `cwd`. A file that can't be copied is skipped
+/// and reported in `errors` without stopping the rest.
+pub fn collect(job_id: &str, patterns: &[String], cwd: &Path, dest: &Path) -> Result<Collected> {
+    let mut collected = Collected::default();
+    let Some(cwd_str) = cwd.to_str() else {
+        bail!("Job directory isn't valid UTF-8: {}", cwd.display());
+    };
+    let canonical_cwd = cwd.canonicalize()?;
+
+    for pattern in patterns {
+        validate_pattern(pattern)?;
+        // The cwd may itself contain glob metacharacters
+        let full = Path::new(&glob::Pattern::escape(cwd_str)).join(pattern);
+        let Some(full) = full.to_str() else {
+            continue;
+        };
+
+        for entry in glob::glob(full)? {
+            let Ok(source) = entry else {
+                continue;
+            };
+            if !source.is_file() {
+                continue;
+            }
+            let Ok(relative) = source.strip_prefix(cwd) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().to_string();
+            if collected.artifacts.iter().any(|a| a.path == relative) {
+                continue;
+            }
+            if !source
+                .canonicalize()
+                .is_ok_and(|real| real.starts_with(&canonical_cwd))
+            {
+                collected
+                    .errors
+                    .push(format!("{relative}: links outside the job directory"));
+                continue;
+            }
+
+            match copy_artifact(job_id, &source, dest, relative.clone()) {
+                Ok(artifact) => collected.artifacts.push(artifact),
+                Err(e) => collected.errors.push(format!("{relative}: {e}")),
+            }
+        }
+    }
+
+    Ok(collected)
+}
+
+fn copy_artifact(job_id: &str, source: &Path, dest: &Path, relative: String) -> Result<Artifact> {
+    let target = dest.join(&relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let size = std::fs::copy(source, &target)?;
+    Ok(Artifact {
+        job_id: job_id.to_string(),
+        path: relative,
+        size,
+        sha256: sha256_file(&target)?,
+        collected_at: Utc::now(),
+    })
+}
+
+/// Copy stored artifacts into `dest`, returning the written paths.
+pub fn extract(artifacts: &[Artifact], store: &Path, dest: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::with_capacity(artifacts.len());
+    for artifact in artifacts {
+        let target = dest.join(&artifact.path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(store.join(&artifact.path), &target)?;
+        written.push(target);
+    }
+    Ok(written)
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    use std::fmt::Write;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+
+    let mut hex = String::with_capacity(64);
+    for byte in hasher.finalize() {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    Ok(hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_pattern() {
+        assert!(validate_pattern("target/report.html").is_ok());
+        assert!(validate_pattern("coverage/*.lcov").is_ok());
+        assert!(validate_pattern("/etc/passwd").is_err());
+        assert!(validate_pattern("../secrets/*").is_err());
+        assert!(validate_pattern("a/../../b").is_err());
+    }
+
+    #[test]
+    fn test_collect_copies_matches() {
+        let cwd = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        std::fs::create_dir_all(cwd.path().join("coverage")).unwrap();
+        std::fs::write(cwd.path().join("coverage/a.lcov"), "a").unwrap();
+        std::fs::write(cwd.path().join("coverage/b.lcov"), "bb").unwrap();
+        std::fs::write(cwd.path().join("coverage/c.txt"), "c").unwrap();
+        std::fs::write(cwd.path().join("report.html"), "<html>").unwrap();
+
+        let patterns = vec![
+            "coverage/*.lcov".to_string(),
+            "report.html".to_string(),
+            "missing/*".to_string(),
+        ];
+        let mut artifacts = collect("abc1", &patterns, cwd.path(), dest.path())
+            .unwrap()
+            .artifacts;
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let paths: Vec<_> = artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["coverage/a.lcov", "coverage/b.lcov", "report.html"]);
+        assert_eq!(artifacts[1].size, 2);
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("coverage/b.lcov")).unwrap(),
+            "bb"
+        );
+    }
+
+    #[test]
+    fn test_collect_skips_duplicates() {
+        let cwd = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        std::fs::write(cwd.path().join("out.log"), "x").unwrap();
+
+        let patterns = vec!["out.log".to_string(), "*.log".to_string()];
+        let artifacts = collect("abc1", &patterns, cwd.path(), dest.path())
+            .unwrap()
+            .artifacts;
+        assert_eq!(artifacts.len(), 1);
+    }
+
+    #[test]
+    fn test_collect_escapes_cwd_and_skips_outside_links() {
+        let root = TempDir::new().unwrap();
+        let cwd = root.path().join("proj [1]");
+        let dest = TempDir::new().unwrap();
+        std::fs::create_dir_all(&cwd).unwrap();
+        std::fs::write(cwd.join("out.txt"), "ok").unwrap();
+        std::fs::write(root.path().join("secret.txt"), "no").unwrap();
+        std::os::unix::fs::symlink(root.path().join("secret.txt"), cwd.join("leak.txt")).unwrap();
+        std::os::unix::fs::symlink(cwd.join("out.txt"), cwd.join("alias.txt")).unwrap();
+
+        let collected = collect("abc1", &["*.txt".to_string()], &cwd, dest.path()).unwrap();
+        let mut paths: Vec<_> = collected
+            .artifacts
+            .iter()
+            .map(|a| a.path.as_str())
+            .collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["alias.txt", "out.txt"]);
+        assert_eq!(collected.errors.len(), 1);
+        assert!(collected.errors[0].starts_with("leak.txt"));
+        assert!(!dest.path().join("leak.txt").exists());
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("f");
+        std::fs::write(&path, "hello").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+
+    #[test]
+    fn test_extract_roundtrip() {
+        let cwd = TempDir::new().unwrap();
+        let store = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        std::fs::create_dir_all(cwd.path().join("target")).unwrap();
+        std::fs::write(cwd.path().join("target/report.html"), "ok").unwrap();
+
+        let artifacts = collect(
+            "abc1",
+            &["target/report.html".to_string()],
+            cwd.path(),
+            store.path(),
+        )
+        .unwrap()
+        .artifacts;
+        let written = extract(&artifacts, store.path(), out.path()).unwrap();
+
+        assert_eq!(written, vec![out.path().join("target/report.html")]);
+        assert_eq!(std::fs::read_to_string(&written[0]).unwrap(), "ok");
+    }
+}
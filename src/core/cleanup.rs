@@ -0,0 +1,86 @@
+use crate::core::{Database, Paths, Status};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// Delete finished jobs created before `before` (optionally only those with
+/// `status`), then remove log, result, exit and artifact files of jobs that no
+/// longer exist. Returns the number of jobs deleted.
+pub fn purge(
+    paths: &Paths,
+    db: &Database,
+    before: DateTime<Utc>,
+    status: Option<Status>,
+) -> Result<usize> {
+    let count = db.delete_old(before, status)?;
+
+    let jobs = db.list(None, None)?;
+    let job_ids: HashSet<_> = jobs.iter().map(|j| j.id.as_str()).collect();
+
+    // Files are named `<id>.<ext>` (rotated logs: `<id>.log.N[.gz]`)
+    for dir in [paths.logs_dir(), paths.results_dir(), paths.exits_dir()] {
+        if !dir.exists() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name().and_then(|s| s.to_str())
+                && let Some((id, _)) = name.split_once('.')
+                && !job_ids.contains(id)
+            {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    // Artifact directories are named by job ID
+    let artifacts_dir = paths.artifacts_dir();
+    if artifacts_dir.exists() {
+        for entry in std::fs::read_dir(&artifacts_dir)? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name().and_then(|s| s.to_str())
+                && !job_ids.contains(name)
+            {
+                let _ = std::fs::remove_dir_all(&path);
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Job;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_purge_removes_files_of_deleted_jobs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        let db = Database::open(&paths).unwrap();
+
+        let mut job = Job::new(
+            "keep".to_string(),
+            "true".to_string(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/project"),
+        );
+        job.status = Status::Running;
+        db.insert(&job).unwrap();
+
+        for name in ["keep.log", "keep.log.1", "gone.log", "gone.log.2.gz"] {
+            std::fs::write(paths.logs_dir().join(name), "x").unwrap();
+        }
+        std::fs::create_dir_all(paths.job_artifacts_dir("gone")).unwrap();
+
+        purge(&paths, &db, Utc::now(), None).unwrap();
+
+        assert!(paths.logs_dir().join("keep.log").exists());
+        assert!(paths.logs_dir().join("keep.log.1").exists());
+        assert!(!paths.logs_dir().join("gone.log").exists());
+        assert!(!paths.logs_dir().join("gone.log.2.gz").exists());
+        assert!(!paths.job_artifacts_dir("gone").exists());
+    }
+}
@@ -0,0 +1,388 @@
+//! User configuration from `~/.jb/config.toml` (or `$JB_HOME/config.toml`).
+//!
+//! ```toml
+//! [run]
+//! timeout = "1h"       # default for jobs started without --timeout
+//! shell = "bash"       # runs each job's command with `<shell> -c`; no arguments
+//!
+//! [list]
+//! limit = 20
+//!
+//! [logs]
+//! max_size = "100M"    # rotate a running job's log past this size
+//! keep = 3             # rotated segments kept per job
+//!
+//! [retention]
+//! max_age = "7d"       # default for `jb clean`
+//! auto = true          # daemon cleans up hourly
+//!
+//! [concurrency]
+//! max_jobs = 8         # running jobs at once; the rest queue
+//! max_per_project = 4
+//!
+//! [stop]
+//! grace_period = "5s"  # SIGTERM to SIGKILL
+//!
+//! [ui]
+//! color = "auto"       # auto, always, never
+//!
+//! [http]
+//! socket = true        # HTTP API on ~/.jb/http.sock
+//! port = 7420          # and on 127.0.0.1:7420, with the token in ~/.jb/http.token
+//! ```
+
+use crate::core::{Paths, UserError, parse_duration, parse_size};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    pub timeout: Option<String>,
+    /// A single executable, by name on `PATH` or by path, run as
+    /// `<shell> -c <command>`. It can't take arguments of its own.
+    pub shell: String,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            shell: "sh".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListConfig {
+    pub limit: usize,
+}
+
+impl Default for ListConfig {
+    fn default() -> Self {
+        Self { limit: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Size string, e.g. "100M"; unset means logs grow without limit
+    pub max_size: Option<String>,
+    pub keep: usize,
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            keep: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub max_age: String,
+    pub auto: bool,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age: "7d".to_string(),
+            auto: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    pub max_jobs: Option<usize>,
+    pub max_per_project: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopConfig {
+    pub grace_period: String,
+}
+
+impl Default for StopConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: "2s".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub color: ColorMode,
+}
+
+/// Optional HTTP/JSON API; read when the daemon starts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub socket: bool,
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub run: RunConfig,
+    pub list: ListConfig,
+    pub logs: LogsConfig,
+    pub retention: RetentionConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub stop: StopConfig,
+    pub ui: UiConfig,
+    pub http: HttpConfig,
+}
+
+/// Settable keys and what they mean, for `jb config`
+pub const KEYS: &[(&str, &str)] = &[
+    ("run.timeout", "Default timeout for jobs (e.g. 1h)"),
+    ("run.shell", "Shell used to run commands"),
+    ("list.limit", "Jobs shown by `jb list`"),
+    ("logs.max_size", "Rotate logs past this size (e.g. 100M)"),
+    ("logs.keep", "Rotated log segments kept per job"),
+    ("retention.max_age", "Default age for `jb clean`"),
+    ("retention.auto", "Daemon removes old jobs hourly"),
+    ("concurrency.max_jobs", "Running jobs at once"),
+    ("concurrency.max_per_project", "Running jobs per project"),
+    ("stop.grace_period", "Wait after SIGTERM before SIGKILL"),
+    ("ui.color", "auto, always or never"),
+    ("http.socket", "Serve the HTTP API on http.sock"),
+    ("http.port", "Serve the HTTP API on this 127.0.0.1 port"),
+];
+
+impl Config {
+    /// Load the config file, falling back to defaults if there isn't one.
+    pub fn load(paths: &Paths) -> Result<Self> {
+        let path = paths.config_file();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        Self::parse(&text)
+            .map_err(|e| UserError::new(format!("Invalid config {}: {e:#}", path.display())).into())
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(t) = &self.run.timeout {
+            parse_duration(t).context("run.timeout")?;
+        }
+        if self.run.shell.trim().is_empty() {
+            bail!("run.shell is empty");
+        }
+        if self.run.shell.contains(char::is_whitespace) {
+            bail!(
+                "run.shell must be a single executable without arguments, got '{}'",
+                self.run.shell
+            );
+        }
+        if let Some(size) = &self.logs.max_size {
+            parse_size(size).context("logs.max_size")?;
+        }
+        parse_duration(&self.retention.max_age).context("retention.max_age")?;
+        parse_duration(&self.stop.grace_period).context("stop.grace_period")?;
+        if self.concurrency.max_jobs == Some(0) || self.concurrency.max_per_project == Some(0) {
+            bail!("concurrency limits must be at least 1");
+        }
+        if self.http.port == Some(0) {
+            bail!("http.port must be a fixed port");
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn default_timeout_secs(&self) -> Option<u64> {
+        self.run
+            .timeout
+            .as_deref()
+            .and_then(|t| parse_duration(t).ok())
+    }
+
+    #[must_use]
+    pub fn log_max_bytes(&self) -> Option<u64> {
+        self.logs
+            .max_size
+            .as_deref()
+            .and_then(|s| parse_size(s).ok())
+    }
+
+    #[must_use]
+    pub fn grace_period_secs(&self) -> u64 {
+        parse_duration(&self.stop.grace_period).unwrap_or(2)
+    }
+
+    /// Effective value of a dotted key like `list.limit`; None if unset.
+    pub fn get(&self, key: &str) -> Result<Option<toml::Value>> {
+        check_key(key)?;
+        let (section, field) = key.split_once('.').expect("keys are section.field");
+        let table = toml::Value::try_from(self)?;
+        Ok(table.get(section).and_then(|s| s.get(field)).cloned())
+    }
+
+    /// One line per key whose effective value differs in `new`,
+    /// e.g. `list.limit: 10 -> 20`.
+    #[must_use]
+    pub fn diff(&self, new: &Self) -> Vec<String> {
+        let show =
+            |v: Option<toml::Value>| v.as_ref().map_or_else(|| "unset".into(), display_value);
+        KEYS.iter()
+            .filter_map(|(key, _)| {
+                let before = self.get(key).ok().flatten();
+                let after = new.get(key).ok().flatten();
+                (before != after).then(|| format!("{key}: {} -> {}", show(before), show(after)))
+            })
+            .collect()
+    }
+
+    /// Set `key` in the config file, keeping the rest of the file as written.
+    pub fn set(paths: &Paths, key: &str, value: &str) -> Result<Self> {
+        check_key(key)?;
+        let (section, field) = key.split_once('.').expect("keys are section.field");
+
+        let path = paths.config_file();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let mut doc: toml_edit::DocumentMut = text
+            .parse()
+            .map_err(|e| UserError::new(format!("Invalid config {}: {e}", path.display())))?;
+
+        // Numbers and booleans keep their type; anything else is a string
+        let parsed = value
+            .parse::<toml_edit::Value>()
+            .ok()
+            .filter(|v| !v.is_str() && !v.is_array() && !v.is_inline_table())
+            .unwrap_or_else(|| value.into());
+        if !doc.contains_key(section) {
+            doc[section] = toml_edit::table();
+        }
+        doc[section][field] = toml_edit::value(parsed);
+
+        let new_text = doc.to_string();
+        let config = Self::parse(&new_text)
+            .map_err(|e| UserError::new(format!("Invalid value for {key}: {e:#}")))?;
+        paths.ensure_dirs()?;
+        std::fs::write(&path, new_text)?;
+        Ok(config)
+    }
+}
+
+/// A config value as the user would type it (strings unquoted)
+#[must_use]
+pub fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn check_key(key: &str) -> Result<()> {
+    if !KEYS.iter().any(|(k, _)| *k == key) {
+        let keys: Vec<_> = KEYS.iter().map(|(k, _)| *k).collect();
+        bail!(UserError::new(format!(
+            "Unknown config key '{key}' (known: {})",
+            keys.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_partial_file() {
+        let config = Config::parse("[list]\nlimit = 25\n").unwrap();
+        assert_eq!(config.list.limit, 25);
+        assert_eq!(config.run.shell, "sh");
+        assert_eq!(config.logs.keep, 3);
+        assert_eq!(config.grace_period_secs(), 2);
+        assert_eq!(config.default_timeout_secs(), None);
+        assert_eq!(config.ui.color, ColorMode::Auto);
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        assert!(Config::parse("[run]\ntimeout = \"soon\"").is_err());
+        assert!(Config::parse("[run]\nshell = \"bash -l\"").is_err());
+        assert!(Config::parse("[logs]\nmax_size = \"big\"").is_err());
+        assert!(Config::parse("[concurrency]\nmax_jobs = 0").is_err());
+        assert!(Config::parse("[ui]\ncolor = \"purple\"").is_err());
+        assert!(Config::parse("[list]\nlimt = 5").is_err());
+        assert!(Config::parse("[http]\nport = 0").is_err());
+        assert!(Config::parse("[http]\nport = 70000").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Config::default();
+        let new = Config::parse(
+            "[list]\nlimit = 20\n[concurrency]\nmax_jobs = 4\n[run]\nshell = \"bash\"",
+        )
+        .unwrap();
+        assert_eq!(
+            old.diff(&new),
+            [
+                "run.shell: sh -> bash",
+                "list.limit: 10 -> 20",
+                "concurrency.max_jobs: unset -> 4",
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        std::fs::write(paths.config_file(), "# my settings\n[list]\nlimit = 5\n").unwrap();
+
+        let config = Config::set(&paths, "run.timeout", "30m").unwrap();
+        assert_eq!(config.default_timeout_secs(), Some(1800));
+        let config = Config::set(&paths, "list.limit", "50").unwrap();
+        assert_eq!(config.list.limit, 50);
+        assert_eq!(
+            config.get("list.limit").unwrap(),
+            Some(toml::Value::Integer(50))
+        );
+        assert_eq!(config.get("concurrency.max_jobs").unwrap(), None);
+
+        // The comment survives, bad values and keys are refused
+        let text = std::fs::read_to_string(paths.config_file()).unwrap();
+        assert!(text.contains("# my settings"));
+        assert!(Config::set(&paths, "list.limit", "many").is_err());
+        assert!(Config::set(&paths, "list.colour", "x").is_err());
+        assert_eq!(Config::load(&paths).unwrap().list.limit, 50);
+    }
+}
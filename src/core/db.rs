@@ -1,21 +1,43 @@
 use crate::core::Paths;
+use crate::core::artifact::Artifact;
 use crate::core::error::UserError;
-use crate::core::job::{Job, Status};
+use crate::core::event::{Event, EventKind};
+use crate::core::group::Group;
+use crate::core::health::HealthStatus;
+use crate::core::job::{Job, RunWhen, Status};
+use crate::core::process;
 use anyhow::{Result, bail};
 use rand::Rng;
 use rusqlite::{Connection, OptionalExtension, params};
 use std::path::PathBuf;
 
+/// Criteria for [`Database::find`]. Unset fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct JobFilter {
+    pub status: Option<Status>,
+    /// Shell-style glob (`test-*`); a plain name matches exactly
+    pub name: Option<String>,
+    pub project: Option<PathBuf>,
+    /// Only jobs created at or after this time
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<usize>,
+}
+
 pub struct Database {
     conn: Connection,
+    paths: Paths,
 }
 
 impl Database {
     pub fn open(paths: &Paths) -> Result<Self> {
         paths.ensure_dirs()?;
         let conn = Connection::open(paths.database())?;
-        let db = Self { conn };
+        let db = Self {
+            conn,
+            paths: paths.clone(),
+        };
         db.init_schema()?;
+        db.migrate()?;
         Ok(db)
     }
 
@@ -36,7 +58,59 @@ impl Database {
                 finished_at TEXT,
                 timeout_secs INTEGER,
                 context TEXT,
-                idempotency_key TEXT UNIQUE
+                idempotency_key TEXT UNIQUE,
+                result TEXT,
+                artifacts TEXT,
+                ready_when TEXT,
+                ready_at TEXT,
+                restart_policy TEXT,
+                restarts INTEGER NOT NULL DEFAULT 0,
+                health TEXT,
+                health_status TEXT,
+                health_output TEXT,
+                health_checked_at TEXT,
+                ports TEXT,
+                env TEXT,
+                concurrency_group TEXT,
+                depends_on TEXT,
+                pid_fingerprint TEXT,
+                group_id TEXT,
+                run_when TEXT,
+                priority INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS groups (
+                id TEXT PRIMARY KEY,
+                name TEXT,
+                project TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS artifacts (
+                job_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                collected_at TEXT NOT NULL,
+                PRIMARY KEY (job_id, path)
+            );
+
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                detail TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_events_job_id ON events(job_id);
+
+            CREATE TABLE IF NOT EXISTS log_cursors (
+                job_id TEXT NOT NULL,
+                caller TEXT NOT NULL,
+                offset INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (job_id, caller)
             );
 
             CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);
@@ -47,13 +121,63 @@ impl Database {
         Ok(())
     }
 
+    /// Add columns introduced after the initial schema to existing databases.
+    fn migrate(&self) -> Result<()> {
+        const COLUMNS: &[(&str, &str)] = &[
+            ("result", "TEXT"),
+            ("artifacts", "TEXT"),
+            ("ready_when", "TEXT"),
+            ("ready_at", "TEXT"),
+            ("restart_policy", "TEXT"),
+            ("restarts", "INTEGER NOT NULL DEFAULT 0"),
+            ("health", "TEXT"),
+            ("health_status", "TEXT"),
+            ("health_output", "TEXT"),
+            ("health_checked_at", "TEXT"),
+            ("ports", "TEXT"),
+            ("env", "TEXT"),
+            ("concurrency_group", "TEXT"),
+            ("depends_on", "TEXT"),
+            ("pid_fingerprint", "TEXT"),
+            ("group_id", "TEXT"),
+            ("run_when", "TEXT"),
+            ("priority", "INTEGER NOT NULL DEFAULT 0"),
+        ];
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM pragma_table_info('jobs')")?;
+        let existing = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (column, decl) in COLUMNS {
+            if !existing.iter().any(|c| c == column) {
+                self.conn
+                    .execute(&format!("ALTER TABLE jobs ADD COLUMN {column} {decl}"), [])?;
+            }
+        }
+        // Needs the column, so not part of the initial schema
+        self.conn
+            .execute_batch("CREATE INDEX IF NOT EXISTS idx_jobs_group_id ON jobs(group_id);")?;
+        Ok(())
+    }
+
     pub fn insert(&self, job: &Job) -> Result<()> {
         self.conn.execute(
             r"
             INSERT INTO jobs (
                 id, name, command, status, project, cwd, pid, exit_code,
-                created_at, started_at, finished_at, timeout_secs, context, idempotency_key
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                created_at, started_at, finished_at, timeout_secs, context, idempotency_key,
+                result, artifacts, ready_when, ready_at, restart_policy, restarts,
+                health, health_status, health_output, health_checked_at, ports,
+                env, concurrency_group, depends_on, pid_fingerprint, group_id, run_when,
+                priority
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+                ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
+                ?31, ?32
+            )
             ",
             params![
                 job.id,
@@ -70,11 +194,104 @@ impl Database {
                 job.timeout_secs,
                 job.context.as_ref().map(std::string::ToString::to_string),
                 job.idempotency_key,
+                job.result.as_ref().map(std::string::ToString::to_string),
+                (!job.artifacts.is_empty())
+                    .then(|| serde_json::to_string(&job.artifacts))
+                    .transpose()?,
+                (!job.ready_when.is_empty())
+                    .then(|| serde_json::to_string(&job.ready_when))
+                    .transpose()?,
+                job.ready_at.map(|t| t.to_rfc3339()),
+                job.restart.map(|p| serde_json::to_string(&p)).transpose()?,
+                job.restarts,
+                job.health.as_ref().map(serde_json::to_string).transpose()?,
+                job.health_status.map(|h| h.as_str()),
+                job.health_output,
+                job.health_checked_at.map(|t| t.to_rfc3339()),
+                (!job.ports.is_empty())
+                    .then(|| serde_json::to_string(&job.ports))
+                    .transpose()?,
+                (!job.env.is_empty())
+                    .then(|| serde_json::to_string(&job.env))
+                    .transpose()?,
+                job.concurrency_group,
+                (!job.depends_on.is_empty())
+                    .then(|| serde_json::to_string(&job.depends_on))
+                    .transpose()?,
+                job.pid_fingerprint,
+                job.group_id,
+                (job.run_when != RunWhen::OnSuccess).then(|| job.run_when.as_str()),
+                job.priority,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_group(&self, group: &Group) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO groups (id, name, project, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                group.id,
+                group.name,
+                group.project.to_string_lossy(),
+                group.created_at.to_rfc3339(),
             ],
         )?;
         Ok(())
     }
 
+    /// Delete a group and the jobs in it, with their events, artifacts and cursors.
+    pub fn delete_group(&self, id: &str) -> Result<()> {
+        for table in ["events", "artifacts", "log_cursors"] {
+            self.conn.execute(
+                &format!(
+                    "DELETE FROM {table} WHERE job_id IN (SELECT id FROM jobs WHERE group_id = ?1)"
+                ),
+                params![id],
+            )?;
+        }
+        self.conn
+            .execute("DELETE FROM jobs WHERE group_id = ?1", params![id])?;
+        self.conn
+            .execute("DELETE FROM groups WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Resolve a group by ID (or prefix) or name; for names, the most recent.
+    pub fn resolve_group(&self, id: &str) -> Result<Group> {
+        let group = self
+            .conn
+            .query_row(
+                "SELECT * FROM groups WHERE id = ?1 OR id LIKE ?1 || '%' OR name = ?1
+                 ORDER BY id = ?1 DESC, id LIKE ?1 || '%' DESC, created_at DESC LIMIT 1",
+                params![id],
+                |row| {
+                    Ok(Group {
+                        id: row.get("id")?,
+                        name: row.get("name")?,
+                        project: PathBuf::from(row.get::<_, String>("project")?),
+                        created_at: chrono::DateTime::parse_from_rfc3339(
+                            &row.get::<_, String>("created_at")?,
+                        )
+                        .map_or_else(|_| chrono::Utc::now(), |t| t.with_timezone(&chrono::Utc)),
+                    })
+                },
+            )
+            .optional()?;
+        group.ok_or_else(|| UserError::new(format!("No group found with ID or name '{id}'")).into())
+    }
+
+    /// A group's jobs, oldest first.
+    pub fn group_jobs(&self, group_id: &str) -> Result<Vec<Job>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM jobs WHERE group_id = ?1 ORDER BY created_at, rowid")?;
+        let jobs = stmt
+            .query_map(params![group_id], Self::row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(jobs)
+    }
+
     pub fn get(&self, id: &str) -> Result<Option<Job>> {
         let job = self
             .conn
@@ -121,17 +338,38 @@ impl Database {
     }
 
     pub fn list(&self, status: Option<Status>, limit: Option<usize>) -> Result<Vec<Job>> {
+        self.find(&JobFilter {
+            status,
+            limit,
+            ..JobFilter::default()
+        })
+    }
+
+    /// List jobs matching every set field of `filter`, newest first.
+    pub fn find(&self, filter: &JobFilter) -> Result<Vec<Job>> {
         let mut sql = String::from("SELECT * FROM jobs WHERE 1=1");
         let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
-        if let Some(s) = status {
+        if let Some(s) = filter.status {
             sql.push_str(" AND status = ?");
             params_vec.push(Box::new(s.as_str().to_string()));
         }
+        if let Some(ref name) = filter.name {
+            sql.push_str(" AND name GLOB ?");
+            params_vec.push(Box::new(name.clone()));
+        }
+        if let Some(ref project) = filter.project {
+            sql.push_str(" AND project = ?");
+            params_vec.push(Box::new(project.to_string_lossy().to_string()));
+        }
+        if let Some(since) = filter.since {
+            sql.push_str(" AND created_at >= ?");
+            params_vec.push(Box::new(since.to_rfc3339()));
+        }
 
         sql.push_str(" ORDER BY created_at DESC");
 
-        if let Some(n) = limit {
+        if let Some(n) = filter.limit {
             use std::fmt::Write;
             let _ = write!(sql, " LIMIT {n}");
         }
@@ -153,27 +391,97 @@ impl Database {
         Ok(())
     }
 
-    pub fn update_started(&self, id: &str, pid: u32) -> Result<()> {
+    pub fn update_started(&self, id: &str, pid: u32, fingerprint: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "UPDATE jobs SET status = 'running', started_at = ?1, pid = ?2 WHERE id = ?3",
-            params![chrono::Utc::now().to_rfc3339(), pid, id],
+            "UPDATE jobs SET status = 'running', started_at = ?1, pid = ?2, pid_fingerprint = ?3
+             WHERE id = ?4",
+            params![chrono::Utc::now().to_rfc3339(), pid, fingerprint, id],
         )?;
         Ok(())
     }
 
-    pub fn update_finished(&self, id: &str, status: Status, exit_code: Option<i32>) -> Result<()> {
+    /// Record how a job ended. Returns false, leaving the job as it is, if
+    /// it had already finished.
+    pub fn update_finished(
+        &self,
+        id: &str,
+        status: Status,
+        exit_code: Option<i32>,
+    ) -> Result<bool> {
+        self.update_finished_at(id, status, exit_code, chrono::Utc::now())
+    }
+
+    pub fn update_finished_at(
+        &self,
+        id: &str,
+        status: Status,
+        exit_code: Option<i32>,
+        finished_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE jobs SET status = ?1, finished_at = ?2, exit_code = ?3
+             WHERE id = ?4 AND status IN ('pending', 'running')",
+            params![status.as_str(), finished_at.to_rfc3339(), exit_code, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Record a service's replacement process. Readiness must be re-established.
+    pub fn update_restarted(&self, id: &str, pid: u32, fingerprint: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET pid = ?1, pid_fingerprint = ?2, restarts = restarts + 1,
+                 ready_at = NULL, exit_code = NULL,
+                 health_status = NULL, health_output = NULL, health_checked_at = NULL
+             WHERE id = ?3",
+            params![pid, fingerprint, id],
+        )?;
+        Ok(())
+    }
+
+    /// Record a health check. `status` is None until the first conclusive result.
+    pub fn update_health(
+        &self,
+        id: &str,
+        status: Option<HealthStatus>,
+        output: &str,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE jobs SET status = ?1, finished_at = ?2, exit_code = ?3 WHERE id = ?4",
+            "UPDATE jobs SET health_status = ?1, health_output = ?2, health_checked_at = ?3
+             WHERE id = ?4",
             params![
-                status.as_str(),
+                status.map(HealthStatus::as_str),
+                output,
                 chrono::Utc::now().to_rfc3339(),
-                exit_code,
                 id
             ],
         )?;
         Ok(())
     }
 
+    pub fn update_ready(&self, id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET ready_at = ?1 WHERE id = ?2",
+            params![chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_priority(&self, id: &str, priority: i32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET priority = ?1 WHERE id = ?2",
+            params![priority, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_result(&self, id: &str, result: &serde_json::Value) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET result = ?1 WHERE id = ?2",
+            params![result.to_string(), id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete_old(
         &self,
         before: chrono::DateTime<chrono::Utc>,
@@ -192,9 +500,134 @@ impl Database {
         let params_refs: Vec<&dyn rusqlite::ToSql> =
             params_vec.iter().map(std::convert::AsRef::as_ref).collect();
         let count = self.conn.execute(&sql, params_refs.as_slice())?;
+        self.conn.execute_batch(
+            "DELETE FROM artifacts WHERE job_id NOT IN (SELECT id FROM jobs);
+             DELETE FROM log_cursors WHERE job_id NOT IN (SELECT id FROM jobs);
+             DELETE FROM events WHERE job_id NOT IN (SELECT id FROM jobs);
+             DELETE FROM groups WHERE id NOT IN
+                 (SELECT group_id FROM jobs WHERE group_id IS NOT NULL);",
+        )?;
         Ok(count)
     }
 
+    pub fn record_event(&self, job_id: &str, kind: EventKind, detail: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO events (job_id, kind, detail, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                job_id,
+                kind.as_str(),
+                detail,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Events after sequence number `after_id`, oldest first.
+    /// With a `limit`, returns the most recent `limit` of them.
+    pub fn events(
+        &self,
+        job_id: Option<&str>,
+        after_id: i64,
+        limit: Option<usize>,
+    ) -> Result<Vec<Event>> {
+        let mut sql = String::from("SELECT * FROM events WHERE id > ?1");
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(after_id)];
+
+        if let Some(id) = job_id {
+            sql.push_str(" AND job_id = ?2");
+            params_vec.push(Box::new(id.to_string()));
+        }
+        sql.push_str(" ORDER BY id DESC");
+        if let Some(n) = limit {
+            use std::fmt::Write;
+            let _ = write!(sql, " LIMIT {n}");
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> =
+            params_vec.iter().map(std::convert::AsRef::as_ref).collect();
+        let mut events = stmt
+            .query_map(params_refs.as_slice(), |row| {
+                Ok(Event {
+                    id: row.get("id")?,
+                    job_id: row.get("job_id")?,
+                    kind: row
+                        .get::<_, String>("kind")?
+                        .parse()
+                        .unwrap_or(EventKind::Finished),
+                    detail: row.get("detail")?,
+                    created_at: chrono::DateTime::parse_from_rfc3339(
+                        &row.get::<_, String>("created_at")?,
+                    )
+                    .map_or_else(|_| chrono::Utc::now(), |t| t.with_timezone(&chrono::Utc)),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        events.reverse();
+        Ok(events)
+    }
+
+    /// Byte offset a caller has already read up to in a job's log.
+    pub fn get_log_cursor(&self, job_id: &str, caller: &str) -> Result<Option<u64>> {
+        let offset = self
+            .conn
+            .query_row(
+                "SELECT offset FROM log_cursors WHERE job_id = ?1 AND caller = ?2",
+                params![job_id, caller],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(offset)
+    }
+
+    pub fn set_log_cursor(&self, job_id: &str, caller: &str, offset: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO log_cursors (job_id, caller, offset, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![job_id, caller, offset, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_artifacts(&self, artifacts: &[Artifact]) -> Result<()> {
+        let mut stmt = self.conn.prepare(
+            "INSERT OR REPLACE INTO artifacts (job_id, path, size, sha256, collected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for a in artifacts {
+            stmt.execute(params![
+                a.job_id,
+                a.path,
+                a.size,
+                a.sha256,
+                a.collected_at.to_rfc3339()
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn list_artifacts(&self, job_id: &str) -> Result<Vec<Artifact>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM artifacts WHERE job_id = ?1 ORDER BY path")?;
+        let artifacts = stmt
+            .query_map(params![job_id], |row| {
+                Ok(Artifact {
+                    job_id: row.get("job_id")?,
+                    path: row.get("path")?,
+                    size: row.get("size")?,
+                    sha256: row.get("sha256")?,
+                    collected_at: chrono::DateTime::parse_from_rfc3339(
+                        &row.get::<_, String>("collected_at")?,
+                    )
+                    .map_or_else(|_| chrono::Utc::now(), |t| t.with_timezone(&chrono::Utc)),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(artifacts)
+    }
+
     fn row_to_job(row: &rusqlite::Row) -> rusqlite::Result<Job> {
         Ok(Job {
             id: row.get("id")?,
@@ -223,6 +656,56 @@ impl Database {
                 .get::<_, Option<String>>("context")?
                 .and_then(|s| serde_json::from_str(&s).ok()),
             idempotency_key: row.get("idempotency_key")?,
+            result: row
+                .get::<_, Option<String>>("result")?
+                .and_then(|s| serde_json::from_str(&s).ok()),
+            artifacts: row
+                .get::<_, Option<String>>("artifacts")?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            ready_when: row
+                .get::<_, Option<String>>("ready_when")?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            ready_at: row
+                .get::<_, Option<String>>("ready_at")?
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|t| t.with_timezone(&chrono::Utc)),
+            restart: row
+                .get::<_, Option<String>>("restart_policy")?
+                .and_then(|s| serde_json::from_str(&s).ok()),
+            restarts: row.get("restarts")?,
+            health: row
+                .get::<_, Option<String>>("health")?
+                .and_then(|s| serde_json::from_str(&s).ok()),
+            health_status: row
+                .get::<_, Option<String>>("health_status")?
+                .and_then(|s| s.parse().ok()),
+            health_output: row.get("health_output")?,
+            health_checked_at: row
+                .get::<_, Option<String>>("health_checked_at")?
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|t| t.with_timezone(&chrono::Utc)),
+            ports: row
+                .get::<_, Option<String>>("ports")?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            env: row
+                .get::<_, Option<String>>("env")?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            concurrency_group: row.get("concurrency_group")?,
+            depends_on: row
+                .get::<_, Option<String>>("depends_on")?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            pid_fingerprint: row.get("pid_fingerprint")?,
+            group_id: row.get("group_id")?,
+            run_when: row
+                .get::<_, Option<String>>("run_when")?
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            priority: row.get("priority")?,
         })
     }
 
@@ -271,7 +754,7 @@ impl Database {
         }
 
         // Sort by created_at desc (newest first) and return first
-        by_name.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        by_name.sort_by_key(|j| std::cmp::Reverse(j.created_at));
         Ok(by_name.into_iter().next().unwrap())
     }
 
@@ -291,9 +774,68 @@ impl Database {
         bail!("Too many jobs - run `jb clean` to remove old jobs")
     }
 
-    /// Check for orphaned jobs (running/pending but process dead) and mark as interrupted.
-    /// Called on DB open to handle daemon crashes.
+    /// A new group ID: `g` and four characters, so it can't be taken for a job's
+    pub fn generate_group_id(&self) -> Result<String> {
+        const CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let mut rng = rand::rng();
+
+        for _ in 0..100 {
+            let id: String = std::iter::once('g')
+                .chain((0..4).map(|_| CHARS[rng.random_range(0..36)] as char))
+                .collect();
+            let taken: i64 = self.conn.query_row(
+                "SELECT COUNT(*) FROM groups WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )?;
+            if taken == 0 {
+                return Ok(id);
+            }
+        }
+
+        bail!("Too many groups - run `jb clean` to remove old jobs")
+    }
+
+    /// Whether a daemon other than this process is running (and so supervising services).
+    fn other_daemon_alive(&self) -> bool {
+        process::running_pid_from_file(&self.paths.pid_file())
+            .is_some_and(|pid| pid != std::process::id())
+    }
+
+    /// Record the end of a job whose process exited with nobody waiting on it.
+    ///
+    /// Uses the exit code the job's wrapper shell left in its exit file; without
+    /// one (killed by a signal, or started before exit files) the job is
+    /// interrupted. The exit file is left for the daemon or `jb clean` to
+    /// remove. Returns the status recorded.
+    pub fn finish_orphan(&self, id: &str) -> Result<Status> {
+        let (status, exit_code, finished_at) =
+            match process::read_exit_file(&self.paths.exit_file(id)) {
+                Some((0, at)) => (Status::Completed, Some(0), at),
+                Some((code, at)) => (Status::Failed, Some(code), at),
+                None => (Status::Interrupted, None, chrono::Utc::now()),
+            };
+
+        if !self.update_finished_at(id, status, exit_code, finished_at)? {
+            // Finished meanwhile
+            return Ok(self.get(id)?.map_or(status, |job| job.status));
+        }
+        let detail = match exit_code {
+            Some(code) => format!("{status} (exit {code})"),
+            None => status.to_string(),
+        };
+        self.record_event(id, EventKind::Finished, Some(&detail))?;
+        Ok(status)
+    }
+
+    /// Check for orphaned jobs (running/pending but process dead) and record how they ended.
+    /// Called by read-only commands to handle daemon crashes.
     pub fn recover_orphans(&self) {
+        if self.other_daemon_alive() {
+            // The daemon records how its jobs end, services restart between
+            // processes and pending jobs wait for their turn
+            return;
+        }
         let orphans = self
             .list(Some(Status::Running), None)
             .unwrap_or_default()
@@ -302,36 +844,17 @@ impl Database {
 
         for job in orphans {
             if let Some(pid) = job.pid
-                && is_process_alive(pid)
+                && process::is_same_process(pid, job.pid_fingerprint.as_deref())
             {
                 // Process still running - leave as is
                 continue;
             }
-            // Process dead or no PID - mark as interrupted
-            let _ = self.update_finished(&job.id, Status::Interrupted, None);
+            // Process dead or no PID
+            let _ = self.finish_orphan(&job.id);
         }
     }
 }
 
-/// Check if a process is still alive by sending signal 0.
-#[cfg(unix)]
-fn is_process_alive(pid: u32) -> bool {
-    use nix::sys::signal::kill;
-    use nix::unistd::Pid;
-
-    if pid == 0 {
-        return false;
-    }
-
-    #[allow(clippy::cast_possible_wrap)]
-    kill(Pid::from_raw(pid as i32), None).is_ok()
-}
-
-#[cfg(not(unix))]
-fn is_process_alive(_pid: u32) -> bool {
-    false
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -495,10 +1018,11 @@ mod tests {
         db.insert(&create_test_job("abc1", Status::Pending))
             .unwrap();
 
-        db.update_started("abc1", 12345).unwrap();
+        db.update_started("abc1", 12345, Some("boot:99")).unwrap();
         let job = db.get("abc1").unwrap().unwrap();
         assert_eq!(job.status, Status::Running);
         assert_eq!(job.pid, Some(12345));
+        assert_eq!(job.pid_fingerprint.as_deref(), Some("boot:99"));
         assert!(job.started_at.is_some());
     }
 
@@ -514,6 +1038,323 @@ mod tests {
         assert_eq!(job.status, Status::Completed);
         assert_eq!(job.exit_code, Some(0));
         assert!(job.finished_at.is_some());
+
+        // A finished job keeps the status it finished with
+        assert!(
+            !db.update_finished("abc1", Status::Interrupted, None)
+                .unwrap()
+        );
+        assert_eq!(db.get("abc1").unwrap().unwrap().status, Status::Completed);
+    }
+
+    #[test]
+    fn test_update_result() {
+        let (db, _tmp) = test_db();
+        db.insert(&create_test_job("abc1", Status::Completed))
+            .unwrap();
+        assert!(db.get("abc1").unwrap().unwrap().result.is_none());
+
+        let result = serde_json::json!({"passed": 12, "failed": 0});
+        db.update_result("abc1", &result).unwrap();
+        let job = db.get("abc1").unwrap().unwrap();
+        assert_eq!(job.result, Some(result));
+    }
+
+    #[test]
+    fn test_artifacts_roundtrip() {
+        let (db, _tmp) = test_db();
+        let job = create_test_job("abc1", Status::Failed)
+            .with_artifacts(vec!["coverage/*.lcov".to_string()]);
+        db.insert(&job).unwrap();
+        assert_eq!(
+            db.get("abc1").unwrap().unwrap().artifacts,
+            vec!["coverage/*.lcov".to_string()]
+        );
+
+        let artifact = Artifact {
+            job_id: "abc1".to_string(),
+            path: "coverage/a.lcov".to_string(),
+            size: 42,
+            sha256: "deadbeef".to_string(),
+            collected_at: chrono::Utc::now(),
+        };
+        db.insert_artifacts(std::slice::from_ref(&artifact))
+            .unwrap();
+
+        let stored = db.list_artifacts("abc1").unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].path, artifact.path);
+        assert_eq!(stored[0].size, 42);
+        assert!(db.list_artifacts("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_old_removes_artifacts() {
+        let (db, _tmp) = test_db();
+        db.insert(&create_test_job("abc1", Status::Completed))
+            .unwrap();
+        db.insert_artifacts(&[Artifact {
+            job_id: "abc1".to_string(),
+            path: "report.html".to_string(),
+            size: 1,
+            sha256: "00".to_string(),
+            collected_at: chrono::Utc::now(),
+        }])
+        .unwrap();
+
+        db.delete_old(chrono::Utc::now(), None).unwrap();
+        assert!(db.list_artifacts("abc1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_log_cursors_per_caller() {
+        let (db, _tmp) = test_db();
+        db.insert(&create_test_job("abc1", Status::Running))
+            .unwrap();
+
+        assert_eq!(db.get_log_cursor("abc1", "agent-a").unwrap(), None);
+        db.set_log_cursor("abc1", "agent-a", 120).unwrap();
+        db.set_log_cursor("abc1", "agent-b", 40).unwrap();
+        db.set_log_cursor("abc1", "agent-a", 300).unwrap();
+
+        assert_eq!(db.get_log_cursor("abc1", "agent-a").unwrap(), Some(300));
+        assert_eq!(db.get_log_cursor("abc1", "agent-b").unwrap(), Some(40));
+    }
+
+    #[test]
+    fn test_restart_policy_and_counter() {
+        let (db, _tmp) = test_db();
+        let policy = crate::core::RestartPolicy {
+            mode: crate::core::RestartMode::OnFailure,
+            max_restarts: Some(5),
+            delay_secs: 2,
+        };
+        let job = create_test_job("svc1", Status::Running).with_restart(policy);
+        db.insert(&job).unwrap();
+        db.update_ready("svc1").unwrap();
+
+        db.update_restarted("svc1", 4242, None).unwrap();
+        let stored = db.get("svc1").unwrap().unwrap();
+        assert_eq!(stored.restart, Some(policy));
+        assert_eq!(stored.restarts, 1);
+        assert_eq!(stored.pid, Some(4242));
+        assert!(stored.ready_at.is_none());
+        assert!(stored.is_service());
+    }
+
+    #[test]
+    fn test_recover_orphans_reads_exit_files() {
+        let (db, tmp) = test_db();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        for id in ["ok", "bad", "lost"] {
+            db.insert(&create_test_job(id, Status::Running)).unwrap();
+        }
+        std::fs::write(paths.exit_file("ok"), "0\n").unwrap();
+        std::fs::write(paths.exit_file("bad"), "2\n").unwrap();
+
+        db.recover_orphans();
+
+        let ok = db.get("ok").unwrap().unwrap();
+        assert_eq!((ok.status, ok.exit_code), (Status::Completed, Some(0)));
+        let bad = db.get("bad").unwrap().unwrap();
+        assert_eq!((bad.status, bad.exit_code), (Status::Failed, Some(2)));
+        let lost = db.get("lost").unwrap().unwrap();
+        assert_eq!((lost.status, lost.exit_code), (Status::Interrupted, None));
+        // Left for the daemon, which may be about to read it
+        assert!(paths.exit_file("ok").exists());
+
+        let events = db.events(Some("bad"), 0, None).unwrap();
+        assert_eq!(events[0].detail.as_deref(), Some("failed (exit 2)"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_recover_orphans_detects_pid_reuse() {
+        let (db, _tmp) = test_db();
+        let pid = std::process::id();
+        db.insert(&create_test_job("mine", Status::Running))
+            .unwrap();
+        db.update_started("mine", pid, process::fingerprint(pid).as_deref())
+            .unwrap();
+        // Same PID, but recorded for a process from an earlier boot
+        db.insert(&create_test_job("reused", Status::Running))
+            .unwrap();
+        db.update_started("reused", pid, Some("old-boot:1"))
+            .unwrap();
+
+        db.recover_orphans();
+        assert_eq!(db.get("mine").unwrap().unwrap().status, Status::Running);
+        assert_eq!(
+            db.get("reused").unwrap().unwrap().status,
+            Status::Interrupted
+        );
+    }
+
+    #[test]
+    fn test_recover_orphans_leaves_supervised_jobs() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        let db = Database::open(&paths).unwrap();
+        let policy = crate::core::RestartPolicy {
+            mode: crate::core::RestartMode::Always,
+            max_restarts: None,
+            delay_secs: 5,
+        };
+        // Process gone (between restarts)
+        db.insert(&create_test_job("svc1", Status::Running).with_restart(policy))
+            .unwrap();
+        // Exited, with the daemon yet to notice
+        db.insert(&create_test_job("done", Status::Running))
+            .unwrap();
+        std::fs::write(paths.exit_file("done"), "0\n").unwrap();
+
+        // Another live process owns the daemon PID file
+        std::fs::write(
+            paths.pid_file(),
+            std::os::unix::process::parent_id().to_string(),
+        )
+        .unwrap();
+        db.recover_orphans();
+        assert_eq!(db.get("svc1").unwrap().unwrap().status, Status::Running);
+        assert_eq!(db.get("done").unwrap().unwrap().status, Status::Running);
+        assert!(paths.exit_file("done").exists());
+
+        std::fs::remove_file(paths.pid_file()).unwrap();
+        db.recover_orphans();
+        assert_eq!(db.get("svc1").unwrap().unwrap().status, Status::Interrupted);
+    }
+
+    #[test]
+    fn test_update_health() {
+        let (db, _tmp) = test_db();
+        let check = crate::core::HealthCheck {
+            probe: crate::core::HealthProbe::Http("http://localhost:3000/health".into()),
+            interval_secs: 10,
+            timeout_secs: 5,
+            threshold: 3,
+            action: crate::core::HealthAction::Restart,
+        };
+        db.insert(&create_test_job("svc1", Status::Running).with_health(check.clone()))
+            .unwrap();
+
+        db.update_health("svc1", Some(HealthStatus::Unhealthy), "HTTP 503")
+            .unwrap();
+        let stored = db.get("svc1").unwrap().unwrap();
+        assert_eq!(stored.health, Some(check));
+        assert_eq!(stored.health_status, Some(HealthStatus::Unhealthy));
+        assert_eq!(stored.health_output.as_deref(), Some("HTTP 503"));
+        assert!(stored.health_checked_at.is_some());
+
+        // A restarted process starts with unknown health
+        db.update_restarted("svc1", 99, None).unwrap();
+        assert!(db.get("svc1").unwrap().unwrap().health_status.is_none());
+    }
+
+    #[test]
+    fn test_ports_roundtrip() {
+        let (db, _tmp) = test_db();
+        let ports = [
+            ("PORT".to_string(), 41_000),
+            ("DB_PORT".to_string(), 41_001),
+        ]
+        .into_iter()
+        .collect();
+        db.insert(&create_test_job("abc1", Status::Running).with_ports(ports))
+            .unwrap();
+        db.insert(&create_test_job("abc2", Status::Running))
+            .unwrap();
+
+        let stored = db.get("abc1").unwrap().unwrap();
+        assert_eq!(stored.ports.get("PORT"), Some(&41_000));
+        assert_eq!(stored.ports.get("DB_PORT"), Some(&41_001));
+        assert!(db.get("abc2").unwrap().unwrap().ports.is_empty());
+    }
+
+    #[test]
+    fn test_update_priority() {
+        let (db, _tmp) = test_db();
+        db.insert(&create_test_job("a", Status::Pending).with_priority(-10))
+            .unwrap();
+        assert_eq!(db.get("a").unwrap().unwrap().priority, -10);
+
+        db.update_priority("a", 10).unwrap();
+        assert_eq!(db.get("a").unwrap().unwrap().priority, 10);
+    }
+
+    #[test]
+    fn test_update_ready() {
+        let (db, _tmp) = test_db();
+        let job = create_test_job("abc1", Status::Running)
+            .with_ready_when(vec![crate::core::ReadyCheck::Port(3000)]);
+        db.insert(&job).unwrap();
+
+        let stored = db.get("abc1").unwrap().unwrap();
+        assert_eq!(stored.ready_when, vec![crate::core::ReadyCheck::Port(3000)]);
+        assert!(stored.ready_at.is_none());
+
+        db.update_ready("abc1").unwrap();
+        assert!(db.get("abc1").unwrap().unwrap().ready_at.is_some());
+    }
+
+    #[test]
+    fn test_events_order_and_filters() {
+        let (db, _tmp) = test_db();
+        db.record_event("a", EventKind::Started, None).unwrap();
+        db.record_event("b", EventKind::Started, None).unwrap();
+        db.record_event("a", EventKind::Ready, None).unwrap();
+        db.record_event("a", EventKind::Finished, Some("completed (exit 0)"))
+            .unwrap();
+
+        let all = db.events(None, 0, None).unwrap();
+        assert_eq!(all.len(), 4);
+        assert!(all.windows(2).all(|w| w[0].id < w[1].id));
+
+        let for_a = db.events(Some("a"), 0, None).unwrap();
+        let kinds: Vec<_> = for_a.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [EventKind::Started, EventKind::Ready, EventKind::Finished]
+        );
+        assert_eq!(for_a[2].detail.as_deref(), Some("completed (exit 0)"));
+
+        // Resume after a sequence number
+        let after = db.events(None, all[1].id, None).unwrap();
+        assert_eq!(after.len(), 2);
+
+        // Limit keeps the most recent, still oldest first
+        let last_two = db.events(None, 0, Some(2)).unwrap();
+        assert_eq!(last_two[0].id, all[2].id);
+        assert_eq!(last_two[1].id, all[3].id);
+    }
+
+    #[test]
+    fn test_migrate_adds_missing_columns() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        {
+            // Database created before the `result` column existed
+            let conn = Connection::open(paths.database()).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE jobs (
+                    id TEXT PRIMARY KEY, name TEXT, command TEXT NOT NULL,
+                    status TEXT NOT NULL, project TEXT NOT NULL, cwd TEXT NOT NULL,
+                    pid INTEGER, exit_code INTEGER, created_at TEXT NOT NULL,
+                    started_at TEXT, finished_at TEXT, timeout_secs INTEGER,
+                    context TEXT, idempotency_key TEXT UNIQUE
+                );",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&paths).unwrap();
+        db.insert(&create_test_job("abc1", Status::Completed))
+            .unwrap();
+        db.update_result("abc1", &serde_json::json!(true)).unwrap();
+        assert_eq!(
+            db.get("abc1").unwrap().unwrap().result,
+            Some(serde_json::json!(true))
+        );
     }
 
     #[test]
@@ -570,6 +1411,59 @@ mod tests {
         assert!(db.insert(&job2).is_err());
     }
 
+    #[test]
+    fn test_find_by_name_glob_and_project() {
+        let (db, _tmp) = test_db();
+        db.insert(&create_test_job("a", Status::Completed).with_name("test-unit"))
+            .unwrap();
+        db.insert(&create_test_job("b", Status::Failed).with_name("test-e2e"))
+            .unwrap();
+        db.insert(&create_test_job("c", Status::Failed).with_name("build"))
+            .unwrap();
+        let mut other = create_test_job("d", Status::Failed).with_name("test-other");
+        other.project = PathBuf::from("/elsewhere");
+        db.insert(&other).unwrap();
+
+        let filter = JobFilter {
+            name: Some("test-*".to_string()),
+            project: Some(PathBuf::from("/project")),
+            ..JobFilter::default()
+        };
+        let mut ids: Vec<_> = db
+            .find(&filter)
+            .unwrap()
+            .into_iter()
+            .map(|j| j.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["a", "b"]);
+
+        let filter = JobFilter {
+            name: Some("test-*".to_string()),
+            status: Some(Status::Failed),
+            ..JobFilter::default()
+        };
+        assert_eq!(db.find(&filter).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_find_since() {
+        let (db, _tmp) = test_db();
+        let mut old = create_test_job("old", Status::Completed);
+        old.created_at = chrono::Utc::now() - chrono::Duration::days(3);
+        db.insert(&old).unwrap();
+        db.insert(&create_test_job("new", Status::Completed))
+            .unwrap();
+
+        let filter = JobFilter {
+            since: Some(chrono::Utc::now() - chrono::Duration::days(1)),
+            ..JobFilter::default()
+        };
+        let jobs = db.find(&filter).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "new");
+    }
+
     #[test]
     fn test_count_all() {
         let (db, _tmp) = test_db();
@@ -640,6 +1534,36 @@ mod tests {
         assert_eq!(result.unwrap().id, "b");
     }
 
+    #[test]
+    fn test_groups_resolve_and_list_jobs() {
+        let (db, _tmp) = test_db();
+        let group = Group::new("gab12".to_string(), PathBuf::from("/project")).with_name("nightly");
+        db.insert_group(&group).unwrap();
+        db.insert(&create_test_job("a", Status::Completed).with_group("gab12"))
+            .unwrap();
+        db.insert(&create_test_job("b", Status::Running).with_group("gab12"))
+            .unwrap();
+        db.insert(&create_test_job("c", Status::Running)).unwrap();
+
+        assert_eq!(db.resolve_group("gab12").unwrap().id, "gab12");
+        assert_eq!(db.resolve_group("gab").unwrap().id, "gab12");
+        assert_eq!(db.resolve_group("nightly").unwrap().id, "gab12");
+        assert!(db.resolve_group("other").is_err());
+
+        let ids: Vec<_> = db
+            .group_jobs("gab12")
+            .unwrap()
+            .into_iter()
+            .map(|j| j.id)
+            .collect();
+        assert_eq!(ids, ["a", "b"]);
+
+        db.delete_group("gab12").unwrap();
+        assert!(db.resolve_group("gab12").is_err());
+        assert!(db.get("a").unwrap().is_none());
+        assert!(db.get("c").unwrap().is_some());
+    }
+
     #[test]
     fn test_name_in_use_running() {
         let (db, _tmp) = test_db();
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Started,
+    Ready,
+    /// A service's process exited and will be restarted
+    Crashed,
+    Restarted,
+    Healthy,
+    Unhealthy,
+    /// A new daemon took over supervising the job's running process
+    Adopted,
+    Finished,
+}
+
+impl EventKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Started => "started",
+            Self::Ready => "ready",
+            Self::Crashed => "crashed",
+            Self::Restarted => "restarted",
+            Self::Healthy => "healthy",
+            Self::Unhealthy => "unhealthy",
+            Self::Adopted => "adopted",
+            Self::Finished => "finished",
+        }
+    }
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for EventKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "started" => Ok(Self::Started),
+            "ready" => Ok(Self::Ready),
+            "crashed" => Ok(Self::Crashed),
+            "restarted" => Ok(Self::Restarted),
+            "healthy" => Ok(Self::Healthy),
+            "unhealthy" => Ok(Self::Unhealthy),
+            "adopted" => Ok(Self::Adopted),
+            "finished" => Ok(Self::Finished),
+            _ => anyhow::bail!("unknown event kind: {s}"),
+        }
+    }
+}
+
+/// A job lifecycle transition recorded by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Monotonic sequence number, usable as a resume point
+    pub id: i64,
+    pub job_id: String,
+    pub kind: EventKind,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_kind_roundtrip() {
+        for kind in [
+            EventKind::Started,
+            EventKind::Ready,
+            EventKind::Crashed,
+            EventKind::Restarted,
+            EventKind::Healthy,
+            EventKind::Unhealthy,
+            EventKind::Adopted,
+            EventKind::Finished,
+        ] {
+            assert_eq!(kind.as_str().parse::<EventKind>().unwrap(), kind);
+        }
+        assert!("bogus".parse::<EventKind>().is_err());
+    }
+}
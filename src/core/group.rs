@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Jobs submitted together by `jb batch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub id: String,
+    pub name: Option<String>,
+    pub project: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Group {
+    #[must_use]
+    pub fn new(id: String, project: PathBuf) -> Self {
+        Self {
+            id,
+            name: None,
+            project,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Name if it has one, else ID
+    #[must_use]
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+/// What a periodic health check probes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "value")]
+pub enum HealthProbe {
+    /// Shell command run in the job's cwd; exit 0 is healthy
+    Command(String),
+    /// TCP port accepting connections on localhost
+    Port(u16),
+    /// HTTP GET returning 2xx/3xx
+    Http(String),
+}
+
+impl std::fmt::Display for HealthProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Command(cmd) => write!(f, "command '{cmd}'"),
+            Self::Port(port) => write!(f, "port {port}"),
+            Self::Http(url) => write!(f, "http {url}"),
+        }
+    }
+}
+
+/// What the daemon does once a job is unhealthy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthAction {
+    Restart,
+    Stop,
+    /// Only record the unhealthy state
+    Mark,
+}
+
+impl HealthAction {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::Stop => "stop",
+            Self::Mark => "mark",
+        }
+    }
+}
+
+impl std::fmt::Display for HealthAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for HealthAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "restart" => Ok(Self::Restart),
+            "stop" => Ok(Self::Stop),
+            "mark" => Ok(Self::Mark),
+            _ => anyhow::bail!("Invalid health action: {s} (expected restart, stop or mark)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub probe: HealthProbe,
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    /// Consecutive failures before the job is unhealthy
+    pub threshold: u32,
+    pub action: HealthAction,
+}
+
+impl HealthCheck {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let HealthProbe::Http(url) = &self.probe
+            && !url.starts_with("http://")
+        {
+            anyhow::bail!("Only http:// URLs are supported: {url}");
+        }
+        if self.interval_secs == 0 {
+            anyhow::bail!("Health check interval must be at least 1s");
+        }
+        if self.threshold == 0 {
+            anyhow::bail!("Health check failure threshold must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+impl std::fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for HealthStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "healthy" => Ok(Self::Healthy),
+            "unhealthy" => Ok(Self::Unhealthy),
+            _ => anyhow::bail!("unknown health status: {s}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(probe: HealthProbe) -> HealthCheck {
+        HealthCheck {
+            probe,
+            interval_secs: 10,
+            timeout_secs: 5,
+            threshold: 3,
+            action: HealthAction::Restart,
+        }
+    }
+
+    #[test]
+    fn test_health_check_validate() {
+        assert!(check(HealthProbe::Port(3000)).validate().is_ok());
+        assert!(
+            check(HealthProbe::Command("true".into()))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            check(HealthProbe::Http("https://localhost".into()))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            HealthCheck {
+                threshold: 0,
+                ..check(HealthProbe::Port(3000))
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_health_action_from_str() {
+        assert_eq!(
+            "restart".parse::<HealthAction>().unwrap(),
+            HealthAction::Restart
+        );
+        assert_eq!("STOP".parse::<HealthAction>().unwrap(), HealthAction::Stop);
+        assert_eq!("mark".parse::<HealthAction>().unwrap(), HealthAction::Mark);
+        assert!("ignore".parse::<HealthAction>().is_err());
+    }
+}
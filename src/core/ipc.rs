@@ -1,21 +1,178 @@
-use crate::core::Job;
+//! Messages between the CLI and the daemon.
+//!
+//! Each message is a 4-byte big-endian length followed by that many bytes of
+//! JSON, tagged with its kind: `{"type": "status", "data": {"id": "..."}}`.
+//! A client opens every connection with [`Request::Hello`]; the daemon answers
+//! with its own protocol version and the optional requests it understands.
+//!
+//! Messages travel in an [`Envelope`]. A daemon with the `multiplex` capability
+//! handles the requests on a connection concurrently and answers each with the
+//! `id` it was sent with, so a client can have many waits in flight at once and
+//! cancel one with [`Request::Cancel`].
+
+use crate::core::{
+    Group, HealthCheck, Job, ReadyCheck, RestartPolicy, RunWhen, UserError, artifact,
+    validate_port_name,
+};
+use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Bumped whenever a message changes shape, so a CLI can tell that the daemon
+/// it is talking to was started from an older (or newer) binary.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Requests a daemon may or may not handle, advertised in [`Response::Hello`].
+pub const CAPABILITIES: &[&str] = &[
+    "reload",
+    "handoff",
+    "multiplex",
+    "batch",
+    "flow",
+    "priority",
+];
 
+/// A message with the request ID that pairs a response with its request:
+/// `{"id": 7, "type": "wait", "data": {...}}`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// Chosen by the client; the response carries the same one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub message: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(id: Option<u64>, message: T) -> Self {
+        Self { id, message }
+    }
+}
+
+/// Everything the daemon needs to create and start a job.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunSpec {
+    pub command: String,
+    pub name: Option<String>,
+    pub cwd: String,
+    /// Derived from `cwd` when empty
+    #[serde(default)]
+    pub project: String,
+    pub timeout_secs: Option<u64>,
+    pub context: Option<serde_json::Value>,
+    pub idempotency_key: Option<String>,
+    /// Glob patterns (relative to `cwd`) collected into the artifact store on exit
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Checks that must all pass before the job is considered ready
+    #[serde(default)]
+    pub ready_when: Vec<ReadyCheck>,
+    /// Supervise the job as a service, restarting it per this policy
+    pub restart: Option<RestartPolicy>,
+    /// Periodic health check while running
+    pub health: Option<HealthCheck>,
+    /// Names of free local ports to allocate and export as env vars
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// Extra environment variables for the process
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Run one at a time with other jobs in this group and project
+    pub concurrency_group: Option<String>,
+    /// IDs of jobs that must complete successfully first
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Run on failure of `depends_on`, or regardless, instead of on success
+    #[serde(default)]
+    pub run_when: RunWhen,
+    /// Queued jobs with a higher priority start first
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl RunSpec {
+    /// Check the fields a caller supplies before a job is created from them.
+    pub fn validate(&self) -> Result<()> {
+        for pattern in &self.artifacts {
+            artifact::validate_pattern(pattern).map_err(|e| UserError::new(e.to_string()))?;
+        }
+        for (i, name) in self.ports.iter().enumerate() {
+            validate_port_name(name).map_err(|e| UserError::new(format!("--port: {e}")))?;
+            if self.ports[..i].contains(name) {
+                anyhow::bail!(UserError::new(format!("--port {name} given twice")));
+            }
+        }
+        for check in &self.ready_when {
+            check
+                .validate()
+                .map_err(|e| UserError::new(format!("Invalid readiness check: {e}")))?;
+        }
+        if let Some(check) = &self.health {
+            check
+                .validate()
+                .map_err(|e| UserError::new(format!("Invalid health check: {e}")))?;
+        }
+        Ok(())
+    }
+}
+
+/// Jobs to start together as one group.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSpec {
+    pub name: Option<String>,
+    pub jobs: Vec<RunSpec>,
+}
+
+/// A pipeline's steps as one group, each step a job.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlowSpec {
+    pub name: String,
+    /// Every step comes after the steps it needs
+    pub steps: Vec<StepSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepSpec {
+    pub name: String,
+    /// Earlier steps whose jobs this one depends on
+    #[serde(default)]
+    pub needs: Vec<String>,
+    /// Earlier steps whose artifact directories are exported as
+    /// `JB_ARTIFACTS_<STEP>`
+    #[serde(default)]
+    pub artifacts_from: Vec<String>,
+    pub job: RunSpec,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum Request {
-    Run {
-        command: String,
-        name: Option<String>,
-        cwd: String,
-        project: String,
-        timeout_secs: Option<u64>,
-        context: Option<serde_json::Value>,
-        idempotency_key: Option<String>,
+    /// First message on every connection
+    Hello {
+        protocol_version: u32,
+        client_version: String,
     },
+    Run(Box<RunSpec>),
+    /// Start several jobs as a group; none are left running if one fails
+    Batch(BatchSpec),
+    /// Start a pipeline's steps as a group, each waiting on the steps it needs
+    Flow(FlowSpec),
     Stop {
         id: String,
+        #[serde(default)]
         force: bool,
     },
+    /// Replace a running service's process
+    Restart {
+        id: String,
+    },
+    /// Change a queued job's priority
+    Reprioritize {
+        id: String,
+        priority: i32,
+    },
     Status {
         id: String,
     },
@@ -26,15 +183,37 @@ pub enum Request {
     Wait {
         id: String,
         timeout_secs: Option<u64>,
+        /// Return once the job's readiness checks pass instead of at exit
+        #[serde(default)]
+        ready: bool,
     },
     Ping,
     Shutdown,
+    /// Exit, leaving running and queued jobs for the next daemon to adopt
+    Handoff,
+    /// Re-read the config file and apply it
+    Reload,
+    /// Abandon the in-flight wait sent with request ID `id` on this
+    /// connection; it is answered with an error
+    Cancel {
+        id: u64,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum Response {
+    Hello {
+        protocol_version: u32,
+        daemon_version: String,
+        capabilities: Vec<String>,
+    },
     Job(Box<Job>),
     Jobs(Vec<Job>),
+    Group {
+        group: Group,
+        jobs: Vec<Job>,
+    },
     Ok,
     Error(String),
     Pong {
@@ -43,4 +222,102 @@ pub enum Response {
         running_jobs: usize,
         total_jobs: usize,
     },
+    /// Settings that changed on reload, e.g. `concurrency.max_jobs: 2 -> 4`
+    Reloaded {
+        changes: Vec<String>,
+    },
+}
+
+/// Largest message either side accepts
+const MAX_MESSAGE: usize = 10 * 1024 * 1024;
+
+/// Read one message; None when the other side closed the connection.
+pub async fn read_message<T: DeserializeOwned>(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> Result<Option<T>> {
+    let mut len_buf = [0u8; 4];
+    match stream.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > MAX_MESSAGE {
+        anyhow::bail!("message too large: {len} bytes");
+    }
+
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).await?;
+
+    Ok(Some(serde_json::from_slice(&buf)?))
+}
+
+pub async fn write_message(
+    stream: &mut (impl AsyncWrite + Unpin),
+    message: &impl Serialize,
+) -> Result<()> {
+    let data = serde_json::to_vec(message)?;
+    #[allow(clippy::cast_possible_truncation)] // messages are always < 4GB
+    let len = (data.len() as u32).to_be_bytes();
+
+    stream.write_all(&len).await?;
+    stream.write_all(&data).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_type_tagged() {
+        let json = serde_json::to_value(Request::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_version: "1.2.3".into(),
+        })
+        .unwrap();
+        assert_eq!(json["type"], "hello");
+        assert_eq!(json["data"]["protocol_version"], PROTOCOL_VERSION);
+
+        assert_eq!(
+            serde_json::to_value(Request::Ping).unwrap(),
+            serde_json::json!({ "type": "ping" })
+        );
+        let error: Response =
+            serde_json::from_str(r#"{"type": "error", "data": "no such job"}"#).unwrap();
+        assert!(matches!(error, Response::Error(e) if e == "no such job"));
+
+        // Messages from before the handshake are not understood
+        assert!(serde_json::from_str::<Request>(r#""Ping""#).is_err());
+    }
+
+    #[test]
+    fn test_envelope_carries_request_id() {
+        let json = serde_json::to_value(Envelope::new(
+            Some(7),
+            Request::Status { id: "abcd".into() },
+        ))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": 7, "type": "status", "data": { "id": "abcd" } })
+        );
+
+        let envelope: Envelope<Request> = serde_json::from_str(r#"{"type": "ping"}"#).unwrap();
+        assert_eq!(envelope.id, None);
+        assert!(matches!(envelope.message, Request::Ping));
+        assert_eq!(
+            serde_json::to_value(Envelope::new(None, Response::Ok)).unwrap(),
+            serde_json::json!({ "type": "ok" })
+        );
+
+        let envelope: Envelope<Response> =
+            serde_json::from_str(r#"{"id": 3, "type": "error", "data": "Wait cancelled"}"#)
+                .unwrap();
+        assert_eq!(envelope.id, Some(3));
+        assert!(matches!(envelope.message, Response::Error(e) if e == "Wait cancelled"));
+    }
 }
@@ -1,8 +1,10 @@
+use crate::core::health::{HealthCheck, HealthStatus};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
     Pending,
@@ -57,6 +59,155 @@ impl std::str::FromStr for Status {
     }
 }
 
+/// Condition that marks a running job as ready (e.g. a dev server accepting requests)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "value")]
+pub enum ReadyCheck {
+    /// Regex matched against the job's output
+    Log(String),
+    /// TCP port accepting connections on localhost
+    Port(u16),
+    /// HTTP GET returning 2xx/3xx
+    Http(String),
+    /// File exists (relative paths resolve against the job's cwd)
+    File(PathBuf),
+}
+
+impl ReadyCheck {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Log(pattern) => {
+                regex::Regex::new(pattern)?;
+            }
+            Self::Http(url) => {
+                if !url.starts_with("http://") {
+                    anyhow::bail!("Only http:// URLs are supported: {url}");
+                }
+            }
+            Self::Port(_) | Self::File(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ReadyCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Log(pattern) => write!(f, "log '{pattern}'"),
+            Self::Port(port) => write!(f, "port {port}"),
+            Self::Http(url) => write!(f, "http {url}"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+        }
+    }
+}
+
+/// When a supervised job (service) is restarted after its process exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    Always,
+    OnFailure,
+    Never,
+}
+
+impl RestartMode {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::OnFailure => "on-failure",
+            Self::Never => "never",
+        }
+    }
+}
+
+impl std::fmt::Display for RestartMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for RestartMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "on-failure" => Ok(Self::OnFailure),
+            "never" => Ok(Self::Never),
+            _ => {
+                anyhow::bail!("Invalid restart policy: {s} (expected always, on-failure or never)")
+            }
+        }
+    }
+}
+
+/// When a job with dependencies runs, once they have finished
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunWhen {
+    /// Every dependency completed; skipped as soon as one doesn't
+    #[default]
+    OnSuccess,
+    /// At least one dependency failed, or was stopped or skipped
+    OnFailure,
+    /// Whatever happened to the dependencies
+    Always,
+}
+
+impl RunWhen {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OnSuccess => "on_success",
+            Self::OnFailure => "on_failure",
+            Self::Always => "always",
+        }
+    }
+}
+
+impl std::fmt::Display for RunWhen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for RunWhen {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "on_success" => Ok(Self::OnSuccess),
+            "on_failure" => Ok(Self::OnFailure),
+            "always" => Ok(Self::Always),
+            _ => {
+                anyhow::bail!("Invalid condition: {s} (expected on_success, on_failure or always)")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    /// Automatic restarts allowed before giving up (None = unlimited)
+    pub max_restarts: Option<u32>,
+    pub delay_secs: u64,
+}
+
+impl RestartPolicy {
+    /// Whether an exit with `success` should be followed by restart number `attempt` (1-based).
+    #[must_use]
+    pub fn should_restart(&self, success: bool, attempt: u32) -> bool {
+        let wanted = match self.mode {
+            RestartMode::Always => true,
+            RestartMode::OnFailure => !success,
+            RestartMode::Never => false,
+        };
+        wanted && self.max_restarts.is_none_or(|max| attempt <= max)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
     pub id: String,
@@ -66,6 +217,8 @@ pub struct Job {
     pub project: PathBuf,
     pub cwd: PathBuf,
     pub pid: Option<u32>,
+    /// Identifies the process behind `pid`, so a reused PID isn't mistaken for it
+    pub pid_fingerprint: Option<String>,
     pub exit_code: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
@@ -73,6 +226,45 @@ pub struct Job {
     pub timeout_secs: Option<u64>,
     pub context: Option<serde_json::Value>,
     pub idempotency_key: Option<String>,
+    /// JSON written by the job to `JB_RESULT_FILE`, captured after exit
+    pub result: Option<serde_json::Value>,
+    /// Glob patterns collected into the artifact store on exit
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// All must pass before the job is marked ready
+    #[serde(default)]
+    pub ready_when: Vec<ReadyCheck>,
+    pub ready_at: Option<DateTime<Utc>>,
+    /// Set for services: the daemon restarts the process according to this policy
+    pub restart: Option<RestartPolicy>,
+    /// Times the process has been restarted (automatic and manual)
+    #[serde(default)]
+    pub restarts: u32,
+    /// Periodic check run by the daemon while the job is running
+    pub health: Option<HealthCheck>,
+    pub health_status: Option<HealthStatus>,
+    /// Output (or error) of the most recent health check
+    pub health_output: Option<String>,
+    pub health_checked_at: Option<DateTime<Utc>>,
+    /// Local ports allocated by the daemon, exported to the job under these names
+    #[serde(default)]
+    pub ports: BTreeMap<String, u16>,
+    /// Extra environment variables for the process
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Jobs sharing a group (within a project) run one at a time
+    pub concurrency_group: Option<String>,
+    /// Jobs that must finish before this one starts
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Whether to run depending on how `depends_on` finished
+    #[serde(default)]
+    pub run_when: RunWhen,
+    /// Queued jobs with a higher priority start first
+    #[serde(default)]
+    pub priority: i32,
+    /// The `jb batch` or `jb flow` group the job was submitted in
+    pub group_id: Option<String>,
 }
 
 impl Job {
@@ -86,6 +278,7 @@ impl Job {
             project,
             cwd,
             pid: None,
+            pid_fingerprint: None,
             exit_code: None,
             created_at: Utc::now(),
             started_at: None,
@@ -93,6 +286,23 @@ impl Job {
             timeout_secs: None,
             context: None,
             idempotency_key: None,
+            result: None,
+            artifacts: Vec::new(),
+            ready_when: Vec::new(),
+            ready_at: None,
+            restart: None,
+            restarts: 0,
+            health: None,
+            health_status: None,
+            health_output: None,
+            health_checked_at: None,
+            ports: BTreeMap::new(),
+            env: BTreeMap::new(),
+            concurrency_group: None,
+            depends_on: Vec::new(),
+            run_when: RunWhen::OnSuccess,
+            priority: 0,
+            group_id: None,
         }
     }
 
@@ -120,6 +330,78 @@ impl Job {
         self
     }
 
+    #[must_use]
+    pub fn with_artifacts(mut self, patterns: Vec<String>) -> Self {
+        self.artifacts = patterns;
+        self
+    }
+
+    #[must_use]
+    pub fn with_ready_when(mut self, checks: Vec<ReadyCheck>) -> Self {
+        self.ready_when = checks;
+        self
+    }
+
+    #[must_use]
+    pub fn with_restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = Some(policy);
+        self
+    }
+
+    #[must_use]
+    pub fn with_health(mut self, check: HealthCheck) -> Self {
+        self.health = Some(check);
+        self
+    }
+
+    #[must_use]
+    pub fn with_ports(mut self, ports: BTreeMap<String, u16>) -> Self {
+        self.ports = ports;
+        self
+    }
+
+    #[must_use]
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    #[must_use]
+    pub fn with_concurrency_group(mut self, group: impl Into<String>) -> Self {
+        self.concurrency_group = Some(group.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_depends_on(mut self, ids: Vec<String>) -> Self {
+        self.depends_on = ids;
+        self
+    }
+
+    #[must_use]
+    pub fn with_run_when(mut self, run_when: RunWhen) -> Self {
+        self.run_when = run_when;
+        self
+    }
+
+    #[must_use]
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    #[must_use]
+    pub fn with_group(mut self, group_id: impl Into<String>) -> Self {
+        self.group_id = Some(group_id.into());
+        self
+    }
+
+    /// Whether the job is supervised by the daemon
+    #[must_use]
+    pub fn is_service(&self) -> bool {
+        self.restart.is_some()
+    }
+
     #[must_use]
     pub fn short_id(&self) -> &str {
         &self.id
@@ -218,6 +500,35 @@ mod tests {
         assert_eq!(job.idempotency_key, Some("unique-key".to_string()));
     }
 
+    #[test]
+    fn test_ready_check_validate() {
+        assert!(
+            ReadyCheck::Log("Listening on \\d+".to_string())
+                .validate()
+                .is_ok()
+        );
+        assert!(ReadyCheck::Log("(unclosed".to_string()).validate().is_err());
+        assert!(
+            ReadyCheck::Http("http://localhost:3000/health".to_string())
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            ReadyCheck::Http("https://example.com".to_string())
+                .validate()
+                .is_err()
+        );
+        assert!(ReadyCheck::Port(3000).validate().is_ok());
+    }
+
+    #[test]
+    fn test_ready_check_serde() {
+        let json = serde_json::to_string(&ReadyCheck::Port(3000)).unwrap();
+        assert_eq!(json, r#"{"type":"port","value":3000}"#);
+        let back: ReadyCheck = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, ReadyCheck::Port(3000));
+    }
+
     #[test]
     fn test_job_short_id() {
         let job = Job::new(
@@ -228,4 +539,44 @@ mod tests {
         );
         assert_eq!(job.short_id(), "xyz9");
     }
+
+    #[test]
+    fn test_restart_mode_from_str() {
+        assert_eq!(
+            "always".parse::<RestartMode>().unwrap(),
+            RestartMode::Always
+        );
+        assert_eq!(
+            "on-failure".parse::<RestartMode>().unwrap(),
+            RestartMode::OnFailure
+        );
+        assert_eq!("never".parse::<RestartMode>().unwrap(), RestartMode::Never);
+        assert!("sometimes".parse::<RestartMode>().is_err());
+    }
+
+    #[test]
+    fn test_restart_policy_should_restart() {
+        let on_failure = RestartPolicy {
+            mode: RestartMode::OnFailure,
+            max_restarts: Some(2),
+            delay_secs: 0,
+        };
+        assert!(on_failure.should_restart(false, 1));
+        assert!(on_failure.should_restart(false, 2));
+        assert!(!on_failure.should_restart(false, 3));
+        assert!(!on_failure.should_restart(true, 1));
+
+        let always = RestartPolicy {
+            mode: RestartMode::Always,
+            max_restarts: None,
+            delay_secs: 0,
+        };
+        assert!(always.should_restart(true, 100));
+
+        let never = RestartPolicy {
+            mode: RestartMode::Never,
+            ..always
+        };
+        assert!(!never.should_restart(false, 1));
+    }
 }
@@ -0,0 +1,382 @@
+//! A job's output lives in `<id>.log` plus the segments rotated out of it.
+//!
+//! Positions in the output count every byte the job has written, including
+//! those rotated out, so a position stays valid as the log rotates. Rotation
+//! records where `<id>.log` now starts in `<id>.log.base`, and holds an
+//! exclusive lock on `<id>.log` while it works; readers take a shared lock
+//! while they find their position.
+
+use crate::core::Paths;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// All files holding a job's output, oldest first: rotated segments
+/// (`<id>.log.N`, `<id>.log.N.gz`, highest N first) followed by `<id>.log`.
+#[must_use]
+pub fn segments(paths: &Paths, job_id: &str) -> Vec<PathBuf> {
+    let mut rotated = Vec::new();
+    for n in 1.. {
+        let plain = paths.rotated_log_file(job_id, n, false);
+        let gz = paths.rotated_log_file(job_id, n, true);
+        if plain.exists() {
+            rotated.push(plain);
+        } else if gz.exists() {
+            rotated.push(gz);
+        } else {
+            break;
+        }
+    }
+    rotated.reverse();
+
+    let current = paths.log_file(job_id);
+    if current.exists() {
+        rotated.push(current);
+    }
+    rotated
+}
+
+/// Open a log segment for buffered reading, decompressing `.gz` files.
+pub fn open(path: &Path) -> std::io::Result<Box<dyn BufRead + Send>> {
+    let file = std::fs::File::open(path)?;
+    if path.extension().is_some_and(|e| e == "gz") {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Move the current log into segment 1 and empty it, shifting older segments
+/// up (gzipped) and deleting any beyond `keep`.
+///
+/// The log is copied and truncated rather than renamed because the job keeps
+/// writing to its open file; it writes in append mode, so output continues at
+/// the new end. Output written between the copy and the truncate is lost.
+pub fn rotate(paths: &Paths, job_id: &str, keep: usize) -> std::io::Result<()> {
+    let current = paths.log_file(job_id);
+    let log = std::fs::OpenOptions::new().write(true).open(&current)?;
+    log.lock()?;
+
+    let mut count = 0;
+    while paths.rotated_log_file(job_id, count + 1, false).exists()
+        || paths.rotated_log_file(job_id, count + 1, true).exists()
+    {
+        count += 1;
+    }
+
+    for n in (1..=count).rev() {
+        let plain = paths.rotated_log_file(job_id, n, false);
+        let gz = paths.rotated_log_file(job_id, n, true);
+        let src = if plain.exists() { plain } else { gz.clone() };
+        if n >= keep {
+            std::fs::remove_file(&src)?;
+            continue;
+        }
+        let dest = paths.rotated_log_file(job_id, n + 1, true);
+        if src == gz {
+            std::fs::rename(&src, &dest)?;
+        } else {
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(&dest)?,
+                flate2::Compression::default(),
+            );
+            std::io::copy(&mut std::fs::File::open(&src)?, &mut encoder)?;
+            encoder.finish()?;
+            std::fs::remove_file(&src)?;
+        }
+    }
+
+    let rotated = if keep > 0 {
+        std::fs::copy(&current, paths.rotated_log_file(job_id, 1, false))?
+    } else {
+        log.metadata()?.len()
+    };
+    std::fs::write(
+        paths.log_base_file(job_id),
+        (read_base(paths, job_id) + rotated).to_string(),
+    )?;
+    log.set_len(0)
+}
+
+/// Where `<id>.log` starts in the job's output
+fn read_base(paths: &Paths, job_id: &str) -> u64 {
+    std::fs::read_to_string(paths.log_base_file(job_id))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// A rotated segment and the part of the job's output it holds
+struct Span {
+    path: PathBuf,
+    start: u64,
+    end: u64,
+}
+
+/// Where each kept part of the job's output is. Call with a lock on `log`.
+struct Layout {
+    /// Rotated segments, oldest first
+    rotated: Vec<Span>,
+    /// Where `<id>.log` starts
+    base: u64,
+    /// Start of the oldest output still kept
+    start: u64,
+    end: u64,
+}
+
+impl Layout {
+    fn read(paths: &Paths, job_id: &str, log: &File) -> std::io::Result<Self> {
+        let base = read_base(paths, job_id);
+        let current = paths.log_file(job_id);
+        let mut rotated = Vec::new();
+        let mut end = base;
+        for path in segments(paths, job_id).into_iter().rev() {
+            if path == current {
+                continue;
+            }
+            // Logs rotated before positions were recorded start at 0
+            let start = end.saturating_sub(content_len(&path)?);
+            rotated.push(Span { path, start, end });
+            end = start;
+        }
+        rotated.reverse();
+        Ok(Self {
+            rotated,
+            base,
+            start: end,
+            end: base + log.metadata()?.len(),
+        })
+    }
+}
+
+/// Size of a segment's output. gzip records it (mod 4 GiB) in its last 4 bytes.
+fn content_len(path: &Path) -> std::io::Result<u64> {
+    if path.extension().is_none_or(|e| e != "gz") {
+        return Ok(std::fs::metadata(path)?.len());
+    }
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() < 4 {
+        return Ok(0);
+    }
+    file.seek(SeekFrom::End(-4))?;
+    let mut size = [0u8; 4];
+    file.read_exact(&mut size)?;
+    Ok(u64::from(u32::from_le_bytes(size)))
+}
+
+/// The positions of the oldest output still kept and of the end of the output.
+pub fn span(paths: &Paths, job_id: &str) -> std::io::Result<(u64, u64)> {
+    let Ok(log) = File::open(paths.log_file(job_id)) else {
+        return Ok((0, 0));
+    };
+    log.lock_shared()?;
+    let layout = Layout::read(paths, job_id, &log)?;
+    Ok((layout.start, layout.end))
+}
+
+/// Reads a job's output in order from a position, following it across
+/// rotations. Output whose segment has since been deleted is skipped.
+pub struct LogReader {
+    paths: Paths,
+    job_id: String,
+    offset: u64,
+    /// The rotated segment being read and where it ends
+    segment: Option<(Box<dyn BufRead + Send>, u64)>,
+}
+
+impl LogReader {
+    #[must_use]
+    pub fn new(paths: &Paths, job_id: &str, offset: u64) -> Self {
+        Self {
+            paths: paths.clone(),
+            job_id: job_id.to_string(),
+            offset,
+            segment: None,
+        }
+    }
+
+    /// Position of the next byte to read
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Up to `max` bytes from the current position, from one segment. Empty
+    /// once everything written so far was read.
+    ///
+    /// A position before the oldest output kept moves to it; one past the
+    /// end (the log was replaced) starts over from the oldest output.
+    pub fn read(&mut self, max: u64) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            if let Some((reader, end)) = &mut self.segment {
+                // Rotated segments don't change, so need no lock
+                reader
+                    .by_ref()
+                    .take(max.min(*end - self.offset))
+                    .read_to_end(&mut data)?;
+                self.offset += data.len() as u64;
+                if self.offset >= *end || data.is_empty() {
+                    // Shorter than its recorded size if it was cut off
+                    self.offset = *end;
+                    self.segment = None;
+                }
+                if !data.is_empty() {
+                    return Ok(data);
+                }
+                continue;
+            }
+
+            let Ok(log) = File::open(self.paths.log_file(&self.job_id)) else {
+                return Ok(data);
+            };
+            log.lock_shared()?;
+            let layout = Layout::read(&self.paths, &self.job_id, &log)?;
+            if self.offset < layout.start || self.offset > layout.end {
+                self.offset = layout.start;
+            }
+            match layout.rotated.into_iter().find(|s| self.offset < s.end) {
+                Some(span) => {
+                    let mut reader = open(&span.path)?;
+                    std::io::copy(
+                        &mut reader.by_ref().take(self.offset - span.start),
+                        &mut std::io::sink(),
+                    )?;
+                    self.segment = Some((reader, span.end));
+                }
+                None => {
+                    let mut log = &log;
+                    log.seek(SeekFrom::Start(self.offset - layout.base))?;
+                    log.take(max).read_to_end(&mut data)?;
+                    self.offset += data.len() as u64;
+                    return Ok(data);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_segments_oldest_first() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+
+        assert!(segments(&paths, "abc1").is_empty());
+
+        std::fs::write(paths.log_file("abc1"), "current\n").unwrap();
+        std::fs::write(paths.rotated_log_file("abc1", 1, false), "newer\n").unwrap();
+        std::fs::write(paths.rotated_log_file("abc1", 2, true), "").unwrap();
+
+        assert_eq!(
+            segments(&paths, "abc1"),
+            vec![
+                paths.rotated_log_file("abc1", 2, true),
+                paths.rotated_log_file("abc1", 1, false),
+                paths.log_file("abc1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rotate_shifts_and_compresses() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+
+        std::fs::write(paths.log_file("abc1"), "first\n").unwrap();
+        rotate(&paths, "abc1", 2).unwrap();
+        std::fs::write(paths.log_file("abc1"), "second\n").unwrap();
+        rotate(&paths, "abc1", 2).unwrap();
+        std::fs::write(paths.log_file("abc1"), "third\n").unwrap();
+        rotate(&paths, "abc1", 2).unwrap();
+
+        // "first" fell off the end; "second" was compressed into segment 2
+        let read = |path: &Path| -> Vec<String> {
+            open(path).unwrap().lines().map(Result::unwrap).collect()
+        };
+        let segs = segments(&paths, "abc1");
+        assert_eq!(
+            segs,
+            vec![
+                paths.rotated_log_file("abc1", 2, true),
+                paths.rotated_log_file("abc1", 1, false),
+                paths.log_file("abc1"),
+            ]
+        );
+        assert_eq!(read(&segs[0]), ["second"]);
+        assert_eq!(read(&segs[1]), ["third"]);
+        assert!(read(&segs[2]).is_empty());
+    }
+
+    #[test]
+    fn test_reader_follows_rotation() {
+        let tmp = TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        let append = |text: &str| {
+            let mut log = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(paths.log_file("abc1"))
+                .unwrap();
+            log.write_all(text.as_bytes()).unwrap();
+        };
+        let read_all = |reader: &mut LogReader| -> String {
+            let mut out = Vec::new();
+            loop {
+                let data = reader.read(3).unwrap();
+                if data.is_empty() {
+                    return String::from_utf8(out).unwrap();
+                }
+                out.extend(data);
+            }
+        };
+
+        append("one\n");
+        let mut reader = LogReader::new(&paths, "abc1", 0);
+        assert_eq!(read_all(&mut reader), "one\n");
+
+        // Written and rotated away between reads
+        append("two\n");
+        rotate(&paths, "abc1", 2).unwrap();
+        append("three\n");
+        rotate(&paths, "abc1", 2).unwrap();
+        append("four\n");
+        assert_eq!(read_all(&mut reader), "two\nthree\nfour\n");
+        assert_eq!(reader.offset(), 19);
+        assert_eq!(span(&paths, "abc1").unwrap(), (0, 19));
+
+        // A later position reads on from there
+        let mut reader = LogReader::new(&paths, "abc1", 6);
+        assert_eq!(read_all(&mut reader), "o\nthree\nfour\n");
+
+        // Segment 2 ("one\ntwo\n") falls off; what's left starts at 8
+        rotate(&paths, "abc1", 2).unwrap();
+        append("five\n");
+        assert_eq!(span(&paths, "abc1").unwrap(), (8, 24));
+        let mut reader = LogReader::new(&paths, "abc1", 0);
+        assert_eq!(read_all(&mut reader), "three\nfour\nfive\n");
+    }
+
+    #[test]
+    fn test_open_gzip_segment() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("abc1.log.1.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"one\ntwo\n").unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let lines: Vec<String> = open(&path).unwrap().lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["one", "two"]);
+    }
+}
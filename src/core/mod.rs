@@ -1,15 +1,32 @@
+pub mod artifact;
+pub mod cleanup;
+pub mod config;
 pub mod db;
 pub mod error;
+pub mod event;
+pub mod group;
+pub mod health;
 pub mod ipc;
 pub mod job;
+pub mod logfile;
 pub mod paths;
+pub mod pipeline;
+pub mod process;
 pub mod project;
+pub mod taskfile;
 
-pub use db::Database;
+pub use config::Config;
+pub use db::{Database, JobFilter};
 pub use error::UserError;
-pub use job::{Job, Status};
+pub use event::{Event, EventKind};
+pub use group::Group;
+pub use health::{HealthAction, HealthCheck, HealthProbe, HealthStatus};
+pub use job::{Job, ReadyCheck, RestartMode, RestartPolicy, RunWhen, Status};
 pub use paths::Paths;
+pub use pipeline::{Pipeline, Step};
+pub use process::is_process_alive;
 pub use project::detect_project;
+pub use taskfile::{Task, TaskFile};
 
 /// Kill an entire process group.
 /// The PID is the process group leader (child was spawned with `process_group(0)`).
@@ -57,6 +74,79 @@ pub fn parse_duration(s: &str) -> anyhow::Result<u64> {
     Ok(n * unit)
 }
 
+/// Named priorities for `--priority`; any other integer is accepted too
+pub const PRIORITIES: &[(&str, i32)] = &[("high", 10), ("normal", 0), ("low", -10)];
+
+/// Parse a priority like "high", "low" or "5" (higher runs first)
+pub fn parse_priority(s: &str) -> anyhow::Result<i32> {
+    let s = s.trim();
+    if let Some((_, value)) = PRIORITIES
+        .iter()
+        .find(|(name, _)| s.eq_ignore_ascii_case(name))
+    {
+        return Ok(*value);
+    }
+    s.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid priority '{s}'. Use: high, normal, low or a number"))
+}
+
+/// A priority by name if it has one, else as a number
+#[must_use]
+pub fn format_priority(priority: i32) -> String {
+    PRIORITIES
+        .iter()
+        .find(|(_, value)| *value == priority)
+        .map_or_else(|| priority.to_string(), |(name, _)| (*name).to_string())
+}
+
+/// Parse a size string like "512K", "100M", "1G" (or plain bytes) into bytes
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let upper = s.to_ascii_uppercase();
+    let upper = upper.strip_suffix('B').unwrap_or(&upper);
+    let (num, unit) = if let Some(n) = upper.strip_suffix('K') {
+        (n, 1024u64)
+    } else if let Some(n) = upper.strip_suffix('M') {
+        (n, 1024 * 1024)
+    } else if let Some(n) = upper.strip_suffix('G') {
+        (n, 1024 * 1024 * 1024)
+    } else {
+        (upper, 1)
+    };
+
+    let n: u64 = num
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{s}'. Use: 512K, 100M, 1G"))?;
+    n.checked_mul(unit)
+        .ok_or_else(|| anyhow::anyhow!("Size '{s}' is too large"))
+}
+
+/// Check that `name` can be used as an environment variable name.
+pub fn validate_env_name(name: &str) -> anyhow::Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        anyhow::bail!("Invalid variable name '{name}' (use letters, digits and _)");
+    }
+    Ok(())
+}
+
+/// Variables a job relies on that a port must not replace, besides `JB_*`
+const RESERVED_ENV_NAMES: &[&str] = &["PATH", "HOME", "USER", "SHELL", "PWD", "TMPDIR"];
+
+/// Check that `name` can hold an allocated port: a valid variable name that
+/// doesn't replace one jb sets (`JB_*`) or the job needs.
+pub fn validate_port_name(name: &str) -> anyhow::Result<()> {
+    validate_env_name(name)?;
+    if name.starts_with("JB_") || RESERVED_ENV_NAMES.contains(&name) {
+        anyhow::bail!("'{name}' is reserved; pick another name for the port");
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,6 +181,17 @@ mod tests {
         assert_eq!(parse_duration("  30s  ").unwrap(), 30);
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("100MB").unwrap(), 100 * 1024 * 1024);
+        assert_eq!(parse_size("1g").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("big").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("99999999999G").is_err());
+    }
+
     #[test]
     fn test_parse_duration_invalid_format() {
         assert!(parse_duration("30").is_err());
@@ -102,4 +203,29 @@ mod tests {
     fn test_parse_duration_invalid_number() {
         assert!(parse_duration("abcs").is_err());
     }
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(parse_priority("high").unwrap(), 10);
+        assert_eq!(parse_priority("Low").unwrap(), -10);
+        assert_eq!(parse_priority(" -3 ").unwrap(), -3);
+        assert!(parse_priority("urgent").is_err());
+        assert_eq!(format_priority(0), "normal");
+        assert_eq!(format_priority(5), "5");
+    }
+
+    #[test]
+    fn test_validate_env_name() {
+        assert!(validate_env_name("PORT").is_ok());
+        assert!(validate_env_name("DB_PORT").is_ok());
+        assert!(validate_env_name("_x1").is_ok());
+        assert!(validate_env_name("").is_err());
+        assert!(validate_env_name("1PORT").is_err());
+        assert!(validate_env_name("DB-PORT").is_err());
+
+        assert!(validate_port_name("DB_PORT").is_ok());
+        assert!(validate_port_name("PATH").is_err());
+        assert!(validate_port_name("JB_RESULT_FILE").is_err());
+        assert!(validate_port_name("DB-PORT").is_err());
+    }
 }
@@ -6,11 +6,15 @@ pub struct Paths {
 }
 
 impl Paths {
+    /// `$JB_HOME` if set, otherwise `~/.jb`
     #[must_use]
     pub fn new() -> Self {
-        let root = dirs::home_dir()
-            .expect("could not determine home directory")
-            .join(".jb");
+        let root = match std::env::var_os("JB_HOME") {
+            Some(home) if !home.is_empty() => PathBuf::from(home),
+            _ => dirs::home_dir()
+                .expect("could not determine home directory")
+                .join(".jb"),
+        };
         Self { root }
     }
 
@@ -36,19 +40,85 @@ impl Paths {
         self.logs_dir().join(format!("{job_id}.log"))
     }
 
+    /// Rotated segment `n` of a job's log (1 = most recent), optionally gzipped
+    #[must_use]
+    pub fn rotated_log_file(&self, job_id: &str, n: usize, compressed: bool) -> PathBuf {
+        let ext = if compressed { ".gz" } else { "" };
+        self.logs_dir().join(format!("{job_id}.log.{n}{ext}"))
+    }
+
+    /// How many bytes of a job's output have been rotated out of its log
+    #[must_use]
+    pub fn log_base_file(&self, job_id: &str) -> PathBuf {
+        self.logs_dir().join(format!("{job_id}.log.base"))
+    }
+
+    #[must_use]
+    pub fn results_dir(&self) -> PathBuf {
+        self.root.join("results")
+    }
+
+    /// Path exported to the job as `JB_RESULT_FILE`
+    #[must_use]
+    pub fn result_file(&self, job_id: &str) -> PathBuf {
+        self.results_dir().join(format!("{job_id}.json"))
+    }
+
+    #[must_use]
+    pub fn exits_dir(&self) -> PathBuf {
+        self.root.join("exits")
+    }
+
+    /// Where a job's wrapper shell records its exit code
+    #[must_use]
+    pub fn exit_file(&self, job_id: &str) -> PathBuf {
+        self.exits_dir().join(format!("{job_id}.exit"))
+    }
+
+    #[must_use]
+    pub fn artifacts_dir(&self) -> PathBuf {
+        self.root.join("artifacts")
+    }
+
+    /// Directory holding the files collected for one job
+    #[must_use]
+    pub fn job_artifacts_dir(&self, job_id: &str) -> PathBuf {
+        self.artifacts_dir().join(job_id)
+    }
+
     #[must_use]
     pub fn socket(&self) -> PathBuf {
         self.root.join("daemon.sock")
     }
 
+    /// Unix socket for the optional HTTP API
+    #[must_use]
+    pub fn http_socket(&self) -> PathBuf {
+        self.root.join("http.sock")
+    }
+
+    /// Bearer token required by the HTTP API on TCP
+    #[must_use]
+    pub fn http_token_file(&self) -> PathBuf {
+        self.root.join("http.token")
+    }
+
     #[must_use]
     pub fn pid_file(&self) -> PathBuf {
         self.root.join("daemon.pid")
     }
 
+    #[must_use]
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join("config.toml")
+    }
+
     pub fn ensure_dirs(&self) -> anyhow::Result<()> {
         std::fs::create_dir_all(&self.root)?;
         std::fs::create_dir_all(self.logs_dir())?;
+        std::fs::create_dir_all(self.results_dir())?;
+        std::fs::create_dir_all(self.exits_dir())?;
+        std::fs::create_dir_all(self.artifacts_dir())?;
         Ok(())
     }
 }
@@ -0,0 +1,346 @@
+//! Pipelines for `jb flow run`: steps that start once the steps they depend
+//! on have finished, run by the daemon as one group.
+//!
+//! ```toml
+//! name = "release"
+//! stages = ["build", "test", "publish"]
+//! env = { CI = "1" }
+//!
+//! [steps.build]
+//! stage = "build"
+//! command = "cargo build --release"
+//! artifacts = ["target/release/app"]
+//!
+//! [steps.test]
+//! stage = "test"
+//! command = "cargo test"
+//!
+//! [steps.publish]
+//! stage = "publish"
+//! command = "./publish.sh $JB_ARTIFACTS_BUILD/target/release/app"
+//!
+//! [steps.notify]
+//! command = "./notify.sh"
+//! depends_on = ["publish"]
+//! when = "on_failure"
+//! ```
+//!
+//! A step in a stage depends on every step in the stage before it, as well as
+//! on its own `depends_on`.
+
+use crate::core::{RunWhen, artifact, parse_duration, validate_env_name};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    pub command: String,
+    pub description: Option<String>,
+    /// One of the pipeline's `stages`
+    pub stage: Option<String>,
+    /// Steps that must finish first
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Whether to run depending on how those steps finished
+    #[serde(default)]
+    pub when: RunWhen,
+    /// Added to the pipeline's `env`
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Duration string, e.g. "10m"
+    pub timeout: Option<String>,
+    /// Working directory relative to the pipeline file
+    pub cwd: Option<PathBuf>,
+    /// Jobs in the same concurrency group run one at a time
+    pub group: Option<String>,
+    /// Collected on exit and exported to later steps as `JB_ARTIFACTS_<STEP>`
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    /// Defaults to the file name without its extension
+    pub name: Option<String>,
+    /// Stage names in the order they run
+    #[serde(default)]
+    pub stages: Vec<String>,
+    /// Environment for every step
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub steps: BTreeMap<String, Step>,
+}
+
+impl Pipeline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("in {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let pipeline: Self = toml::from_str(text)?;
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            bail!("no steps defined");
+        }
+        for key in self.env.keys() {
+            validate_env_name(key).context("env")?;
+        }
+        for (i, stage) in self.stages.iter().enumerate() {
+            if self.stages[..i].contains(stage) {
+                bail!("stage '{stage}' is listed twice");
+            }
+        }
+        for (name, step) in &self.steps {
+            if step.command.trim().is_empty() {
+                bail!("step '{name}' has an empty command");
+            }
+            if let Some(stage) = &step.stage
+                && !self.stages.contains(stage)
+            {
+                bail!("step '{name}' is in stage '{stage}', which isn't listed in stages");
+            }
+            if let Some(t) = &step.timeout {
+                parse_duration(t).with_context(|| format!("step '{name}' timeout"))?;
+            }
+            if let Some(cwd) = &step.cwd
+                && (cwd.is_absolute()
+                    || cwd
+                        .components()
+                        .any(|c| matches!(c, std::path::Component::ParentDir)))
+            {
+                bail!("step '{name}' cwd must be inside the pipeline's directory");
+            }
+            for key in step.env.keys() {
+                validate_env_name(key).with_context(|| format!("step '{name}' env"))?;
+            }
+            for pattern in &step.artifacts {
+                artifact::validate_pattern(pattern)
+                    .with_context(|| format!("step '{name}' artifacts"))?;
+            }
+            for dep in &step.depends_on {
+                if !self.steps.contains_key(dep) {
+                    bail!("step '{name}' depends on unknown step '{dep}'");
+                }
+            }
+            if step.when != RunWhen::OnSuccess && self.needs(name).is_empty() {
+                bail!(
+                    "step '{name}' runs {} but depends on no other step",
+                    step.when
+                );
+            }
+        }
+        let mut exported: BTreeMap<String, &str> = BTreeMap::new();
+        for (name, _) in self.steps.iter().filter(|(_, s)| !s.artifacts.is_empty()) {
+            if let Some(other) = exported.insert(artifacts_env_name(name), name) {
+                bail!(
+                    "steps '{other}' and '{name}' would both export {}",
+                    artifacts_env_name(name)
+                );
+            }
+        }
+        // Rejects cycles
+        self.order()?;
+        Ok(())
+    }
+
+    /// Steps `name` depends on: its `depends_on` and every step in the
+    /// nearest earlier stage that has any.
+    #[must_use]
+    pub fn needs(&self, name: &str) -> Vec<String> {
+        let Some(step) = self.steps.get(name) else {
+            return Vec::new();
+        };
+        let mut needs: BTreeSet<String> = step.depends_on.iter().cloned().collect();
+        let position = step
+            .stage
+            .as_ref()
+            .and_then(|stage| self.stages.iter().position(|s| s == stage));
+        if let Some(position) = position {
+            for stage in self.stages[..position].iter().rev() {
+                let in_stage: Vec<&String> = self
+                    .steps
+                    .iter()
+                    .filter(|(_, s)| s.stage.as_ref() == Some(stage))
+                    .map(|(n, _)| n)
+                    .collect();
+                if !in_stage.is_empty() {
+                    needs.extend(in_stage.into_iter().cloned());
+                    break;
+                }
+            }
+        }
+        needs.into_iter().collect()
+    }
+
+    /// Every step, each after the steps it needs, earlier stages first.
+    pub fn order(&self) -> Result<Vec<String>> {
+        fn visit(
+            pipeline: &Pipeline,
+            name: &str,
+            stack: &mut Vec<String>,
+            order: &mut Vec<String>,
+        ) -> Result<()> {
+            if order.iter().any(|n| n == name) {
+                return Ok(());
+            }
+            if stack.iter().any(|n| n == name) {
+                bail!("dependency cycle: {} -> {name}", stack.join(" -> "));
+            }
+            stack.push(name.to_string());
+            for dep in pipeline.needs(name) {
+                visit(pipeline, &dep, stack, order)?;
+            }
+            stack.pop();
+            order.push(name.to_string());
+            Ok(())
+        }
+
+        let mut names: Vec<&String> = self.steps.keys().collect();
+        names.sort_by_key(|name| {
+            self.steps[*name]
+                .stage
+                .as_ref()
+                .and_then(|stage| self.stages.iter().position(|s| s == stage))
+        });
+        let mut order = Vec::new();
+        for name in names {
+            visit(self, name, &mut Vec::new(), &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Steps `name` depends on, directly or through other steps.
+    #[must_use]
+    pub fn ancestors(&self, name: &str) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        let mut queue = self.needs(name);
+        while let Some(dep) = queue.pop() {
+            if found.insert(dep.clone()) {
+                queue.extend(self.needs(&dep));
+            }
+        }
+        found
+    }
+}
+
+/// `JB_ARTIFACTS_<STEP>`, with the step name uppercased and anything other
+/// than letters and digits replaced by `_`
+#[must_use]
+pub fn artifacts_env_name(step: &str) -> String {
+    let step: String = step
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("JB_ARTIFACTS_{step}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        stages = ["build", "test", "publish"]
+        env = { CI = "1" }
+
+        [steps.build]
+        stage = "build"
+        command = "cargo build --release"
+        artifacts = ["target/release/app"]
+
+        [steps.lint]
+        stage = "test"
+        command = "cargo clippy"
+
+        [steps.test]
+        stage = "test"
+        command = "cargo test"
+        env = { RUST_BACKTRACE = "1" }
+
+        [steps.publish]
+        stage = "publish"
+        command = "./publish.sh"
+
+        [steps.notify]
+        command = "./notify.sh"
+        depends_on = ["publish"]
+        when = "on_failure"
+    "#;
+
+    #[test]
+    fn test_stages_add_dependencies() {
+        let pipeline = Pipeline::parse(SAMPLE).unwrap();
+        assert!(pipeline.needs("build").is_empty());
+        assert_eq!(pipeline.needs("test"), ["build"]);
+        assert_eq!(pipeline.needs("publish"), ["lint", "test"]);
+        assert_eq!(pipeline.needs("notify"), ["publish"]);
+        assert_eq!(pipeline.steps["notify"].when, RunWhen::OnFailure);
+        assert_eq!(
+            pipeline.ancestors("notify").into_iter().collect::<Vec<_>>(),
+            ["build", "lint", "publish", "test"]
+        );
+
+        let order = pipeline.order().unwrap();
+        assert_eq!(order, ["build", "lint", "test", "publish", "notify"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_pipelines() {
+        let cycle = r#"
+            [steps.a]
+            command = "a"
+            depends_on = ["b"]
+            [steps.b]
+            command = "b"
+            depends_on = ["a"]
+        "#;
+        assert!(
+            Pipeline::parse(cycle)
+                .unwrap_err()
+                .to_string()
+                .contains("cycle")
+        );
+
+        let unknown_stage = "[steps.a]\ncommand = \"a\"\nstage = \"build\"";
+        assert!(Pipeline::parse(unknown_stage).is_err());
+
+        let lonely_cleanup = "[steps.a]\ncommand = \"a\"\nwhen = \"always\"";
+        assert!(Pipeline::parse(lonely_cleanup).is_err());
+
+        let bad_when = "[steps.a]\ncommand = \"a\"\nwhen = \"sometimes\"";
+        assert!(Pipeline::parse(bad_when).is_err());
+
+        assert!(Pipeline::parse("name = \"empty\"").is_err());
+
+        let same_env = r#"
+            [steps.build-a]
+            command = "a"
+            artifacts = ["a.out"]
+            [steps.build_a]
+            command = "b"
+            artifacts = ["b.out"]
+        "#;
+        assert!(
+            Pipeline::parse(same_env)
+                .unwrap_err()
+                .to_string()
+                .contains("JB_ARTIFACTS_BUILD_A")
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Telling a job's process apart from an unrelated one that later got the same PID.
+//!
+//! A PID alone is only unique among running processes: after the process exits
+//! (or the machine reboots) it can be handed to something else. A fingerprint
+//! pairs the kernel's boot ID with the process start time from
+//! `/proc/<pid>/stat`, which together identify one process for good.
+
+use crate::core::kill_process_group;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// `/bin/sh -c` script that runs a job's command and writes its exit code to
+/// a file, so the result survives even if no daemon is waiting on it.
+///
+/// Arguments: `$0` is the shell for the command, `$1` the command and `$2` the
+/// exit file. Nothing is written if the wrapper itself is killed, as on `jb stop`.
+pub const EXIT_WRAPPER: &str = r#"trap 'echo $? > "$2"' EXIT; "$0" -c "$1""#;
+
+/// Exit code written by [`EXIT_WRAPPER`] and when the job finished (the file's mtime).
+#[must_use]
+pub fn read_exit_file(path: &Path) -> Option<(i32, DateTime<Utc>)> {
+    let code = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    let finished_at = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some((code, finished_at.into()))
+}
+
+/// Check if a process is still alive by sending signal 0.
+#[cfg(unix)]
+pub fn is_process_alive(pid: u32) -> bool {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    if pid == 0 {
+        return false;
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    let exists = kill(Pid::from_raw(pid as i32), None).is_ok();
+    // An exited process still answers until its parent reaps it, which for a
+    // job adopted from a previous daemon is whenever init gets round to it
+    exists && read_stat(pid).is_none_or(|stat| stat_field(&stat, 3) != Some("Z"))
+}
+
+#[cfg(not(unix))]
+pub fn is_process_alive(_pid: u32) -> bool {
+    false
+}
+
+/// `<boot id>:<start time>` for a running process, or None where /proc isn't available.
+#[must_use]
+pub fn fingerprint(pid: u32) -> Option<String> {
+    let boot_id = std::fs::read_to_string("/proc/sys/kernel/random/boot_id").ok()?;
+    let start_time = stat_field(&read_stat(pid)?, 22)?.to_string();
+    Some(format!("{}:{start_time}", boot_id.trim()))
+}
+
+/// Whether `pid` is alive and, if a fingerprint was recorded for it, still the same process.
+///
+/// Without a recorded fingerprint (older jobs) or a current one (no /proc) this
+/// falls back to plain liveness.
+#[must_use]
+pub fn is_same_process(pid: u32, recorded: Option<&str>) -> bool {
+    if !is_process_alive(pid) {
+        return false;
+    }
+    match (recorded, fingerprint(pid)) {
+        (Some(recorded), Some(current)) => recorded == current,
+        _ => true,
+    }
+}
+
+/// Signal the process group led by `pid`, unless the PID now belongs to
+/// another process. Returns whether a signal was sent.
+pub fn kill_if_same(pid: u32, recorded: Option<&str>, force: bool) -> bool {
+    if !is_same_process(pid, recorded) {
+        return false;
+    }
+    kill_process_group(pid, force);
+    true
+}
+
+/// Write a PID file for this process: its PID, then its fingerprint on the next line.
+pub fn write_pid_file(path: &Path) -> std::io::Result<()> {
+    let pid = std::process::id();
+    let contents = match fingerprint(pid) {
+        Some(fp) => format!("{pid}\n{fp}\n"),
+        None => format!("{pid}\n"),
+    };
+    std::fs::write(path, contents)
+}
+
+/// The PID in a PID file, if the process that wrote it is still running.
+#[must_use]
+pub fn running_pid_from_file(path: &Path) -> Option<u32> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    let pid = lines.next()?.trim().parse().ok()?;
+    let recorded = lines.next().map(str::trim).filter(|s| !s.is_empty());
+    is_same_process(pid, recorded).then_some(pid)
+}
+
+fn read_stat(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()
+}
+
+/// Field `n` (1-based, as in proc(5)) of a `/proc/<pid>/stat` line.
+///
+/// The command name (field 2) is in parentheses and may itself contain spaces
+/// and parentheses, so fields are counted from the last `)`.
+fn stat_field(stat: &str, n: usize) -> Option<&str> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(n.checked_sub(3)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_field() {
+        let stat = "4242 (my (odd) cmd) S 1 4242 4242 0 -1 4194560 120 0 0 0 1 0 0 0 20 0 1 0 987654 2000 100";
+        assert_eq!(stat_field(stat, 3), Some("S"));
+        assert_eq!(stat_field(stat, 4), Some("1"));
+        assert_eq!(stat_field(stat, 22), Some("987654"));
+        assert_eq!(stat_field(stat, 2), None);
+        assert_eq!(stat_field("garbage", 3), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fingerprint_detects_reuse() {
+        let pid = std::process::id();
+        let fp = fingerprint(pid).unwrap();
+        assert_eq!(fingerprint(pid).as_deref(), Some(fp.as_str()));
+        assert!(is_same_process(pid, Some(&fp)));
+        assert!(is_same_process(pid, None));
+
+        // Same PID, different start time or boot
+        let (boot_id, _) = fp.rsplit_once(':').unwrap();
+        assert!(!is_same_process(pid, Some(&format!("{boot_id}:1"))));
+        assert!(!kill_if_same(pid, Some("other-boot:1"), false));
+    }
+
+    #[test]
+    fn test_exit_wrapper_records_exit_code() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let exit_file = tmp.path().join("job.exit");
+        for (command, expected) in [("true", 0), ("echo hi; exit 3", 3), ("kill -9 $$", 137)] {
+            let status = std::process::Command::new("/bin/sh")
+                .args(["-c", EXIT_WRAPPER, "sh", command])
+                .arg(&exit_file)
+                .stdout(std::process::Stdio::null())
+                .status()
+                .unwrap();
+            assert_eq!(status.code(), Some(expected));
+            let (code, _) = read_exit_file(&exit_file).unwrap();
+            assert_eq!(code, expected, "{command}");
+        }
+        assert!(read_exit_file(&tmp.path().join("missing")).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pid_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("daemon.pid");
+        write_pid_file(&path).unwrap();
+        assert_eq!(running_pid_from_file(&path), Some(std::process::id()));
+
+        // A stale file whose PID was taken by another process
+        std::fs::write(&path, format!("{}\nold-boot:1\n", std::process::id())).unwrap();
+        assert_eq!(running_pid_from_file(&path), None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Project task definitions from `.jb.toml` at the project root.
+//!
+//! ```toml
+//! [tasks.build]
+//! command = "cargo build"
+//! group = "cargo"
+//!
+//! [tasks.test]
+//! command = "cargo test"
+//! depends_on = ["build"]
+//! env = { RUST_BACKTRACE = "1" }
+//! timeout = "10m"
+//! group = "cargo"
+//! artifacts = ["target/nextest/junit.xml"]
+//! ```
+
+use crate::core::{artifact, parse_duration, validate_env_name};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".jb.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Task {
+    pub command: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Duration string, e.g. "10m"
+    pub timeout: Option<String>,
+    /// Working directory relative to the project root
+    pub cwd: Option<PathBuf>,
+    /// Tasks that must complete successfully first
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Jobs in the same concurrency group run one at a time
+    pub group: Option<String>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskFile {
+    #[serde(default)]
+    pub tasks: BTreeMap<String, Task>,
+}
+
+impl TaskFile {
+    /// Load `.jb.toml` from the project root, if there is one.
+    pub fn load(project: &Path) -> Result<Option<Self>> {
+        let path = project.join(FILE_NAME);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let file = Self::parse(&text).with_context(|| format!("in {}", path.display()))?;
+        Ok(Some(file))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: Self = toml::from_str(text)?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, task) in &self.tasks {
+            if task.command.trim().is_empty() {
+                bail!("task '{name}' has an empty command");
+            }
+            if let Some(t) = &task.timeout {
+                parse_duration(t).with_context(|| format!("task '{name}' timeout"))?;
+            }
+            if let Some(cwd) = &task.cwd
+                && (cwd.is_absolute()
+                    || cwd
+                        .components()
+                        .any(|c| matches!(c, std::path::Component::ParentDir)))
+            {
+                bail!("task '{name}' cwd must be inside the project");
+            }
+            for key in task.env.keys() {
+                validate_env_name(key).with_context(|| format!("task '{name}' env"))?;
+            }
+            for pattern in &task.artifacts {
+                artifact::validate_pattern(pattern)
+                    .with_context(|| format!("task '{name}' artifacts"))?;
+            }
+            for dep in &task.depends_on {
+                if !self.tasks.contains_key(dep) {
+                    bail!("task '{name}' depends on unknown task '{dep}'");
+                }
+            }
+        }
+        // Reject cycles up front
+        for name in self.tasks.keys() {
+            self.plan(name)?;
+        }
+        Ok(())
+    }
+
+    /// Tasks to run for `name`: its dependencies (transitively) in a valid
+    /// order, each once, followed by `name` itself.
+    pub fn plan(&self, name: &str) -> Result<Vec<String>> {
+        fn visit(
+            file: &TaskFile,
+            name: &str,
+            stack: &mut Vec<String>,
+            order: &mut Vec<String>,
+        ) -> Result<()> {
+            if order.iter().any(|n| n == name) {
+                return Ok(());
+            }
+            if stack.iter().any(|n| n == name) {
+                bail!("dependency cycle: {} -> {name}", stack.join(" -> "));
+            }
+            let Some(task) = file.tasks.get(name) else {
+                bail!("unknown task '{name}'");
+            };
+            stack.push(name.to_string());
+            for dep in &task.depends_on {
+                visit(file, dep, stack, order)?;
+            }
+            stack.pop();
+            order.push(name.to_string());
+            Ok(())
+        }
+
+        let mut order = Vec::new();
+        visit(self, name, &mut Vec::new(), &mut order)?;
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [tasks.build]
+        command = "cargo build"
+        group = "cargo"
+
+        [tasks.lint]
+        command = "cargo clippy"
+        group = "cargo"
+
+        [tasks.test]
+        command = "cargo test"
+        depends_on = ["build", "lint"]
+        env = { RUST_BACKTRACE = "1" }
+        timeout = "10m"
+        cwd = "crates/core"
+        artifacts = ["target/*.xml"]
+
+        [tasks.ci]
+        command = "echo done"
+        depends_on = ["test", "build"]
+    "#;
+
+    #[test]
+    fn test_parse_tasks() {
+        let file = TaskFile::parse(SAMPLE).unwrap();
+        assert_eq!(file.tasks.len(), 4);
+
+        let test = &file.tasks["test"];
+        assert_eq!(test.command, "cargo test");
+        assert_eq!(test.env["RUST_BACKTRACE"], "1");
+        assert_eq!(test.timeout.as_deref(), Some("10m"));
+        assert_eq!(test.cwd, Some(PathBuf::from("crates/core")));
+        assert_eq!(file.tasks["build"].group.as_deref(), Some("cargo"));
+    }
+
+    #[test]
+    fn test_plan_orders_dependencies_once() {
+        let file = TaskFile::parse(SAMPLE).unwrap();
+        assert_eq!(file.plan("build").unwrap(), ["build"]);
+        assert_eq!(file.plan("test").unwrap(), ["build", "lint", "test"]);
+        assert_eq!(file.plan("ci").unwrap(), ["build", "lint", "test", "ci"]);
+        assert!(file.plan("deploy").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_files() {
+        let cycle = r#"
+            [tasks.a]
+            command = "a"
+            depends_on = ["b"]
+            [tasks.b]
+            command = "b"
+            depends_on = ["a"]
+        "#;
+        assert!(
+            TaskFile::parse(cycle)
+                .unwrap_err()
+                .to_string()
+                .contains("cycle")
+        );
+
+        let unknown_dep = "[tasks.a]\ncommand = \"a\"\ndepends_on = [\"nope\"]";
+        assert!(TaskFile::parse(unknown_dep).is_err());
+
+        let bad_timeout = "[tasks.a]\ncommand = \"a\"\ntimeout = \"soon\"";
+        assert!(TaskFile::parse(bad_timeout).is_err());
+
+        let escaping_cwd = "[tasks.a]\ncommand = \"a\"\ncwd = \"../other\"";
+        assert!(TaskFile::parse(escaping_cwd).is_err());
+
+        let bad_env = "[tasks.a]\ncommand = \"a\"\nenv = { \"1X\" = \"y\" }";
+        assert!(TaskFile::parse(bad_env).is_err());
+
+        let unknown_key = "[tasks.a]\ncommand = \"a\"\ncomand = \"typo\"";
+        assert!(TaskFile::parse(unknown_key).is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(TaskFile::load(tmp.path()).unwrap().is_none());
+
+        std::fs::write(tmp.path().join(FILE_NAME), SAMPLE).unwrap();
+        let file = TaskFile::load(tmp.path()).unwrap().unwrap();
+        assert!(file.tasks.contains_key("ci"));
+    }
+}
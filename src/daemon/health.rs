@@ -0,0 +1,124 @@
+use crate::core::{EventKind, HealthAction, HealthCheck, HealthProbe, HealthStatus, Job};
+use crate::daemon::probe;
+use crate::daemon::spawner;
+use crate::daemon::state::DaemonState;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Run the job's health check every interval while process `pid` is running.
+///
+/// Jobs with readiness checks aren't probed until they're ready. The job turns
+/// unhealthy after `threshold` consecutive failures, which triggers the
+/// configured action once; a later success marks it healthy again.
+pub fn spawn(state: Arc<DaemonState>, job: &Job, pid: u32) {
+    let Some(check) = job.health.clone() else {
+        return;
+    };
+
+    let job_id = job.id.clone();
+    let cwd = job.cwd.clone();
+    let needs_ready = !job.ready_when.is_empty();
+
+    tokio::spawn(async move {
+        let mut failures = 0;
+        let mut status: Option<HealthStatus> = None;
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(check.interval_secs)).await;
+
+            let current = state
+                .running_jobs
+                .lock()
+                .unwrap()
+                .get(&job_id)
+                .map(|r| r.pid);
+            if current != Some(pid) {
+                return;
+            }
+            let ready = || {
+                state
+                    .get_job(&job_id)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|j| j.ready_at.is_some())
+            };
+            if needs_ready && !ready() {
+                continue;
+            }
+
+            let (ok, output) = run_probe(&check, &cwd).await;
+            failures = if ok { 0 } else { failures + 1 };
+
+            let next = if ok {
+                Some(HealthStatus::Healthy)
+            } else if failures >= check.threshold {
+                Some(HealthStatus::Unhealthy)
+            } else {
+                // Below the threshold: keep the previous state
+                status
+            };
+            let changed = next.is_some() && next != status;
+
+            {
+                let db = state.db.lock().unwrap();
+                let _ = db.update_health(&job_id, next, &output);
+                if changed {
+                    let kind = match next {
+                        Some(HealthStatus::Unhealthy) => EventKind::Unhealthy,
+                        _ => EventKind::Healthy,
+                    };
+                    let _ = db.record_event(&job_id, kind, Some(&output));
+                }
+            }
+            if !changed {
+                continue;
+            }
+            status = next;
+
+            if next == Some(HealthStatus::Unhealthy) {
+                warn!(
+                    "Job {} unhealthy after {} failed checks: {}",
+                    job_id, failures, output
+                );
+                match check.action {
+                    HealthAction::Restart => {
+                        let _ = spawner::restart_job(&state, &job_id);
+                        // The restarted process gets a fresh monitor
+                        return;
+                    }
+                    HealthAction::Stop => {
+                        let _ = spawner::stop_job(&state, &job_id, false);
+                        return;
+                    }
+                    HealthAction::Mark => {}
+                }
+            } else {
+                info!("Job {} is healthy", job_id);
+            }
+        }
+    });
+}
+
+/// Probe once, returning success and a short description of the outcome.
+async fn run_probe(check: &HealthCheck, cwd: &Path) -> (bool, String) {
+    let timeout = Duration::from_secs(check.timeout_secs);
+    match &check.probe {
+        HealthProbe::Command(cmd) => probe::command(cmd, cwd, timeout).await,
+        HealthProbe::Port(port) => {
+            if probe::tcp(*port, timeout).await {
+                (true, format!("port {port} accepting connections"))
+            } else {
+                (false, format!("port {port} not accepting connections"))
+            }
+        }
+        HealthProbe::Http(url) => {
+            match tokio::time::timeout(timeout, probe::http_status(url)).await {
+                Ok(Some(code)) => ((200..400).contains(&code), format!("HTTP {code}")),
+                Ok(None) => (false, format!("no response from {url}")),
+                Err(_) => (false, format!("timed out after {}s", check.timeout_secs)),
+            }
+        }
+    }
+}
@@ -0,0 +1,834 @@
+//! Optional HTTP/JSON API, for tools that can't speak the IPC framing.
+//!
+//! Served on `~/.jb/http.sock` when `http.socket` is set and on
+//! `127.0.0.1:<http.port>` when `http.port` is. Requests over TCP must carry
+//! `Authorization: Bearer <token>` with the token from `~/.jb/http.token`.
+//! Each connection serves one request, through the same [`handle_request`] as
+//! the CLI.
+//!
+//! - `POST /jobs` with a [`RunSpec`] body starts a job (`project` may be omitted)
+//! - `GET /jobs?status=running&limit=20` lists jobs
+//! - `GET /jobs/{id}` returns one job
+//! - `POST /jobs/{id}/stop?force=true` stops it
+//! - `GET /jobs/{id}/wait?timeout=60&ready=true` returns the job once it finishes (or is ready)
+//! - `GET /jobs/{id}/logs?follow=true` streams its output, chunked, until it exits
+//! - `GET /events?job={id}` streams events as server-sent events, resuming after
+//!   `Last-Event-ID` (or `?after=`); without `job`, only new events are sent
+//!
+//! Errors are `{"error": "..."}`: 400 for a bad request, 404 for an unknown
+//! job, 409 when the job's state doesn't allow the request, 504 when a wait
+//! times out and 500 otherwise. A wait is dropped if its client hangs up.
+
+use crate::core::ipc::{Request, Response, RunSpec};
+use crate::core::{EventKind, Paths, Status, logfile, parse_duration};
+use crate::daemon::server::{ShutdownMode, handle_request};
+use crate::daemon::state::DaemonState;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::watch;
+use tracing::{debug, error, info};
+
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+const READ_CHUNK: u64 = 64 * 1024;
+/// How often followed logs and event streams look for more
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Idle event streams get a comment this often, so closed connections are noticed
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+type ShutdownTx = watch::Sender<Option<ShutdownMode>>;
+
+/// Start the listeners enabled in the config.
+pub async fn spawn(state: &Arc<DaemonState>, shutdown_tx: &ShutdownTx) -> Result<()> {
+    let config = state.config.read().unwrap().http.clone();
+
+    if config.socket {
+        let path = state.paths.http_socket();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let listener =
+            UnixListener::bind(&path).with_context(|| format!("binding {}", path.display()))?;
+        info!("HTTP API on {}", path.display());
+
+        let (state, shutdown_tx) = (state.clone(), shutdown_tx.clone());
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream, None, state.clone(), shutdown_tx.clone()));
+                    }
+                    Err(e) => error!("HTTP accept error: {}", e),
+                }
+            }
+        });
+    }
+
+    if let Some(port) = config.port {
+        let token: Arc<str> = load_or_create_token(&state.paths)?.into();
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("binding 127.0.0.1:{port}"))?;
+        info!("HTTP API on 127.0.0.1:{}", port);
+
+        let (state, shutdown_tx) = (state.clone(), shutdown_tx.clone());
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(
+                            stream,
+                            Some(token.clone()),
+                            state.clone(),
+                            shutdown_tx.clone(),
+                        ));
+                    }
+                    Err(e) => error!("HTTP accept error: {}", e),
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// The API token, created on first use and readable only by the user.
+fn load_or_create_token(paths: &Paths) -> Result<String> {
+    use rand::Rng;
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let path = paths.http_token_file();
+    if let Ok(text) = std::fs::read_to_string(&path)
+        && !text.trim().is_empty()
+    {
+        return Ok(text.trim().to_string());
+    }
+
+    let mut rng = rand::rng();
+    let token: String = (0..32)
+        .map(|_| char::from_digit(rng.random_range(0..16), 16).unwrap())
+        .collect();
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("writing {}", path.display()))?;
+    writeln!(file, "{token}")?;
+    Ok(token)
+}
+
+/// Compare without stopping at the first difference
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn serve<S>(
+    stream: S,
+    token: Option<Arc<str>>,
+    state: Arc<DaemonState>,
+    shutdown_tx: ShutdownTx,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    if let Err(e) = handle_connection(&mut stream, token.as_deref(), &state, &shutdown_tx).await {
+        // Mostly clients hanging up on a stream
+        debug!("HTTP connection error: {:#}", e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+async fn handle_connection<S>(
+    stream: &mut BufReader<S>,
+    token: Option<&str>,
+    state: &Arc<DaemonState>,
+    shutdown_tx: &ShutdownTx,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request = match read_request(stream).await {
+        Ok(Some(request)) => request,
+        Ok(None) => return Ok(()),
+        Err(e) => return send_error(stream, 400, &format!("{e:#}")).await,
+    };
+
+    if let Some(token) = token {
+        let given = request
+            .header("authorization")
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if !given.is_some_and(|given| token_matches(given.trim(), token)) {
+            return send_error(stream, 401, "Missing or wrong token").await;
+        }
+    }
+
+    match route(stream, &request, state, shutdown_tx).await {
+        Ok(()) => Ok(()),
+        Err(RouteError::BadRequest(message)) => send_error(stream, 400, &message).await,
+        Err(RouteError::Io(e)) => Err(e),
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// Keyed by lowercase name
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+
+    /// `?force`, `?force=1` and `?force=true` all count
+    fn flag(&self, name: &str) -> bool {
+        matches!(self.param(name), Some("" | "1" | "true"))
+    }
+}
+
+async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<HttpRequest>> {
+    let mut head = (&mut *reader).take(MAX_HEADER_BYTES);
+    let mut line = String::new();
+    if head.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (path, query) = (percent_decode(path), parse_query(query));
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        head.read_line(&mut line).await?;
+        if !line.ends_with('\n') {
+            bail!("headers too large or cut short");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let len = headers
+        .get("content-length")
+        .map(|v| v.parse::<usize>())
+        .transpose()
+        .context("invalid Content-Length")?
+        .unwrap_or(0);
+    if len > MAX_BODY_BYTES {
+        bail!("body too large: {len} bytes");
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(HttpRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+enum RouteError {
+    BadRequest(String),
+    Io(anyhow::Error),
+}
+
+impl From<anyhow::Error> for RouteError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> RouteError {
+    RouteError::BadRequest(message.into())
+}
+
+async fn route<S>(
+    stream: &mut S,
+    request: &HttpRequest,
+    state: &Arc<DaemonState>,
+    shutdown_tx: &ShutdownTx,
+) -> Result<(), RouteError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let ipc = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["jobs"]) => run_request(&request.body)?,
+        ("GET", ["jobs"]) => Request::List {
+            status: request
+                .param("status")
+                .map(|s| s.parse::<Status>().map(|_| s.to_string()))
+                .transpose()
+                .map_err(|e| bad_request(e.to_string()))?,
+            limit: request
+                .param("limit")
+                .map(str::parse)
+                .transpose()
+                .map_err(|_| bad_request("limit must be a number"))?,
+        },
+        ("GET", ["jobs", id]) => Request::Status { id: id.to_string() },
+        ("POST", ["jobs", id, "stop"]) => Request::Stop {
+            id: id.to_string(),
+            force: request.flag("force"),
+        },
+        ("GET", ["jobs", id, "wait"]) => Request::Wait {
+            id: id.to_string(),
+            timeout_secs: request
+                .param("timeout")
+                .map(|t| t.parse().or_else(|_| parse_duration(t)))
+                .transpose()
+                .map_err(|_| bad_request("timeout must be seconds or a duration like 5m"))?,
+            ready: request.flag("ready"),
+        },
+        ("GET", ["jobs", id, "logs"]) => {
+            return Ok(stream_logs(stream, state, id, request.flag("follow")).await?);
+        }
+        ("GET", ["events"]) => return stream_events(stream, state, request).await,
+        _ => return Ok(send_error(stream, 404, "Not found").await?),
+    };
+
+    let created = matches!(ipc, Request::Run(_));
+    let response = if matches!(ipc, Request::Wait { .. }) {
+        // Nobody is left to answer once the client hangs up
+        tokio::select! {
+            response = handle_request(ipc, state, shutdown_tx) => response,
+            () = hung_up(stream) => return Ok(()),
+        }
+    } else {
+        handle_request(ipc, state, shutdown_tx).await
+    };
+    Ok(send_response(stream, response, created).await?)
+}
+
+/// Resolves once the client closes the connection. Clients send nothing
+/// after their request, so anything read is discarded.
+async fn hung_up<S: AsyncRead + Unpin>(stream: &mut S) {
+    let mut buf = [0u8; 512];
+    while let Ok(n) = stream.read(&mut buf).await {
+        if n == 0 {
+            return;
+        }
+    }
+}
+
+/// A run request from a JSON [`RunSpec`], checked as `jb run` checks it.
+fn run_request(body: &[u8]) -> Result<Request, RouteError> {
+    let spec: RunSpec =
+        serde_json::from_slice(body).map_err(|e| bad_request(format!("Invalid job spec: {e}")))?;
+    if spec.command.trim().is_empty() {
+        return Err(bad_request("command is empty"));
+    }
+    if !Path::new(&spec.cwd).is_absolute() {
+        return Err(bad_request("cwd must be an absolute path"));
+    }
+    spec.validate().map_err(|e| bad_request(e.to_string()))?;
+    Ok(Request::Run(Box::new(spec)))
+}
+
+/// Send a job's output, including segments rotated out of its log. With
+/// `follow`, keep sending until the job has exited.
+async fn stream_logs<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    state: &Arc<DaemonState>,
+    id: &str,
+    follow: bool,
+) -> Result<()> {
+    let job = match state.get_job(id)? {
+        Some(job) => job,
+        None => return send_error(stream, 404, &format!("Job not found: {id}")).await,
+    };
+
+    write_head(
+        stream,
+        200,
+        &[
+            ("Content-Type", "text/plain; charset=utf-8"),
+            ("Transfer-Encoding", "chunked"),
+        ],
+    )
+    .await?;
+
+    let mut reader = logfile::LogReader::new(&state.paths, &job.id, 0);
+    loop {
+        // Checked before reading so output written just before exit is sent
+        let done = !follow
+            || state
+                .get_job(&job.id)?
+                .is_none_or(|job| job.status.is_terminal());
+
+        // Everything written so far, across segments rotated out of the log
+        loop {
+            let (returned, data) = tokio::task::spawn_blocking(move || {
+                let data = reader.read(READ_CHUNK);
+                (reader, data)
+            })
+            .await?;
+            reader = returned;
+            let data = data?;
+            if data.is_empty() {
+                break;
+            }
+            write_chunk(stream, &data).await?;
+        }
+
+        if done {
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    stream.write_all(b"0\r\n\r\n").await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Send events as server-sent events, each with its sequence number as `id`.
+/// A single job's stream ends once it has finished.
+async fn stream_events<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    state: &Arc<DaemonState>,
+    request: &HttpRequest,
+) -> Result<(), RouteError> {
+    let job_id = match request.param("job") {
+        Some(id) => match state.get_job(id)? {
+            Some(job) => Some(job.id),
+            None => {
+                return Ok(send_error(stream, 404, &format!("Job not found: {id}")).await?);
+            }
+        },
+        None => None,
+    };
+
+    let mut last_id = match request
+        .header("last-event-id")
+        .or_else(|| request.param("after"))
+    {
+        Some(id) => id
+            .parse()
+            .map_err(|_| bad_request("Last-Event-ID must be an event number"))?,
+        // A job's whole history, or only what happens from now on
+        None if job_id.is_some() => 0,
+        None => state
+            .db
+            .lock()
+            .unwrap()
+            .events(None, 0, Some(1))?
+            .last()
+            .map_or(0, |e| e.id),
+    };
+
+    write_head(
+        stream,
+        200,
+        &[
+            ("Content-Type", "text/event-stream"),
+            ("Cache-Control", "no-cache"),
+        ],
+    )
+    .await?;
+
+    let mut idle = Duration::ZERO;
+    loop {
+        let events = state
+            .db
+            .lock()
+            .unwrap()
+            .events(job_id.as_deref(), last_id, None)?;
+        let mut finished = false;
+        for event in &events {
+            let frame = format!(
+                "id: {}\nevent: {}\ndata: {}\n\n",
+                event.id,
+                event.kind.as_str(),
+                serde_json::to_string(event).map_err(anyhow::Error::from)?
+            );
+            stream
+                .write_all(frame.as_bytes())
+                .await
+                .map_err(anyhow::Error::from)?;
+            last_id = event.id;
+            finished |= event.kind == EventKind::Finished;
+        }
+
+        if events.is_empty() {
+            idle += POLL_INTERVAL;
+            if idle >= KEEPALIVE_INTERVAL {
+                stream
+                    .write_all(b": keepalive\n\n")
+                    .await
+                    .map_err(anyhow::Error::from)?;
+                idle = Duration::ZERO;
+            }
+        } else {
+            idle = Duration::ZERO;
+        }
+        stream.flush().await.map_err(anyhow::Error::from)?;
+
+        if let Some(id) = &job_id
+            && (finished
+                || (events.is_empty()
+                    && state
+                        .get_job(id)?
+                        .is_none_or(|job| job.status.is_terminal())))
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn send_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: Response,
+    created: bool,
+) -> Result<()> {
+    let (status, body) = match response {
+        Response::Job(job) => (if created { 201 } else { 200 }, serde_json::to_value(job)?),
+        Response::Jobs(jobs) => (200, serde_json::to_value(jobs)?),
+        Response::Ok => (200, serde_json::json!({ "ok": true })),
+        Response::Error(e) => return send_error(stream, error_status(&e), &e).await,
+        other => (200, serde_json::to_value(other)?),
+    };
+    send_json(stream, status, &body).await
+}
+
+/// Status for an error message from [`handle_request`]. Only the job a
+/// request names makes it a 404; a missing dependency is a bad request.
+fn error_status(message: &str) -> u16 {
+    if message.starts_with("Job not found: ") {
+        404
+    } else if message == "Wait timed out" {
+        504
+    } else if message.starts_with("Dependency not found: ")
+        || message.ends_with("has no readiness checks")
+    {
+        400
+    } else if [
+        "is not running",
+        "is not a service",
+        "is in use",
+        "exited before becoming ready",
+    ]
+    .iter()
+    .any(|conflict| message.contains(conflict))
+    {
+        // The job isn't in a state that allows the request
+        409
+    } else {
+        500
+    }
+}
+
+async fn send_error<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    message: &str,
+) -> Result<()> {
+    send_json(stream, status, &serde_json::json!({ "error": message })).await
+}
+
+async fn send_json<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    body: &serde_json::Value,
+) -> Result<()> {
+    let body = serde_json::to_vec(body)?;
+    let len = body.len().to_string();
+    write_head(
+        stream,
+        status,
+        &[
+            ("Content-Type", "application/json"),
+            ("Content-Length", &len),
+        ],
+    )
+    .await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn write_head<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    headers: &[(&str, &str)],
+) -> Result<()> {
+    use std::fmt::Write;
+
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        500 => "Internal Server Error",
+        504 => "Gateway Timeout",
+        _ => "",
+    };
+    let mut head = format!("HTTP/1.1 {status} {reason}\r\nConnection: close\r\n");
+    for (name, value) in headers {
+        let _ = write!(head, "{name}: {value}\r\n");
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    Ok(())
+}
+
+async fn write_chunk<S: AsyncWrite + Unpin>(stream: &mut S, data: &[u8]) -> Result<()> {
+    stream
+        .write_all(format!("{:x}\r\n", data.len()).as_bytes())
+        .await?;
+    stream.write_all(data).await?;
+    stream.write_all(b"\r\n").await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Config, Job};
+    use std::path::PathBuf;
+
+    fn setup() -> (tempfile::TempDir, Arc<DaemonState>) {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        let state = DaemonState::new(&paths, Config::default()).unwrap();
+        (tmp, Arc::new(state))
+    }
+
+    fn insert_job(state: &DaemonState, id: &str, status: Status) {
+        let mut job = Job::new(
+            id.to_string(),
+            "echo hello".to_string(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+        );
+        job.status = status;
+        state.db.lock().unwrap().insert(&job).unwrap();
+    }
+
+    /// Send a raw request and return the raw response
+    async fn request(state: &Arc<DaemonState>, token: Option<&str>, raw: &str) -> String {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let (shutdown_tx, _rx) = watch::channel(None);
+        let server = tokio::spawn(serve(
+            server,
+            token.map(Into::into),
+            state.clone(),
+            shutdown_tx,
+        ));
+        client.write_all(raw.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("status=running&limit=5&force&name=my%20job+x");
+        assert_eq!(query["status"], "running");
+        assert_eq!(query["limit"], "5");
+        assert_eq!(query["force"], "");
+        assert_eq!(query["name"], "my job x");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[tokio::test]
+    async fn test_job_endpoints() {
+        let (_tmp, state) = setup();
+        insert_job(&state, "abcd", Status::Completed);
+
+        let response = request(&state, None, "GET /jobs/abcd HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(r#""id":"abcd""#));
+
+        let response = request(&state, None, "GET /jobs?limit=5 HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains(r#""id":"abcd""#));
+
+        let response = request(&state, None, "GET /jobs/nope HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+        assert!(response.contains(r#"{"error":"Job not found: nope"}"#));
+
+        let response = request(&state, None, "POST /jobs/abcd/stop HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 409"));
+
+        let response = request(&state, None, "GET /jobs?status=done HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        insert_job(&state, "runs", Status::Running);
+        let raw = "GET /jobs/runs/wait?timeout=0 HTTP/1.1\r\n\r\n";
+        let response = request(&state, None, raw).await;
+        assert!(response.starts_with("HTTP/1.1 504"));
+
+        let body = r#"{"command": "true", "cwd": "relative"}"#;
+        let raw = format!(
+            "POST /jobs HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let response = request(&state, None, &raw).await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+        assert!(response.contains("cwd must be an absolute path"));
+
+        let body = r#"{"command": "true", "cwd": "/tmp", "depends_on": ["nope"]}"#;
+        let raw = format!(
+            "POST /jobs HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let response = request(&state, None, &raw).await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+        assert!(response.contains("Dependency not found: nope"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_dropped_when_client_hangs_up() {
+        let (_tmp, state) = setup();
+        insert_job(&state, "runs", Status::Running);
+
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let (shutdown_tx, _rx) = watch::channel(None);
+        let server = tokio::spawn(serve(server, None, state.clone(), shutdown_tx));
+        client
+            .write_all(b"GET /jobs/runs/wait HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(2), server)
+            .await
+            .expect("wait still running")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_token_required_on_tcp() {
+        let (_tmp, state) = setup();
+        insert_job(&state, "abcd", Status::Completed);
+
+        let response = request(&state, Some("secret"), "GET /jobs/abcd HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+        let response = request(
+            &state,
+            Some("secret"),
+            "GET /jobs/abcd HTTP/1.1\r\nAuthorization: Bearer wrong!\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+        let response = request(
+            &state,
+            Some("secret"),
+            "GET /jobs/abcd HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_logs_and_events_streams() {
+        let (_tmp, state) = setup();
+        insert_job(&state, "abcd", Status::Completed);
+        std::fs::write(state.paths.log_file("abcd"), "early\n").unwrap();
+        logfile::rotate(&state.paths, "abcd", 1).unwrap();
+        std::fs::write(state.paths.log_file("abcd"), "hello\n").unwrap();
+        {
+            let db = state.db.lock().unwrap();
+            db.record_event("abcd", EventKind::Started, None).unwrap();
+            db.record_event("abcd", EventKind::Finished, Some("exit 0"))
+                .unwrap();
+        }
+
+        let response = request(&state, None, "GET /jobs/abcd/logs?follow HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains("Transfer-Encoding: chunked"));
+        assert!(response.ends_with("6\r\nearly\n\r\n6\r\nhello\n\r\n0\r\n\r\n"));
+
+        // Ends on its own once the finished event has been sent
+        let response = request(&state, None, "GET /events?job=abcd HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains("Content-Type: text/event-stream"));
+        assert!(response.contains("event: started\n"));
+        assert!(response.contains("event: finished\ndata: {"));
+
+        let raw = "GET /events?job=abcd HTTP/1.1\r\nLast-Event-ID: 1\r\n\r\n";
+        let response = request(&state, None, raw).await;
+        assert!(!response.contains("event: started"));
+        assert!(response.contains("event: finished"));
+    }
+
+    #[test]
+    fn test_token_is_created_once() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        let token = load_or_create_token(&paths).unwrap();
+        assert_eq!(token.len(), 32);
+        assert_eq!(load_or_create_token(&paths).unwrap(), token);
+        assert!(token_matches(&token, &token));
+        assert!(!token_matches("short", &token));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::core::{cleanup, logfile, parse_duration};
+use crate::daemon::state::DaemonState;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How often running jobs' logs are checked against `logs.max_size`
+const LOG_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often old jobs are removed when `retention.auto` is on
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Start the background upkeep task. Settings are re-read on every pass, so
+/// a config reload takes effect without restarting it.
+pub fn spawn(state: &Arc<DaemonState>) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut last_cleanup: Option<Instant> = None;
+        loop {
+            let (max_bytes, keep, auto_clean, max_age) = {
+                let config = state.config.read().unwrap();
+                (
+                    config.log_max_bytes(),
+                    config.logs.keep,
+                    config.retention.auto,
+                    config.retention.max_age.clone(),
+                )
+            };
+
+            if let Some(max_bytes) = max_bytes {
+                rotate_logs(&state, max_bytes, keep).await;
+            }
+            if auto_clean && last_cleanup.is_none_or(|t| t.elapsed() >= CLEANUP_INTERVAL) {
+                clean_old_jobs(&state, &max_age);
+                last_cleanup = Some(Instant::now());
+            }
+
+            tokio::time::sleep(LOG_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Rotate the log of any running job that has grown past `max_bytes`.
+async fn rotate_logs(state: &Arc<DaemonState>, max_bytes: u64, keep: usize) {
+    let ids: Vec<String> = state.running_jobs.lock().unwrap().keys().cloned().collect();
+    for id in ids {
+        let path = state.paths.log_file(&id);
+        if std::fs::metadata(&path).map_or(true, |m| m.len() <= max_bytes) {
+            continue;
+        }
+        let paths = state.paths.clone();
+        let job_id = id.clone();
+        match tokio::task::spawn_blocking(move || logfile::rotate(&paths, &job_id, keep)).await {
+            Ok(Ok(())) => info!("Rotated log of job {}", id),
+            Ok(Err(e)) => warn!("Failed to rotate log of job {}: {}", id, e),
+            Err(e) => warn!("Log rotation for job {} panicked: {}", id, e),
+        }
+    }
+}
+
+/// Remove finished jobs older than `max_age`.
+fn clean_old_jobs(state: &DaemonState, max_age: &str) {
+    let Ok(secs) = parse_duration(max_age) else {
+        return;
+    };
+    #[allow(clippy::cast_possible_wrap)] // durations won't exceed i64::MAX
+    let before = chrono::Utc::now() - chrono::Duration::seconds(secs as i64);
+    let removed = {
+        let db = state.db.lock().unwrap();
+        cleanup::purge(&state.paths, &db, before, None)
+    };
+    match removed {
+        Ok(0) => {}
+        Ok(n) => info!("Removed {} jobs older than {}", n, max_age),
+        Err(e) => warn!("Automatic cleanup failed: {}", e),
+    }
+}
@@ -1,8 +1,14 @@
+pub mod health;
+pub mod http;
+pub mod maintenance;
+pub mod probe;
+pub mod ready;
+pub mod scheduler;
 pub mod server;
 pub mod spawner;
 pub mod state;
 
-use crate::core::Paths;
+use crate::core::{Config, Paths, process};
 use anyhow::{Result, bail};
 use std::sync::Arc;
 use tracing::info;
@@ -16,19 +22,23 @@ pub async fn run() -> Result<()> {
         bail!("Daemon already running with PID {existing_pid}");
     }
 
+    let config = Config::load(&paths)?;
+
     info!("Starting job daemon");
     info!("Socket: {}", paths.socket().display());
     info!("Database: {}", paths.database().display());
 
     // Write PID file
-    std::fs::write(paths.pid_file(), std::process::id().to_string())?;
+    process::write_pid_file(&paths.pid_file())?;
 
     // Clean up stale socket
     if paths.socket().exists() {
         std::fs::remove_file(paths.socket())?;
     }
 
-    let state = Arc::new(state::DaemonState::new(&paths)?);
+    let state = Arc::new(state::DaemonState::new(&paths, config)?);
+    scheduler::recover(&state);
+    maintenance::spawn(&state);
 
     // Run the server
     let result = server::run(paths.clone(), state.clone()).await;
@@ -36,6 +46,7 @@ pub async fn run() -> Result<()> {
     // Cleanup
     let _ = std::fs::remove_file(paths.pid_file());
     let _ = std::fs::remove_file(paths.socket());
+    let _ = std::fs::remove_file(paths.http_socket());
 
     result
 }
@@ -47,31 +58,10 @@ fn check_existing_daemon(paths: &Paths) -> Option<u32> {
         return None;
     }
 
-    let pid_str = std::fs::read_to_string(&pid_file).ok()?;
-    let pid: u32 = pid_str.trim().parse().ok()?;
-
-    // Check if process is running
-    if is_process_running(pid) {
-        Some(pid)
-    } else {
-        // Stale PID file, clean it up
+    let pid = process::running_pid_from_file(&pid_file);
+    if pid.is_none() {
+        // Stale PID file (or its PID now belongs to another process), clean it up
         let _ = std::fs::remove_file(&pid_file);
-        None
     }
-}
-
-#[cfg(unix)]
-fn is_process_running(pid: u32) -> bool {
-    use nix::sys::signal::kill;
-    use nix::unistd::Pid;
-    // Signal 0 (None) doesn't send a signal but checks if process exists
-    #[allow(clippy::cast_possible_wrap)] // PIDs are always < i32::MAX
-    let pid = Pid::from_raw(pid as i32);
-    kill(pid, None).is_ok()
-}
-
-#[cfg(not(unix))]
-fn is_process_running(_pid: u32) -> bool {
-    // On non-Unix, assume not running (conservative)
-    false
+    pid
 }
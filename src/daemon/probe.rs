@@ -0,0 +1,190 @@
+//! Probes shared by readiness and health checks.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Whether something accepts TCP connections on `127.0.0.1:port`.
+pub async fn tcp(port: u16, timeout: Duration) -> bool {
+    matches!(
+        tokio::time::timeout(timeout, TcpStream::connect(("127.0.0.1", port))).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Whether a GET of `url` answers with a 2xx or 3xx status.
+pub async fn http(url: &str, timeout: Duration) -> bool {
+    matches!(
+        tokio::time::timeout(timeout, http_status(url)).await,
+        Ok(Some(200..=399))
+    )
+}
+
+/// Largest amount of command output kept from a health check
+const MAX_COMMAND_OUTPUT: usize = 1024;
+
+/// Run `cmd` with `sh -c` in `cwd`. Returns whether it exited 0 within `timeout`,
+/// and its combined output (tail, trimmed) or the reason it failed. The command
+/// runs in its own process group, which is killed whole on timeout.
+pub async fn command(cmd: &str, cwd: &Path, timeout: Duration) -> (bool, String) {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => return (false, format!("failed to run: {e}")),
+    };
+    let pid = child.id().unwrap_or(0);
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            let text = text.trim();
+            let start = text.len().saturating_sub(MAX_COMMAND_OUTPUT);
+            let start = (start..text.len())
+                .find(|&i| text.is_char_boundary(i))
+                .unwrap_or(text.len());
+            let mut summary = text[start..].to_string();
+            if !output.status.success() {
+                let code = output
+                    .status
+                    .code()
+                    .map_or_else(|| "signal".to_string(), |c| c.to_string());
+                summary = if summary.is_empty() {
+                    format!("exit {code}")
+                } else {
+                    format!("exit {code}: {summary}")
+                };
+            }
+            (output.status.success(), summary)
+        }
+        Ok(Err(e)) => (false, format!("failed to run: {e}")),
+        Err(_) => {
+            crate::core::kill_process_group(pid, true);
+            (false, format!("timed out after {}s", timeout.as_secs()))
+        }
+    }
+}
+
+/// Issue a minimal HTTP/1.0 GET and return the response status code.
+pub async fn http_status(url: &str) -> Option<u16> {
+    let (host, port, path) = parse_http_url(url)?;
+    let mut stream = TcpStream::connect((host.as_str(), port)).await.ok()?;
+
+    let request = format!("GET {path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.ok()?;
+
+    // The status line is all we need
+    let mut buf = vec![0u8; 256];
+    let mut len = 0;
+    while len < buf.len() && !buf[..len].contains(&b'\n') {
+        let n = stream.read(&mut buf[len..]).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+
+    let line = String::from_utf8_lossy(&buf[..len]);
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Split `http://host[:port][/path]` into its parts.
+fn parse_http_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((h, p)) => (h, p.parse().ok()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_url() {
+        assert_eq!(
+            parse_http_url("http://localhost:3000/health"),
+            Some(("localhost".into(), 3000, "/health".into()))
+        );
+        assert_eq!(
+            parse_http_url("http://example.com"),
+            Some(("example.com".into(), 80, "/".into()))
+        );
+        assert_eq!(parse_http_url("https://example.com"), None);
+        assert_eq!(parse_http_url("http://:80/"), None);
+        assert_eq!(parse_http_url("http://host:notaport/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_and_http_probes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 512];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(b"HTTP/1.0 204 No Content\r\n\r\n").await;
+            }
+        });
+
+        let timeout = Duration::from_secs(2);
+        assert!(tcp(port, timeout).await);
+        assert!(http(&format!("http://127.0.0.1:{port}/health"), timeout).await);
+    }
+
+    #[tokio::test]
+    async fn test_command_probe() {
+        let cwd = std::env::temp_dir();
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(command("echo ok", &cwd, timeout).await, (true, "ok".into()));
+        assert_eq!(
+            command("echo down >&2; exit 2", &cwd, timeout).await,
+            (false, "exit 2: down".into())
+        );
+        let (ok, output) = command("sleep 5", &cwd, Duration::from_millis(100)).await;
+        assert!(!ok);
+        assert!(output.starts_with("timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ok, _) = command(
+            "sleep 30 & echo $! > bg.pid; wait",
+            dir.path(),
+            Duration::from_millis(300),
+        )
+        .await;
+        assert!(!ok);
+
+        let pid: u32 = std::fs::read_to_string(dir.path().join("bg.pid"))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!crate::core::process::is_process_alive(pid));
+    }
+}
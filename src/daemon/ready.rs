@@ -0,0 +1,197 @@
+use crate::core::{EventKind, Job, ReadyCheck};
+use crate::daemon::probe;
+use crate::daemon::state::DaemonState;
+use regex::Regex;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often pending checks are re-evaluated
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Per-attempt timeout for network probes
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Watch a running job until all its readiness checks pass, then mark it ready.
+///
+/// Checks latch: once one passes it is not re-evaluated. The watcher gives up
+/// silently when the job stops running or its process `pid` is replaced by a
+/// restart. Log checks only consider output after `log_offset`.
+pub fn spawn(state: Arc<DaemonState>, job: &Job, pid: u32, log_offset: u64) {
+    if job.ready_when.is_empty() {
+        return;
+    }
+
+    let job_id = job.id.clone();
+    let cwd = job.cwd.clone();
+    let checks = job.ready_when.clone();
+
+    tokio::spawn(async move {
+        let log_path = state.paths.log_file(&job_id);
+        let mut pending: Vec<Pending> = checks
+            .iter()
+            .filter_map(|c| Pending::new(c, &cwd))
+            .collect();
+        let mut log = LogScanner {
+            offset: log_offset,
+            partial: Vec::new(),
+        };
+
+        while !pending.is_empty() {
+            let current = state
+                .running_jobs
+                .lock()
+                .unwrap()
+                .get(&job_id)
+                .map(|r| r.pid);
+            if current != Some(pid) {
+                return;
+            }
+
+            // Read new output once per tick and share it across log checks
+            let lines = if pending.iter().any(|p| matches!(p, Pending::Log(_))) {
+                log.poll(&log_path)
+            } else {
+                Vec::new()
+            };
+
+            let mut still_pending = Vec::with_capacity(pending.len());
+            for check in pending {
+                if !check.passed(&lines).await {
+                    still_pending.push(check);
+                }
+            }
+            pending = still_pending;
+
+            if !pending.is_empty() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+
+        let detail = checks
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let db = state.db.lock().unwrap();
+        if let Err(e) = db.update_ready(&job_id) {
+            warn!("Failed to mark job {} ready: {}", job_id, e);
+            return;
+        }
+        let _ = db.record_event(&job_id, EventKind::Ready, Some(&detail));
+        info!("Job {} is ready ({})", job_id, detail);
+    });
+}
+
+/// A readiness check that hasn't passed yet, prepared for repeated evaluation
+enum Pending {
+    Log(Regex),
+    Port(u16),
+    Http(String),
+    File(std::path::PathBuf),
+}
+
+impl Pending {
+    fn new(check: &ReadyCheck, cwd: &Path) -> Option<Self> {
+        Some(match check {
+            ReadyCheck::Log(pattern) => match Regex::new(pattern) {
+                Ok(re) => Self::Log(re),
+                Err(e) => {
+                    warn!("Ignoring invalid readiness pattern '{}': {}", pattern, e);
+                    return None;
+                }
+            },
+            ReadyCheck::Port(port) => Self::Port(*port),
+            ReadyCheck::Http(url) => Self::Http(url.clone()),
+            ReadyCheck::File(path) => Self::File(cwd.join(path)),
+        })
+    }
+
+    async fn passed(&self, new_lines: &[String]) -> bool {
+        match self {
+            Self::Log(re) => new_lines.iter().any(|l| re.is_match(l)),
+            Self::Port(port) => probe::tcp(*port, PROBE_TIMEOUT).await,
+            Self::Http(url) => probe::http(url, PROBE_TIMEOUT).await,
+            Self::File(path) => path.exists(),
+        }
+    }
+}
+
+/// Incrementally reads a growing log file, yielding lines not seen before.
+#[derive(Default)]
+struct LogScanner {
+    offset: u64,
+    /// Trailing output without a newline yet
+    partial: Vec<u8>,
+}
+
+impl LogScanner {
+    /// New lines since the last poll. A trailing partial line is included so
+    /// prompts without a newline can match, and is re-read once completed.
+    fn poll(&mut self, path: &Path) -> Vec<String> {
+        let Ok(mut file) = std::fs::File::open(path) else {
+            return Vec::new();
+        };
+        // The log was rotated: start over from the top
+        if file.metadata().is_ok_and(|m| m.len() < self.offset) {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if file.seek(SeekFrom::Start(self.offset)).is_err() {
+            return Vec::new();
+        }
+        let mut buf = Vec::new();
+        if file.read_to_end(&mut buf).is_err() {
+            return Vec::new();
+        }
+        self.offset += buf.len() as u64;
+        self.partial.extend_from_slice(&buf);
+
+        let mut lines: Vec<String> = Vec::new();
+        let complete = self
+            .partial
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        for line in self.partial[..complete].split_inclusive(|&b| b == b'\n') {
+            let text = String::from_utf8_lossy(line);
+            lines.push(text.trim_end_matches(['\n', '\r']).to_string());
+        }
+        self.partial.drain(..complete);
+        if !self.partial.is_empty() {
+            lines.push(String::from_utf8_lossy(&self.partial).to_string());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_log_scanner_incremental() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("job.log");
+        let mut file = std::fs::File::create(&path).unwrap();
+        let mut scanner = LogScanner::default();
+
+        assert!(scanner.poll(&tmp.path().join("missing.log")).is_empty());
+
+        write!(file, "compiling\nlisten").unwrap();
+        let lines = scanner.poll(&path);
+        assert!(lines.contains(&"compiling".to_string()));
+        assert!(lines.contains(&"listen".to_string()));
+
+        writeln!(file, "ing on :3000").unwrap();
+        let lines = scanner.poll(&path);
+        assert!(lines.contains(&"listening on :3000".to_string()));
+        assert!(!lines.contains(&"compiling".to_string()));
+
+        // Nothing new
+        assert!(scanner.poll(&path).is_empty());
+    }
+}
@@ -0,0 +1,459 @@
+use crate::core::ipc::Response;
+use crate::core::{EventKind, Job, RunWhen, Status, process};
+use crate::daemon::spawner;
+use crate::daemon::state::DaemonState;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Jobs waiting to start, highest priority first and otherwise in
+/// submission order.
+///
+/// A job starts once every job it depends on has completed, no other job in
+/// its concurrency group (within the same project) is running, and the
+/// configured concurrency limits allow it. If a dependency ends any other way
+/// the job is skipped, and so are its dependents. Jobs that run `on_failure`
+/// or `always` instead wait for every dependency to finish, and `on_failure`
+/// jobs are skipped if they all completed.
+#[derive(Default)]
+pub struct JobQueue {
+    pub pending: Vec<Job>,
+    /// (project, group) pairs with a job currently running
+    pub busy_groups: HashSet<(PathBuf, String)>,
+    /// Started jobs and their projects, for the concurrency limits
+    pub active: HashMap<String, PathBuf>,
+}
+
+impl JobQueue {
+    /// Queue `job` behind the jobs with the same or a higher priority.
+    pub fn push(&mut self, job: Job) {
+        let at = self
+            .pending
+            .iter()
+            .position(|queued| queued.priority < job.priority)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(at, job);
+    }
+}
+
+enum Decision {
+    Start,
+    Wait,
+    /// No longer pending (e.g. cancelled); forget it
+    Drop,
+    Skip(String),
+}
+
+fn group_key(job: &Job) -> Option<(PathBuf, String)> {
+    job.concurrency_group
+        .as_ref()
+        .map(|group| (job.project.clone(), group.clone()))
+}
+
+/// Queue a newly inserted job and start whatever is runnable.
+pub fn enqueue(state: &Arc<DaemonState>, job: Job) {
+    state.queue.lock().unwrap().push(job);
+    dispatch(state);
+}
+
+/// Start every queued job whose dependencies and group allow it, and skip
+/// jobs whose dependencies can no longer succeed.
+pub fn dispatch(state: &Arc<DaemonState>) {
+    let mut to_start = Vec::new();
+    {
+        let mut queue = state.queue.lock().unwrap();
+        // Skips can unblock (or skip) later jobs, so go round until nothing changes
+        loop {
+            let mut changed = false;
+            let mut i = 0;
+            while i < queue.pending.len() {
+                match decide(state, &queue, &queue.pending[i]) {
+                    Decision::Wait => i += 1,
+                    Decision::Start => {
+                        let job = queue.pending.remove(i);
+                        if let Some(key) = group_key(&job) {
+                            queue.busy_groups.insert(key);
+                        }
+                        queue.active.insert(job.id.clone(), job.project.clone());
+                        to_start.push(job);
+                    }
+                    Decision::Drop => {
+                        let job = queue.pending.remove(i);
+                        state.release_ports(&job.ports);
+                        changed = true;
+                    }
+                    Decision::Skip(reason) => {
+                        let job = queue.pending.remove(i);
+                        info!("Job {} skipped: {}", job.id, reason);
+                        {
+                            let db = state.db.lock().unwrap();
+                            let _ = db.update_finished(&job.id, Status::Stopped, None);
+                            let _ = db.record_event(
+                                &job.id,
+                                EventKind::Finished,
+                                Some(&format!("skipped: {reason}")),
+                            );
+                        }
+                        state.release_ports(&job.ports);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    for job in to_start {
+        spawner::start_job(state, job);
+    }
+}
+
+fn decide(state: &DaemonState, queue: &JobQueue, job: &Job) -> Decision {
+    let db = state.db.lock().unwrap();
+
+    match db.get(&job.id) {
+        Ok(Some(current)) if current.status == Status::Pending => {}
+        _ => return Decision::Drop,
+    }
+
+    let mut unfinished = false;
+    let mut failed = false;
+    for dep in &job.depends_on {
+        match db.get(dep) {
+            Ok(Some(d)) if d.status == Status::Completed => {}
+            Ok(Some(d)) if d.status.is_terminal() => {
+                if job.run_when == RunWhen::OnSuccess {
+                    return Decision::Skip(format!("dependency {} {}", d.short_id(), d.status));
+                }
+                failed = true;
+            }
+            Ok(Some(_)) => unfinished = true,
+            Ok(None) => return Decision::Skip(format!("dependency {dep} not found")),
+            // Transient DB error: try again on the next dispatch
+            Err(_) => unfinished = true,
+        }
+    }
+    if unfinished {
+        return Decision::Wait;
+    }
+    if job.run_when == RunWhen::OnFailure && !failed {
+        return Decision::Skip("dependencies succeeded".to_string());
+    }
+
+    if let Some(key) = group_key(job)
+        && queue.busy_groups.contains(&key)
+    {
+        return Decision::Wait;
+    }
+
+    let limits = state.config.read().unwrap().concurrency.clone();
+    if limits.max_jobs.is_some_and(|max| queue.active.len() >= max) {
+        return Decision::Wait;
+    }
+    if let Some(max) = limits.max_per_project
+        && queue.active.values().filter(|p| **p == job.project).count() >= max
+    {
+        return Decision::Wait;
+    }
+
+    Decision::Start
+}
+
+/// Pick up jobs left behind by a previous daemon: adopt running jobs whose
+/// process is still alive, queue pending ones again, and mark the rest
+/// interrupted.
+pub fn recover(state: &Arc<DaemonState>) {
+    let (running, mut pending) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.list(Some(Status::Running), None).unwrap_or_default(),
+            db.list(Some(Status::Pending), None).unwrap_or_default(),
+        )
+    };
+
+    for job in running {
+        if job
+            .pid
+            .is_some_and(|pid| process::is_same_process(pid, job.pid_fingerprint.as_deref()))
+        {
+            state.reserve_ports(&job.ports);
+            {
+                let mut queue = state.queue.lock().unwrap();
+                if let Some(key) = group_key(&job) {
+                    queue.busy_groups.insert(key);
+                }
+                queue.active.insert(job.id.clone(), job.project.clone());
+            }
+            spawner::adopt_job(state, job);
+        } else {
+            match state.db.lock().unwrap().finish_orphan(&job.id) {
+                Ok(status) => info!("Job {} ended with no daemon watching: {}", job.id, status),
+                Err(e) => warn!("Failed to record end of job {}: {}", job.id, e),
+            }
+            let _ = std::fs::remove_file(state.paths.exit_file(&job.id));
+        }
+    }
+
+    // Listed newest first; keep submission order
+    pending.reverse();
+    if !pending.is_empty() {
+        info!("Re-queueing {} pending jobs", pending.len());
+    }
+    {
+        let mut queue = state.queue.lock().unwrap();
+        for job in pending {
+            state.reserve_ports(&job.ports);
+            queue.push(job);
+        }
+    }
+    dispatch(state);
+}
+
+/// Free the job's concurrency slots after it finishes and start what was waiting.
+pub fn finished(state: &Arc<DaemonState>, job: &Job) {
+    {
+        let mut queue = state.queue.lock().unwrap();
+        if let Some(key) = group_key(job) {
+            queue.busy_groups.remove(&key);
+        }
+        queue.active.remove(&job.id);
+    }
+    dispatch(state);
+}
+
+/// Change a queued job's priority, moving it within the queue.
+pub fn reprioritize(state: &Arc<DaemonState>, job_id: &str, priority: i32) -> Response {
+    let found = {
+        let mut queue = state.queue.lock().unwrap();
+        let position = queue.pending.iter().position(|j| j.id == job_id);
+        position.map(|i| {
+            let mut job = queue.pending.remove(i);
+            job.priority = priority;
+            queue.push(job.clone());
+            job
+        })
+    };
+    let Some(job) = found else {
+        return Response::Error(format!(
+            "Job {job_id} is not queued; only pending jobs can be reprioritized"
+        ));
+    };
+
+    if let Err(e) = state.db.lock().unwrap().update_priority(&job.id, priority) {
+        return Response::Error(e.to_string());
+    }
+    info!("Job {} priority set to {}", job.id, priority);
+
+    // It may now be first in line for a free slot
+    dispatch(state);
+    match state.get_job(&job.id) {
+        Ok(Some(job)) => Response::Job(Box::new(job)),
+        Ok(None) => Response::Error(format!("Job not found: {job_id}")),
+        Err(e) => Response::Error(e.to_string()),
+    }
+}
+
+/// Cancel a queued job. Falls back to stopping it if it has already started.
+pub fn cancel(state: &Arc<DaemonState>, job_id: &str, force: bool) -> Response {
+    let removed = {
+        let mut queue = state.queue.lock().unwrap();
+        queue
+            .pending
+            .iter()
+            .position(|j| j.id == job_id)
+            .map(|i| queue.pending.remove(i))
+    };
+    let Some(job) = removed else {
+        return spawner::stop_job(state, job_id, force);
+    };
+
+    {
+        let db = state.db.lock().unwrap();
+        let _ = db.update_finished(&job.id, Status::Stopped, None);
+        let _ = db.record_event(&job.id, EventKind::Finished, Some("cancelled"));
+    }
+    state.release_ports(&job.ports);
+    info!("Job {} cancelled before starting", job.id);
+
+    // Its dependents can't run now
+    dispatch(state);
+    Response::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Config, Paths};
+
+    fn setup() -> (tempfile::TempDir, DaemonState) {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let state = DaemonState::new(
+            &Paths::with_root(tmp.path().to_path_buf()),
+            Config::default(),
+        )
+        .unwrap();
+        (tmp, state)
+    }
+
+    fn job(state: &DaemonState, id: &str, status: Status) -> Job {
+        let mut job = Job::new(
+            id.to_string(),
+            "true".to_string(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/project"),
+        );
+        job.status = status;
+        state.db.lock().unwrap().insert(&job).unwrap();
+        job
+    }
+
+    #[test]
+    fn test_decide_dependencies() {
+        let (_tmp, state) = setup();
+        let queue = JobQueue::default();
+        job(&state, "done", Status::Completed);
+        job(&state, "busy", Status::Running);
+        job(&state, "bad", Status::Failed);
+
+        let waiting = job(&state, "job1", Status::Pending).with_depends_on(vec!["busy".into()]);
+        assert!(matches!(decide(&state, &queue, &waiting), Decision::Wait));
+
+        let ready = job(&state, "job2", Status::Pending).with_depends_on(vec!["done".into()]);
+        assert!(matches!(decide(&state, &queue, &ready), Decision::Start));
+
+        let doomed =
+            job(&state, "job3", Status::Pending).with_depends_on(vec!["done".into(), "bad".into()]);
+        match decide(&state, &queue, &doomed) {
+            Decision::Skip(reason) => assert_eq!(reason, "dependency bad failed"),
+            _ => panic!("expected skip"),
+        }
+
+        let cancelled = job(&state, "job4", Status::Stopped);
+        assert!(matches!(decide(&state, &queue, &cancelled), Decision::Drop));
+    }
+
+    #[test]
+    fn test_queue_orders_by_priority() {
+        let (_tmp, state) = setup();
+        let mut queue = JobQueue::default();
+        queue.push(job(&state, "bench1", Status::Pending).with_priority(-10));
+        queue.push(job(&state, "plain", Status::Pending));
+        queue.push(job(&state, "bench2", Status::Pending).with_priority(-10));
+        queue.push(job(&state, "check", Status::Pending).with_priority(10));
+        queue.push(job(&state, "later", Status::Pending));
+
+        let order: Vec<&str> = queue.pending.iter().map(|j| j.id.as_str()).collect();
+        assert_eq!(order, ["check", "plain", "later", "bench1", "bench2"]);
+    }
+
+    #[test]
+    fn test_decide_run_when() {
+        let (_tmp, state) = setup();
+        let queue = JobQueue::default();
+        job(&state, "done", Status::Completed);
+        job(&state, "busy", Status::Running);
+        job(&state, "bad", Status::Failed);
+
+        let cleanup = job(&state, "job1", Status::Pending)
+            .with_depends_on(vec!["bad".into(), "busy".into()])
+            .with_run_when(RunWhen::Always);
+        assert!(matches!(decide(&state, &queue, &cleanup), Decision::Wait));
+        let cleanup = cleanup.with_depends_on(vec!["bad".into(), "done".into()]);
+        assert!(matches!(decide(&state, &queue, &cleanup), Decision::Start));
+
+        let notify = job(&state, "job2", Status::Pending)
+            .with_depends_on(vec!["done".into(), "bad".into()])
+            .with_run_when(RunWhen::OnFailure);
+        assert!(matches!(decide(&state, &queue, &notify), Decision::Start));
+        let notify = notify.with_depends_on(vec!["done".into()]);
+        match decide(&state, &queue, &notify) {
+            Decision::Skip(reason) => assert_eq!(reason, "dependencies succeeded"),
+            _ => panic!("expected skip"),
+        }
+    }
+
+    #[test]
+    fn test_decide_concurrency_group() {
+        let (_tmp, state) = setup();
+        let mut queue = JobQueue::default();
+        let grouped = job(&state, "a", Status::Pending).with_concurrency_group("cargo");
+        assert!(matches!(decide(&state, &queue, &grouped), Decision::Start));
+
+        queue
+            .busy_groups
+            .insert((PathBuf::from("/project"), "cargo".into()));
+        assert!(matches!(decide(&state, &queue, &grouped), Decision::Wait));
+
+        // Same group name in another project is independent
+        let mut other = job(&state, "job2", Status::Pending).with_concurrency_group("cargo");
+        other.project = PathBuf::from("/elsewhere");
+        assert!(matches!(decide(&state, &queue, &other), Decision::Start));
+    }
+
+    #[tokio::test]
+    async fn test_recover_adopts_live_jobs() {
+        let (_tmp, state) = setup();
+        let state = Arc::new(state);
+
+        // This test process stands in for a job that outlived its daemon
+        let mut live = Job::new(
+            "live".to_string(),
+            "sleep 60".to_string(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/project"),
+        )
+        .with_concurrency_group("cargo");
+        live.status = Status::Running;
+        live.pid = Some(std::process::id());
+        state.db.lock().unwrap().insert(&live).unwrap();
+        job(&state, "gone", Status::Running);
+        let mut queued = live.clone();
+        queued.id = "job1".to_string();
+        queued.status = Status::Pending;
+        queued.pid = None;
+        state.db.lock().unwrap().insert(&queued).unwrap();
+
+        recover(&state);
+
+        let queue = state.queue.lock().unwrap();
+        assert!(queue.active.contains_key("live"));
+        // Queued again, behind the adopted job's group
+        assert_eq!(queue.pending.len(), 1);
+        assert_eq!(queue.pending[0].id, "job1");
+        drop(queue);
+        let gone = state.get_job("gone").unwrap().unwrap();
+        assert_eq!(gone.status, Status::Interrupted);
+    }
+
+    #[test]
+    fn test_decide_concurrency_limits() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.concurrency.max_jobs = Some(2);
+        config.concurrency.max_per_project = Some(1);
+        let state = DaemonState::new(&Paths::with_root(tmp.path().to_path_buf()), config).unwrap();
+
+        let mut queue = JobQueue::default();
+        let next = job(&state, "job1", Status::Pending);
+        assert!(matches!(decide(&state, &queue, &next), Decision::Start));
+
+        // One job of this project is already running
+        queue
+            .active
+            .insert("other".into(), PathBuf::from("/project"));
+        assert!(matches!(decide(&state, &queue, &next), Decision::Wait));
+
+        // ...and the global limit applies across projects
+        let mut elsewhere = job(&state, "job2", Status::Pending);
+        elsewhere.project = PathBuf::from("/elsewhere");
+        assert!(matches!(
+            decide(&state, &queue, &elsewhere),
+            Decision::Start
+        ));
+        queue.active.insert("third".into(), PathBuf::from("/third"));
+        assert!(matches!(decide(&state, &queue, &elsewhere), Decision::Wait));
+    }
+}
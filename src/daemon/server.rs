@@ -1,29 +1,60 @@
-use crate::core::ipc::{Request, Response};
+use crate::core::ipc::{
+    CAPABILITIES, Envelope, PROTOCOL_VERSION, Request, Response, read_message, write_message,
+};
 use crate::core::{Paths, Status};
-use crate::daemon::spawner;
 use crate::daemon::state::DaemonState;
+use crate::daemon::{http, scheduler, spawner};
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::watch;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 
+/// What happens to jobs when the daemon exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Stop running jobs and mark them and queued jobs interrupted
+    Interrupt,
+    /// Leave them to be adopted by the next daemon
+    KeepJobs,
+}
+
 pub async fn run(paths: Paths, state: Arc<DaemonState>) -> Result<()> {
     let listener = UnixListener::bind(paths.socket())?;
     info!("Listening on {}", paths.socket().display());
 
     // Shutdown signal channel
-    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (shutdown_tx, shutdown_rx) = watch::channel(None);
+
+    // The daemon still runs for the CLI if the HTTP API can't start
+    if let Err(e) = http::spawn(&state, &shutdown_tx).await {
+        error!("HTTP API not started: {:#}", e);
+    }
 
     // Spawn signal handler
     let shutdown_tx_clone = shutdown_tx.clone();
     tokio::spawn(async move {
         wait_for_shutdown_signal().await;
-        let _ = shutdown_tx_clone.send(true);
+        let _ = shutdown_tx_clone.send(Some(ShutdownMode::Interrupt));
     });
 
-    loop {
+    // SIGHUP reloads the config
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut sighup = signal(SignalKind::hangup())?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            while sighup.recv().await.is_some() {
+                info!("Received SIGHUP");
+                let _ = reload_config(&state);
+            }
+        });
+    }
+
+    let mode = loop {
         tokio::select! {
             result = listener.accept() => {
                 match result {
@@ -41,15 +72,20 @@ pub async fn run(paths: Paths, state: Arc<DaemonState>) -> Result<()> {
                     }
                 }
             }
-            () = shutdown_signal(&shutdown_rx) => {
+            mode = shutdown_signal(&shutdown_rx) => {
                 info!("Shutdown signal received, stopping daemon");
-                break;
+                break mode;
             }
         }
-    }
+    };
 
-    // Mark running jobs as interrupted
-    state.interrupt_running_jobs();
+    match mode {
+        ShutdownMode::Interrupt => state.interrupt_running_jobs(),
+        ShutdownMode::KeepJobs => info!(
+            "Leaving {} running jobs for the next daemon",
+            state.running_count()
+        ),
+    }
 
     info!("Daemon shutdown complete");
     Ok(())
@@ -76,79 +112,163 @@ async fn wait_for_shutdown_signal() {
     }
 }
 
-async fn shutdown_signal(rx: &watch::Receiver<bool>) {
+/// Apply the config file to the running daemon, logging what changed.
+/// Raised limits may let queued jobs start straight away.
+fn reload_config(state: &Arc<DaemonState>) -> Result<Vec<String>> {
+    let changes = match state.reload_config() {
+        Ok(changes) => changes,
+        Err(e) => {
+            warn!("Config reload failed, keeping current settings: {:#}", e);
+            return Err(e);
+        }
+    };
+    if changes.is_empty() {
+        info!("Config reloaded, no changes");
+    }
+    for change in &changes {
+        info!("Config changed: {}", change);
+    }
+    scheduler::dispatch(state);
+    Ok(changes)
+}
+
+async fn shutdown_signal(rx: &watch::Receiver<Option<ShutdownMode>>) -> ShutdownMode {
     let mut rx = rx.clone();
-    while !*rx.borrow() {
+    loop {
+        if let Some(mode) = *rx.borrow() {
+            return mode;
+        }
         if rx.changed().await.is_err() {
-            break;
+            return ShutdownMode::Interrupt;
         }
     }
 }
 
+/// Serve one client connection. Requests run concurrently and are answered in
+/// the order they finish, each with its request ID. Waits still in flight when
+/// the client hangs up are dropped.
 async fn handle_connection(
-    mut stream: UnixStream,
+    stream: UnixStream,
     state: Arc<DaemonState>,
-    shutdown_tx: watch::Sender<bool>,
+    shutdown_tx: watch::Sender<Option<ShutdownMode>>,
 ) -> Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Envelope<Response>>();
+    let writes = tokio::spawn(async move {
+        while let Some(response) = rx.recv().await {
+            if let Err(e) = write_message(&mut writer, &response).await {
+                warn!("Write error: {}", e);
+                break;
+            }
+        }
+    });
+
+    // Waits by request ID, so `Cancel` can end them
+    let waits: Arc<Mutex<HashMap<u64, oneshot::Sender<()>>>> = Arc::default();
+    let mut tasks = JoinSet::new();
     loop {
-        let request = match read_message(&mut stream).await {
-            Ok(Some(req)) => req,
+        let Envelope {
+            id,
+            message: request,
+        } = match read_message::<Envelope<Request>>(&mut reader).await {
+            Ok(Some(envelope)) => envelope,
             Ok(None) => break,
             Err(e) => {
                 warn!("Read error: {}", e);
                 break;
             }
         };
+        while tasks.try_join_next().is_some() {}
 
-        let response = handle_request(request, &state, &shutdown_tx).await;
-
-        if let Err(e) = write_message(&mut stream, &response).await {
-            warn!("Write error: {}", e);
-            break;
+        if let Request::Cancel { id: target } = request {
+            let response = match waits.lock().unwrap().remove(&target) {
+                Some(cancel) => {
+                    let _ = cancel.send(());
+                    Response::Ok
+                }
+                None => Response::Error(format!("No wait in flight with request ID {target}")),
+            };
+            let _ = tx.send(Envelope::new(id, response));
+            continue;
         }
-    }
-
-    Ok(())
-}
 
-async fn read_message(stream: &mut UnixStream) -> Result<Option<Request>> {
-    let mut len_buf = [0u8; 4];
-    match stream.read_exact(&mut len_buf).await {
-        Ok(_) => {}
-        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-        Err(e) => return Err(e.into()),
-    }
+        let cancelled = match (id, &request) {
+            (Some(wait_id), Request::Wait { .. }) => {
+                let mut waits = waits.lock().unwrap();
+                if waits.contains_key(&wait_id) {
+                    let response =
+                        Response::Error(format!("Request ID {wait_id} is already in flight"));
+                    let _ = tx.send(Envelope::new(id, response));
+                    continue;
+                }
+                let (cancel, cancelled) = oneshot::channel();
+                waits.insert(wait_id, cancel);
+                Some(cancelled)
+            }
+            _ => None,
+        };
 
-    let len = u32::from_be_bytes(len_buf) as usize;
-    if len > 10 * 1024 * 1024 {
-        anyhow::bail!("message too large: {len} bytes");
+        let (state, shutdown_tx, tx, waits) = (
+            state.clone(),
+            shutdown_tx.clone(),
+            tx.clone(),
+            waits.clone(),
+        );
+        tasks.spawn(async move {
+            let response = match cancelled {
+                Some(cancelled) => {
+                    let response = tokio::select! {
+                        response = handle_request(request, &state, &shutdown_tx) => Some(response),
+                        result = cancelled => result.ok().map(|()| {
+                            Response::Error("Wait cancelled".to_string())
+                        }),
+                    };
+                    if let Some(id) = id {
+                        waits.lock().unwrap().remove(&id);
+                    }
+                    response
+                }
+                None => Some(handle_request(request, &state, &shutdown_tx).await),
+            };
+            // No response once the client has hung up
+            if let Some(response) = response {
+                let _ = tx.send(Envelope::new(id, response));
+            }
+        });
     }
 
-    let mut buf = vec![0u8; len];
-    stream.read_exact(&mut buf).await?;
-
-    let request: Request = serde_json::from_slice(&buf)?;
-    Ok(Some(request))
-}
-
-async fn write_message(stream: &mut UnixStream, response: &Response) -> Result<()> {
-    let data = serde_json::to_vec(response)?;
-    #[allow(clippy::cast_possible_truncation)] // messages are always < 4GB
-    let len = (data.len() as u32).to_be_bytes();
-
-    stream.write_all(&len).await?;
-    stream.write_all(&data).await?;
-    stream.flush().await?;
-
+    // Ends the waits; everything else finishes and is answered
+    waits.lock().unwrap().clear();
+    while tasks.join_next().await.is_some() {}
+    drop(tx);
+    let _ = writes.await;
     Ok(())
 }
 
-async fn handle_request(
+/// Answer one request. Shared by the IPC socket and the HTTP API.
+pub async fn handle_request(
     request: Request,
     state: &Arc<DaemonState>,
-    shutdown_tx: &watch::Sender<bool>,
+    shutdown_tx: &watch::Sender<Option<ShutdownMode>>,
 ) -> Response {
     match request {
+        Request::Hello {
+            protocol_version,
+            client_version,
+        } => {
+            if protocol_version != PROTOCOL_VERSION {
+                warn!(
+                    "Client {} speaks protocol {}, daemon speaks {}",
+                    client_version, protocol_version, PROTOCOL_VERSION
+                );
+            }
+            Response::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+                capabilities: CAPABILITIES.iter().map(ToString::to_string).collect(),
+            }
+        }
+
         Request::Ping => Response::Pong {
             pid: std::process::id(),
             uptime_secs: state.uptime_secs(),
@@ -158,43 +278,45 @@ async fn handle_request(
 
         Request::Shutdown => {
             info!("Shutdown requested via IPC");
-            let _ = shutdown_tx.send(true);
+            let _ = shutdown_tx.send(Some(ShutdownMode::Interrupt));
             Response::Ok
         }
 
-        Request::Run {
-            command,
-            name,
-            cwd,
-            project,
-            timeout_secs,
-            context,
-            idempotency_key,
-        } => {
-            spawner::spawn_job(
-                state,
-                command,
-                name,
-                cwd,
-                project,
-                timeout_secs,
-                context,
-                idempotency_key,
-            )
-            .await
+        Request::Handoff => {
+            info!("Handoff requested via IPC");
+            let _ = shutdown_tx.send(Some(ShutdownMode::KeepJobs));
+            Response::Ok
         }
 
-        Request::Stop { id, force } => match state.get_job(&id) {
+        Request::Reload => match reload_config(state) {
+            Ok(changes) => Response::Reloaded { changes },
+            Err(e) => Response::Error(format!("{e:#}")),
+        },
+
+        Request::Run(spec) => spawner::spawn_job(state, *spec),
+
+        Request::Batch(batch) => spawner::spawn_batch(state, batch),
+        Request::Flow(flow) => spawner::spawn_flow(state, flow),
+
+        Request::Stop { id, force } => spawner::stop(state, &id, force),
+
+        Request::Restart { id } => match state.get_job(&id) {
             Ok(Some(job)) => {
-                if job.status != Status::Running {
-                    return Response::Error(format!("Job {} is not running", job.short_id()));
+                if !job.is_service() {
+                    return Response::Error(format!("Job {} is not a service", job.short_id()));
                 }
-                spawner::stop_job(state, &job.id, force)
+                spawner::restart_job(state, &job.id)
             }
             Ok(None) => Response::Error(format!("Job not found: {id}")),
             Err(e) => Response::Error(e.to_string()),
         },
 
+        Request::Reprioritize { id, priority } => match state.get_job(&id) {
+            Ok(Some(job)) => scheduler::reprioritize(state, &job.id, priority),
+            Ok(None) => Response::Error(format!("Job not found: {id}")),
+            Err(e) => Response::Error(e.to_string()),
+        },
+
         Request::Status { id } => match state.get_job(&id) {
             Ok(Some(job)) => Response::Job(Box::new(job)),
             Ok(None) => Response::Error(format!("Job not found: {id}")),
@@ -210,6 +332,143 @@ async fn handle_request(
             }
         }
 
-        Request::Wait { id, timeout_secs } => spawner::wait_for_job(state, &id, timeout_secs).await,
+        Request::Wait {
+            id,
+            timeout_secs,
+            ready,
+        } => spawner::wait_for_job(state, &id, timeout_secs, ready).await,
+
+        Request::Cancel { .. } => {
+            Response::Error("Cancel only applies to waits on the same IPC connection".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{DaemonClient, Probe};
+    use crate::core::{Config, Job};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// A daemon connection handler on a socket in a temp dir
+    fn setup() -> (tempfile::TempDir, Arc<DaemonState>, PathBuf) {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = Paths::with_root(tmp.path().to_path_buf());
+        paths.ensure_dirs().unwrap();
+        let state = Arc::new(DaemonState::new(&paths, Config::default()).unwrap());
+        for id in ["first", "second"] {
+            let mut job = Job::new(
+                id.to_string(),
+                "sleep 60".to_string(),
+                PathBuf::from("/tmp"),
+                PathBuf::from("/tmp"),
+            );
+            job.status = Status::Running;
+            state.db.lock().unwrap().insert(&job).unwrap();
+        }
+
+        let listener = UnixListener::bind(paths.socket()).unwrap();
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            let (shutdown_tx, _rx) = watch::channel(None);
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = handle_connection(stream, server_state.clone(), shutdown_tx.clone()).await;
+            }
+        });
+        (tmp, state, paths.socket())
+    }
+
+    fn finish(state: &DaemonState, id: &str) {
+        let db = state.db.lock().unwrap();
+        db.update_finished(id, Status::Completed, Some(0)).unwrap();
+    }
+
+    async fn send(stream: &mut UnixStream, id: u64, request: Request) {
+        write_message(stream, &Envelope::new(Some(id), request))
+            .await
+            .unwrap();
+    }
+
+    async fn receive(stream: &mut UnixStream) -> Envelope<Response> {
+        tokio::time::timeout(Duration::from_secs(5), read_message(stream))
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn wait(id: &str) -> Request {
+        Request::Wait {
+            id: id.to_string(),
+            timeout_secs: None,
+            ready: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_answered_out_of_order() {
+        let (_tmp, state, socket) = setup();
+        let mut stream = UnixStream::connect(&socket).await.unwrap();
+
+        send(&mut stream, 1, wait("first")).await;
+        send(&mut stream, 2, wait("second")).await;
+        send(&mut stream, 3, Request::Status { id: "first".into() }).await;
+        assert_eq!(receive(&mut stream).await.id, Some(3));
+
+        finish(&state, "second");
+        let response = receive(&mut stream).await;
+        assert_eq!(response.id, Some(2));
+        assert!(matches!(response.message, Response::Job(job) if job.id == "second"));
+
+        send(&mut stream, 1, wait("second")).await;
+        let response = receive(&mut stream).await;
+        assert_eq!(response.id, Some(1));
+        assert!(
+            matches!(response.message, Response::Error(e) if e == "Request ID 1 is already in flight")
+        );
+
+        send(&mut stream, 4, Request::Cancel { id: 1 }).await;
+        let mut responses = [receive(&mut stream).await, receive(&mut stream).await];
+        responses.sort_by_key(|r| r.id);
+        assert!(matches!(&responses[0].message, Response::Error(e) if e == "Wait cancelled"));
+        assert!(matches!(responses[1].message, Response::Ok));
+
+        send(&mut stream, 5, Request::Cancel { id: 1 }).await;
+        let response = receive(&mut stream).await;
+        assert!(
+            matches!(response.message, Response::Error(e) if e == "No wait in flight with request ID 1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_waits_concurrently_on_one_connection() {
+        let (_tmp, state, socket) = setup();
+        let Probe::Ready(client) = DaemonClient::probe_at(&socket).await.unwrap() else {
+            panic!("daemon not ready");
+        };
+        assert!(client.supports("multiplex"));
+
+        let first = tokio::spawn({
+            let client = client.clone();
+            async move { client.wait("first", None).await }
+        });
+        // A wait dropped early is cancelled and leaves the connection usable
+        let abandoned =
+            tokio::time::timeout(Duration::from_millis(100), client.wait("second", None));
+        assert!(abandoned.await.is_err());
+        assert_eq!(
+            client.status("second").await.unwrap().status,
+            Status::Running
+        );
+
+        finish(&state, "first");
+        let job = tokio::time::timeout(Duration::from_secs(5), first)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.id, "first");
     }
 }
@@ -1,100 +1,357 @@
-use crate::core::ipc::Response;
-use crate::core::{Job, Status, kill_process_group};
+use crate::core::ipc::{BatchSpec, FlowSpec, Response, RunSpec};
+use crate::core::pipeline::artifacts_env_name;
+use crate::core::{
+    EventKind, Group, Job, Status, artifact, detect_project, kill_process_group, process,
+};
 use crate::daemon::state::{DaemonState, RunningJob};
+use crate::daemon::{health, ready, scheduler};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs::File;
-use tokio::process::Command;
-use tokio::sync::{oneshot, watch};
+use tokio::process::{Child, Command};
+use tokio::sync::{Notify, oneshot, watch};
 use tracing::{error, info, warn};
 
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::unused_async)]
-pub async fn spawn_job(
+pub fn spawn_job(state: &Arc<DaemonState>, spec: RunSpec) -> Response {
+    match create_job(state, spec, None) {
+        Ok(Created::New(job)) => {
+            scheduler::enqueue(state, job.clone());
+            // Still pending, will update to running shortly
+            Response::Job(Box::new(job))
+        }
+        Ok(Created::Existing(job)) => Response::Job(Box::new(job)),
+        Err(e) => Response::Error(e),
+    }
+}
+
+/// Create a group and start its jobs. Nothing is queued until every job has
+/// been created, so if one can't be, none of them runs and the group is deleted.
+pub fn spawn_batch(state: &Arc<DaemonState>, batch: BatchSpec) -> Response {
+    if batch.jobs.is_empty() {
+        return Response::Error("A batch needs at least one job".to_string());
+    }
+    let total = batch.jobs.len();
+    start_group(
+        state,
+        batch.name,
+        batch.jobs,
+        |i| format!("Job {} of {total} in the batch", i + 1),
+        |_, _, _, _| {},
+    )
+}
+
+/// Create a group for a pipeline and start its steps, each depending on the
+/// jobs of the steps it needs. Step jobs are named `<group>.<step>`, so the
+/// same pipeline can run more than once at a time.
+pub fn spawn_flow(state: &Arc<DaemonState>, flow: FlowSpec) -> Response {
+    if flow.steps.is_empty() {
+        return Response::Error("A flow needs at least one step".to_string());
+    }
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, step) in flow.steps.iter().enumerate() {
+        for earlier in step.needs.iter().chain(&step.artifacts_from) {
+            if !index.contains_key(earlier.as_str()) {
+                return Response::Error(format!(
+                    "Step '{}' needs '{earlier}', which isn't an earlier step",
+                    step.name
+                ));
+            }
+        }
+        if index.insert(&step.name, i).is_some() {
+            return Response::Error(format!("Step '{}' is defined twice", step.name));
+        }
+    }
+
+    let names: Vec<&str> = flow.steps.iter().map(|s| s.name.as_str()).collect();
+    let links: Vec<(Vec<usize>, Vec<usize>)> = flow
+        .steps
+        .iter()
+        .map(|step| {
+            let lookup = |names: &[String]| names.iter().map(|n| index[n.as_str()]).collect();
+            (lookup(&step.needs), lookup(&step.artifacts_from))
+        })
+        .collect();
+    let specs = flow.steps.iter().map(|step| step.job.clone()).collect();
+    start_group(
+        state,
+        Some(flow.name.clone()),
+        specs,
+        |i| format!("Step '{}'", names[i]),
+        |i, group_id, ids, spec| {
+            let (needs, artifacts_from) = &links[i];
+            spec.name = Some(format!("{group_id}.{}", names[i]));
+            spec.depends_on
+                .extend(needs.iter().map(|&dep| ids[dep].clone()));
+            for &dep in artifacts_from {
+                let dir = state.paths.job_artifacts_dir(&ids[dep]);
+                spec.env.insert(
+                    artifacts_env_name(names[dep]),
+                    dir.to_string_lossy().to_string(),
+                );
+            }
+        },
+    )
+}
+
+/// Create a group and a pending job per spec, then queue them all. `link`
+/// can fill in a spec from the group's ID and the IDs of the jobs created
+/// before it; `label` names a spec in errors.
+fn start_group(
     state: &Arc<DaemonState>,
-    command: String,
     name: Option<String>,
-    cwd: String,
-    project: String,
-    timeout_secs: Option<u64>,
-    context: Option<serde_json::Value>,
-    idempotency_key: Option<String>,
+    specs: Vec<RunSpec>,
+    label: impl Fn(usize) -> String,
+    link: impl Fn(usize, &str, &[String], &mut RunSpec),
 ) -> Response {
+    let group = {
+        let db = state.db.lock().unwrap();
+        let id = match db.generate_group_id() {
+            Ok(id) => id,
+            Err(e) => return Response::Error(e.to_string()),
+        };
+        let mut group = Group::new(id, spec_project(&specs[0]));
+        if let Some(name) = name {
+            group = group.with_name(name);
+        }
+        if let Err(e) = db.insert_group(&group) {
+            return Response::Error(format!("Failed to create group: {e}"));
+        }
+        group
+    };
+
+    let mut created = Vec::with_capacity(specs.len());
+    let mut ids = Vec::with_capacity(specs.len());
+    for (i, mut spec) in specs.into_iter().enumerate() {
+        link(i, &group.id, &ids, &mut spec);
+        match create_job(state, spec, Some(&group.id)) {
+            Ok(job) => {
+                let (Created::New(j) | Created::Existing(j)) = &job;
+                ids.push(j.id.clone());
+                created.push(job);
+            }
+            Err(e) => {
+                discard(state, &group, &created);
+                return Response::Error(format!("{}: {e}", label(i)));
+            }
+        }
+    }
+
+    let jobs = created
+        .into_iter()
+        .map(|job| match job {
+            Created::New(job) => {
+                scheduler::enqueue(state, job.clone());
+                job
+            }
+            // An idempotency key matched a job submitted before
+            Created::Existing(job) => job,
+        })
+        .collect();
+    Response::Group { group, jobs }
+}
+
+/// Stop a running job or cancel a queued one.
+pub fn stop(state: &Arc<DaemonState>, id: &str, force: bool) -> Response {
+    match state.get_job(id) {
+        Ok(Some(job)) => match job.status {
+            Status::Running => stop_job(state, &job.id, force),
+            Status::Pending => scheduler::cancel(state, &job.id, force),
+            _ => Response::Error(format!("Job {} is not running", job.short_id())),
+        },
+        Ok(None) => Response::Error(format!("Job not found: {id}")),
+        Err(e) => Response::Error(e.to_string()),
+    }
+}
+
+/// The project a spec's job belongs to
+fn spec_project(spec: &RunSpec) -> PathBuf {
+    if spec.project.is_empty() {
+        detect_project(&PathBuf::from(&spec.cwd))
+    } else {
+        PathBuf::from(&spec.project)
+    }
+}
+
+/// A job record made from a spec
+enum Created {
+    New(Job),
+    /// The job submitted earlier with the spec's idempotency key
+    Existing(Job),
+}
+
+/// Check a spec and record its job as pending, without queueing it.
+fn create_job(
+    state: &Arc<DaemonState>,
+    spec: RunSpec,
+    group_id: Option<&str>,
+) -> Result<Created, String> {
     // Check idempotency key and name uniqueness, generate ID
     let id = {
         let db = state.db.lock().unwrap();
 
         // Idempotency check
-        if let Some(ref key) = idempotency_key
+        if let Some(ref key) = spec.idempotency_key
             && let Ok(Some(existing)) = db.get_by_idempotency_key(key)
         {
-            return Response::Job(Box::new(existing));
+            return Ok(Created::Existing(existing));
         }
 
         // Name uniqueness check: can't have two running jobs with same name
-        if let Some(ref n) = name
+        if let Some(ref n) = spec.name
             && let Ok(Some(running)) = db.name_in_use(n)
         {
-            return Response::Error(format!(
+            return Err(format!(
                 "Name '{}' is in use by running job {}",
                 n,
                 running.short_id()
             ));
         }
 
+        for dep in &spec.depends_on {
+            match db.get(dep) {
+                Ok(Some(_)) => {}
+                Ok(None) => return Err(format!("Dependency not found: {dep}")),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
         match db.generate_id() {
             Ok(id) => id,
-            Err(e) => return Response::Error(e.to_string()),
+            Err(e) => return Err(e.to_string()),
         }
     };
 
+    let project = spec_project(&spec);
+    let cwd = PathBuf::from(&spec.cwd);
+
     // Create job record
-    let mut job = Job::new(
-        id,
-        command.clone(),
-        PathBuf::from(&cwd),
-        PathBuf::from(&project),
-    );
-
-    if let Some(n) = name {
+    let mut job = Job::new(id, spec.command, cwd, project)
+        .with_artifacts(spec.artifacts)
+        .with_ready_when(spec.ready_when)
+        .with_env(spec.env)
+        .with_depends_on(spec.depends_on)
+        .with_run_when(spec.run_when)
+        .with_priority(spec.priority);
+
+    if let Some(n) = spec.name {
         job = job.with_name(n);
     }
-    if let Some(t) = timeout_secs {
+    // Services run until stopped; other jobs fall back to the configured default
+    let timeout = match spec.restart {
+        Some(_) => spec.timeout_secs,
+        None => spec
+            .timeout_secs
+            .or_else(|| state.config.read().unwrap().default_timeout_secs()),
+    };
+    if let Some(t) = timeout {
         job = job.with_timeout(t);
     }
-    if let Some(c) = context {
+    if let Some(c) = spec.context {
         job = job.with_context(c);
     }
-    if let Some(k) = idempotency_key {
+    if let Some(k) = spec.idempotency_key {
         job = job.with_idempotency_key(k);
     }
+    if let Some(policy) = spec.restart {
+        job = job.with_restart(policy);
+    }
+    if let Some(check) = spec.health {
+        job = job.with_health(check);
+    }
+    if let Some(group) = spec.concurrency_group {
+        job = job.with_concurrency_group(group);
+    }
+    if let Some(group_id) = group_id {
+        job = job.with_group(group_id);
+    }
+    if !spec.ports.is_empty() {
+        match state.allocate_ports(&spec.ports) {
+            Ok(ports) => job = job.with_ports(ports),
+            Err(e) => return Err(format!("Failed to allocate ports: {e}")),
+        }
+    }
 
     // Insert into DB
     {
         let db = state.db.lock().unwrap();
         if let Err(e) = db.insert(&job) {
-            return Response::Error(format!("Failed to create job: {e}"));
+            state.release_ports(&job.ports);
+            return Err(format!("Failed to create job: {e}"));
         }
     }
 
-    let job_id = job.id.clone();
-    let state_clone = state.clone();
+    Ok(Created::New(job))
+}
 
-    // Spawn the process
+/// Delete a group that failed to start, along with the jobs created for it.
+fn discard(state: &Arc<DaemonState>, group: &Group, created: &[Created]) {
+    for job in created {
+        if let Created::New(job) = job {
+            state.release_ports(&job.ports);
+        }
+    }
+    let db = state.db.lock().unwrap();
+    if let Err(e) = db.delete_group(&group.id) {
+        warn!("Failed to delete group {}: {}", group.id, e);
+    }
+}
+
+/// Run a job the scheduler has released, then free its ports and group.
+pub fn start_job(state: &Arc<DaemonState>, job: Job) {
+    let state = state.clone();
     tokio::spawn(async move {
-        if let Err(e) = run_job(&state_clone, job_id.clone(), command, cwd, timeout_secs).await {
-            error!("Job {} failed to spawn: {}", job_id, e);
+        if let Err(e) = run_job(&state, job.clone()).await {
+            error!("Job {} failed to spawn: {}", job.id, e);
+            let db = state.db.lock().unwrap();
+            let _ = db.update_finished(&job.id, Status::Failed, None);
+            let _ = db.record_event(
+                &job.id,
+                EventKind::Finished,
+                Some(&format!("failed to spawn: {e}")),
+            );
         }
+        state.release_ports(&job.ports);
+        scheduler::finished(&state, &job);
     });
-
-    // Return the job (still pending, will update to running shortly)
-    Response::Job(Box::new(job))
 }
 
-/// Time to wait for graceful shutdown before SIGKILL
-const GRACEFUL_SHUTDOWN_SECS: u64 = 2;
+/// How often an adopted process is checked for exit
+const ADOPTED_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Resume supervising a job whose process outlived the previous daemon.
+///
+/// Output keeps going to the same log file and the original timeout still
+/// counts from when the job started. The process isn't our child, so its exit
+/// is noticed by polling and its exit code read from its exit file.
+pub fn adopt_job(state: &Arc<DaemonState>, job: Job) {
+    let pid = job.pid.unwrap_or(0);
+    let timeout_secs = job.timeout_secs.map(|secs| {
+        let elapsed = job
+            .started_at
+            .map_or(0, |t| (chrono::Utc::now() - t).num_seconds());
+        secs.saturating_sub(u64::try_from(elapsed).unwrap_or(0))
+    });
+    {
+        let db = state.db.lock().unwrap();
+        let _ = db.record_event(&job.id, EventKind::Adopted, Some(&format!("pid {pid}")));
+    }
+    info!("Adopted job {} with PID {}", job.id, pid);
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let adopted = Process::Adopted {
+            pid,
+            fingerprint: job.pid_fingerprint.clone(),
+            exit_file: state.paths.exit_file(&job.id),
+        };
+        supervise(&state, job.clone(), adopted, timeout_secs).await;
+        state.release_ports(&job.ports);
+        scheduler::finished(&state, &job);
+    });
+}
 
 /// Signal completion to any waiting callers
 fn signal_completion(job: Option<RunningJob>) {
@@ -105,43 +362,48 @@ fn signal_completion(job: Option<RunningJob>) {
     }
 }
 
-#[allow(clippy::too_many_lines)]
-async fn run_job(
-    state: &Arc<DaemonState>,
-    job_id: String,
-    command: String,
-    cwd: String,
-    timeout_secs: Option<u64>,
-) -> anyhow::Result<()> {
+async fn run_job(state: &Arc<DaemonState>, job: Job) -> anyhow::Result<()> {
+    let job_id = job.id.clone();
     let log_path = state.paths.log_file(&job_id);
 
-    // Create log file
-    let log_file = File::create(&log_path).await?;
-    let log_file_std = log_file.into_std().await;
-
-    // Spawn process in new session (detached)
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .current_dir(&cwd)
-        .stdout(Stdio::from(log_file_std.try_clone()?))
-        .stderr(Stdio::from(log_file_std))
-        .process_group(0) // Create new process group (setsid equivalent)
-        .spawn()?;
-
+    // Create log file, then write in append mode so rotation can truncate it
+    File::create(&log_path).await?;
+    let log_file = std::fs::OpenOptions::new().append(true).open(&log_path)?;
+    let child = spawn_process(state, &job, log_file)?;
     let pid = child.id().unwrap_or(0);
 
     // Update DB with running status
     {
         let db = state.db.lock().unwrap();
-        db.update_started(&job_id, pid)?;
+        db.update_started(&job_id, pid, process::fingerprint(pid).as_deref())?;
+        let _ = db.record_event(&job_id, EventKind::Started, Some(&format!("pid {pid}")));
     }
 
     info!("Job {} started with PID {}", job_id, pid);
 
-    // Create channels for completion notification and stop signal
+    let timeout_secs = job.timeout_secs;
+    supervise(state, job, Process::Child(child), timeout_secs).await;
+    Ok(())
+}
+
+/// Track a running process until the job ends, restarting services per their policy.
+#[allow(clippy::too_many_lines)]
+async fn supervise(
+    state: &Arc<DaemonState>,
+    job: Job,
+    mut process: Process,
+    mut timeout_secs: Option<u64>,
+) {
+    let job_id = job.id.clone();
+    let log_path = state.paths.log_file(&job_id);
+    let mut pid = process.id();
+    // Until the first respawn the exit status may be unknowable
+    let mut adopted = matches!(process, Process::Adopted { .. });
+
+    // Create channels for completion notification, stop signal and manual restarts
     let (completion_tx, _completion_rx) = oneshot::channel();
     let (stop_tx, mut stop_rx) = watch::channel(false);
+    let restart = Arc::new(Notify::new());
 
     // Track running job
     {
@@ -150,61 +412,102 @@ async fn run_job(
             job_id.clone(),
             RunningJob {
                 pid,
+                fingerprint: process.fingerprint(),
                 stop_tx,
                 completion_tx: Some(completion_tx),
+                restart: restart.clone(),
             },
         );
     }
 
-    // Event-based monitoring with tokio::select!
-    // Note: We use changed() instead of wait_for() because wait_for() returns
-    // a non-Send guard that causes issues with tokio::spawn
-    let result = if let Some(timeout) = timeout_secs {
-        tokio::select! {
-            biased;
-
-            // Stop signal from stop_job or interrupt_running_jobs
-            // (changed() returns when value is updated; we only send true)
-            _ = stop_rx.changed() => {
-                JobResult::Stopped
+    if job.ready_at.is_none() {
+        ready::spawn(state.clone(), &job, pid, 0);
+    }
+    health::spawn(state.clone(), &job, pid);
+
+    // Supervise: services go around again according to their restart policy
+    let mut attempts = 0;
+    let mut restarts = 0;
+    let result = loop {
+        let grace_secs = state.config.read().unwrap().grace_period_secs();
+        let result = monitor(
+            &mut process,
+            &job_id,
+            timeout_secs,
+            grace_secs,
+            &mut stop_rx,
+            &restart,
+        )
+        .await;
+        timeout_secs = job.timeout_secs;
+
+        match (&result, job.restart) {
+            (JobResult::Restart, _) => {
+                info!("Job {} restarting on request", job_id);
             }
+            (JobResult::Completed(status), Some(policy))
+                if policy.should_restart(status.is_some_and(|s| s.success()), attempts + 1) =>
+            {
+                attempts += 1;
+                let detail = describe_exit(*status);
+                warn!(
+                    "Service {} exited ({}), restarting in {}s",
+                    job_id, detail, policy.delay_secs
+                );
+                {
+                    let db = state.db.lock().unwrap();
+                    let _ = db.record_event(&job_id, EventKind::Crashed, Some(&detail));
+                }
 
-            // Timeout expired - escalate: SIGTERM → wait → SIGKILL
-            () = tokio::time::sleep(Duration::from_secs(timeout)) => {
-                warn!("Job {} timed out after {}s, sending SIGTERM", job_id, timeout);
-                kill_process_group(pid, false); // SIGTERM first
-
-                // Give process time to exit gracefully
-                tokio::select! {
+                let stopped = tokio::select! {
                     biased;
-                    _ = stop_rx.changed() => JobResult::Stopped,
-                    status = child.wait() => JobResult::Completed(status.ok()),
-                    () = tokio::time::sleep(Duration::from_secs(GRACEFUL_SHUTDOWN_SECS)) => {
-                        warn!("Job {} did not exit after SIGTERM, sending SIGKILL", job_id);
-                        kill_process_group(pid, true); // Force kill
-                        JobResult::Timeout
-                    }
+                    _ = stop_rx.changed() => true,
+                    () = restart.notified() => false,
+                    () = tokio::time::sleep(Duration::from_secs(policy.delay_secs)) => false,
+                };
+                if stopped {
+                    break JobResult::Stopped;
                 }
             }
-
-            // Process exited normally
-            status = child.wait() => {
-                JobResult::Completed(status.ok())
-            }
+            _ => break result,
         }
-    } else {
-        // No timeout - just wait for exit or stop signal
-        tokio::select! {
-            biased;
-
-            _ = stop_rx.changed() => {
-                JobResult::Stopped
-            }
 
-            status = child.wait() => {
-                JobResult::Completed(status.ok())
+        // Respawn, appending to the same log. Readiness starts over from here.
+        let log_offset = std::fs::metadata(&log_path).map_or(0, |m| m.len());
+        let spawned = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .and_then(|log| spawn_process(state, &job, log));
+        process = match spawned {
+            Ok(child) => Process::Child(child),
+            Err(e) => {
+                error!("Service {} failed to restart: {}", job_id, e);
+                break JobResult::Completed(None);
             }
+        };
+        pid = process.id();
+        adopted = false;
+        restarts += 1;
+
+        let fingerprint = process.fingerprint();
+        if let Some(running) = state.running_jobs.lock().unwrap().get_mut(&job_id) {
+            running.pid = pid;
+            running.fingerprint.clone_from(&fingerprint);
         }
+        {
+            let db = state.db.lock().unwrap();
+            let _ = db.update_restarted(&job_id, pid, fingerprint.as_deref());
+            let _ = db.record_event(
+                &job_id,
+                EventKind::Restarted,
+                Some(&format!("pid {pid} (restart {restarts})")),
+            );
+        }
+        info!("Service {} restarted with PID {}", job_id, pid);
+
+        ready::spawn(state.clone(), &job, pid, log_offset);
+        health::spawn(state.clone(), &job, pid);
     };
 
     // Remove from running jobs
@@ -213,9 +516,14 @@ async fn run_job(
         running.remove(&job_id)
     };
 
+    // Capture result and artifacts before waiters can observe a terminal status
+    ingest_result(state, &job_id);
+    let _ = std::fs::remove_file(state.paths.exit_file(&job_id));
+    collect_artifacts(state, &job).await;
+
     // Handle result
     match result {
-        JobResult::Stopped => {
+        JobResult::Stopped | JobResult::Restart => {
             // stop_job already updated DB, just signal completion
             signal_completion(removed);
         }
@@ -223,7 +531,12 @@ async fn run_job(
             // Update DB with timeout status
             {
                 let db = state.db.lock().unwrap();
-                let _ = db.update_finished(&job_id, Status::Stopped, None);
+                if db
+                    .update_finished(&job_id, Status::Stopped, None)
+                    .unwrap_or(false)
+                {
+                    let _ = db.record_event(&job_id, EventKind::Finished, Some("timed out"));
+                }
             }
             info!("Job {} timed out", job_id);
             signal_completion(removed);
@@ -232,35 +545,297 @@ async fn run_job(
             let (status, exit_code) = match exit_status {
                 Some(es) if es.success() => (Status::Completed, es.code()),
                 Some(es) => (Status::Failed, es.code()),
+                None if adopted => (Status::Interrupted, None),
                 None => (Status::Failed, None),
             };
 
+            let db = state.db.lock().unwrap();
+            // Keeps the status recorded by whoever saw the exit first
+            if db
+                .update_finished(&job_id, status, exit_code)
+                .unwrap_or(false)
             {
-                let db = state.db.lock().unwrap();
-                let _ = db.update_finished(&job_id, status, exit_code);
+                let mut detail = match exit_code {
+                    Some(code) => format!("{status} (exit {code})"),
+                    None if adopted => format!("{status} (exited after adoption, status unknown)"),
+                    None => status.to_string(),
+                };
+                if restarts > 0 {
+                    use std::fmt::Write;
+                    let _ = write!(detail, " after {restarts} restarts");
+                }
+                let _ = db.record_event(&job_id, EventKind::Finished, Some(&detail));
+                info!("Job {} finished with status {:?}", job_id, status);
             }
-            info!("Job {} finished with status {:?}", job_id, status);
+            drop(db);
             signal_completion(removed);
         }
     }
+}
 
-    Ok(())
+/// Start the job's command in its own process group, writing output to `log`.
+fn spawn_process(
+    state: &Arc<DaemonState>,
+    job: &Job,
+    log: std::fs::File,
+) -> std::io::Result<Child> {
+    let shell = state.config.read().unwrap().run.shell.clone();
+    let exit_file = state.paths.exit_file(&job.id);
+    // Left over from a service's previous process
+    let _ = std::fs::remove_file(&exit_file);
+    Command::new("/bin/sh")
+        .arg("-c")
+        .arg(process::EXIT_WRAPPER)
+        .arg(shell)
+        .arg(&job.command)
+        .arg(exit_file)
+        .current_dir(&job.cwd)
+        .env("JB_RESULT_FILE", state.paths.result_file(&job.id))
+        .envs(
+            job.ports
+                .iter()
+                .map(|(name, port)| (name, port.to_string())),
+        )
+        .envs(&job.env)
+        .stdout(Stdio::from(log.try_clone()?))
+        .stderr(Stdio::from(log))
+        .process_group(0) // Create new process group (setsid equivalent)
+        .spawn()
+}
+
+/// A job's process: one this daemon spawned, or one adopted from a previous daemon
+enum Process {
+    Child(Child),
+    /// Not our child: liveness is polled and the exit code comes from the exit file
+    Adopted {
+        pid: u32,
+        fingerprint: Option<String>,
+        exit_file: PathBuf,
+    },
+}
+
+impl Process {
+    fn id(&self) -> u32 {
+        match self {
+            Self::Child(child) => child.id().unwrap_or(0),
+            Self::Adopted { pid, .. } => *pid,
+        }
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        match self {
+            Self::Child(_) => process::fingerprint(self.id()),
+            Self::Adopted { fingerprint, .. } => fingerprint.clone(),
+        }
+    }
+
+    /// Signal the process group. An unreaped child can't have its PID reused,
+    /// but an adopted process is checked first.
+    fn kill(&self, force: bool) {
+        match self {
+            Self::Child(_) => kill_process_group(self.id(), force),
+            Self::Adopted {
+                pid, fingerprint, ..
+            } => {
+                process::kill_if_same(*pid, fingerprint.as_deref(), force);
+            }
+        }
+    }
+
+    /// Wait for the process to exit. The status is None if it can't be known.
+    async fn wait(&mut self) -> Option<std::process::ExitStatus> {
+        match self {
+            Self::Child(child) => child.wait().await.ok(),
+            Self::Adopted {
+                pid,
+                fingerprint,
+                exit_file,
+            } => {
+                use std::os::unix::process::ExitStatusExt;
+                while process::is_same_process(*pid, fingerprint.as_deref()) {
+                    tokio::time::sleep(ADOPTED_POLL_INTERVAL).await;
+                }
+                // Encoded as wait(2) would report a normal exit with this code
+                process::read_exit_file(exit_file)
+                    .map(|(code, _)| std::process::ExitStatus::from_raw((code & 0xff) << 8))
+            }
+        }
+    }
+}
+
+/// Wait for one run of the process to end: exit, stop, timeout or restart request.
+async fn monitor(
+    process: &mut Process,
+    job_id: &str,
+    timeout_secs: Option<u64>,
+    grace_secs: u64,
+    stop_rx: &mut watch::Receiver<bool>,
+    restart: &Notify,
+) -> JobResult {
+    let timeout = async {
+        match timeout_secs {
+            Some(secs) => tokio::time::sleep(Duration::from_secs(secs)).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    // Note: We use changed() instead of wait_for() because wait_for() returns
+    // a non-Send guard that causes issues with tokio::spawn
+    tokio::select! {
+        biased;
+
+        // Stop signal from stop_job or interrupt_running_jobs
+        // (changed() returns when value is updated; we only send true)
+        _ = stop_rx.changed() => JobResult::Stopped,
+
+        // Timeout expired - escalate: SIGTERM → wait → SIGKILL
+        () = timeout => {
+            warn!("Job {} timed out after {}s, sending SIGTERM", job_id, timeout_secs.unwrap_or(0));
+            match terminate(process, job_id, grace_secs, stop_rx).await {
+                // An adopted process's exit status is unknown; the timeout is what we know
+                Some(JobResult::Completed(None)) | None => JobResult::Timeout,
+                Some(result) => result,
+            }
+        }
+
+        // Manual restart of a service: same escalation, then the caller respawns
+        () = restart.notified() => {
+            match terminate(process, job_id, grace_secs, stop_rx).await {
+                Some(JobResult::Stopped) => JobResult::Stopped,
+                _ => JobResult::Restart,
+            }
+        }
+
+        // Process exited normally
+        status = process.wait() => JobResult::Completed(status),
+    }
+}
+
+/// SIGTERM the process group, then SIGKILL if it hasn't exited after the grace period.
+/// Returns how it ended, or None if it had to be killed.
+async fn terminate(
+    process: &mut Process,
+    job_id: &str,
+    grace_secs: u64,
+    stop_rx: &mut watch::Receiver<bool>,
+) -> Option<JobResult> {
+    process.kill(false); // SIGTERM first
+
+    // Give process time to exit gracefully
+    tokio::select! {
+        biased;
+        _ = stop_rx.changed() => Some(JobResult::Stopped),
+        status = process.wait() => Some(JobResult::Completed(status)),
+        () = tokio::time::sleep(Duration::from_secs(grace_secs)) => {
+            warn!("Job {} did not exit after SIGTERM, sending SIGKILL", job_id);
+            process.kill(true); // Force kill
+            let _ = process.wait().await;
+            None
+        }
+    }
+}
+
+/// Short description of how a process ended, e.g. "exit 1" or "signal 9"
+fn describe_exit(status: Option<std::process::ExitStatus>) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match status {
+        Some(s) => match (s.code(), s.signal()) {
+            (Some(code), _) => format!("exit {code}"),
+            (None, Some(sig)) => format!("signal {sig}"),
+            (None, None) => "exited".to_string(),
+        },
+        None => "wait failed".to_string(),
+    }
+}
+
+/// Copy the job's declared artifacts into the artifact store and record them.
+async fn collect_artifacts(state: &Arc<DaemonState>, job: &Job) {
+    if job.artifacts.is_empty() {
+        return;
+    }
+
+    let patterns = job.artifacts.clone();
+    let job_id = job.id.clone();
+    let cwd = job.cwd.clone();
+    let dest = state.paths.job_artifacts_dir(&job.id);
+    let collected =
+        tokio::task::spawn_blocking(move || artifact::collect(&job_id, &patterns, &cwd, &dest))
+            .await;
+
+    match collected {
+        Ok(Ok(collected)) => {
+            info!(
+                "Job {} collected {} artifacts",
+                job.id,
+                collected.artifacts.len()
+            );
+            for e in &collected.errors {
+                warn!("Job {} artifact skipped: {}", job.id, e);
+            }
+            let db = state.db.lock().unwrap();
+            if let Err(e) = db.insert_artifacts(&collected.artifacts) {
+                error!("Failed to record artifacts for job {}: {}", job.id, e);
+            }
+        }
+        Ok(Err(e)) => warn!("Failed to collect artifacts for job {}: {}", job.id, e),
+        Err(e) => error!(
+            "Artifact collection task for job {} panicked: {}",
+            job.id, e
+        ),
+    }
+}
+
+/// Largest result file the daemon will store on the job record
+const MAX_RESULT_BYTES: u64 = 1024 * 1024;
+
+/// Read the job's `JB_RESULT_FILE` (if written) into the DB, then remove it.
+fn ingest_result(state: &Arc<DaemonState>, job_id: &str) {
+    let path = state.paths.result_file(job_id);
+    let Ok(meta) = std::fs::metadata(&path) else {
+        return;
+    };
+
+    if meta.len() > MAX_RESULT_BYTES {
+        warn!(
+            "Job {} result file is {} bytes (max {}), ignoring",
+            job_id,
+            meta.len(),
+            MAX_RESULT_BYTES
+        );
+    } else {
+        match std::fs::read(&path).map(|data| serde_json::from_slice::<serde_json::Value>(&data)) {
+            Ok(Ok(value)) => {
+                let db = state.db.lock().unwrap();
+                if let Err(e) = db.update_result(job_id, &value) {
+                    error!("Failed to store result for job {}: {}", job_id, e);
+                }
+            }
+            Ok(Err(e)) => warn!("Job {} wrote invalid JSON to result file: {}", job_id, e),
+            Err(e) => warn!("Failed to read result file for job {}: {}", job_id, e),
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
 }
 
 enum JobResult {
     Completed(Option<std::process::ExitStatus>),
     Stopped,
     Timeout,
+    /// A service was asked to restart and its process has exited
+    Restart,
 }
 
 pub fn stop_job(state: &Arc<DaemonState>, job_id: &str, force: bool) -> Response {
     // Get job info and signal stop
     let job = {
         let running = state.running_jobs.lock().unwrap();
-        running.get(job_id).map(|j| (j.pid, j.stop_tx.clone()))
+        running
+            .get(job_id)
+            .map(|j| (j.pid, j.fingerprint.clone(), j.stop_tx.clone()))
     };
 
-    let Some((pid, stop_tx)) = job else {
+    let Some((pid, fingerprint, stop_tx)) = job else {
         return Response::Error(format!("Job {job_id} is not running"));
     };
 
@@ -268,12 +843,14 @@ pub fn stop_job(state: &Arc<DaemonState>, job_id: &str, force: bool) -> Response
     let _ = stop_tx.send(true);
 
     // Kill the entire process group (not just the shell wrapper)
-    kill_process_group(pid, force);
+    process::kill_if_same(pid, fingerprint.as_deref(), force);
 
-    // Update DB
+    // Capture the result before waiters can observe a terminal status
+    ingest_result(state, job_id);
     {
         let db = state.db.lock().unwrap();
         let _ = db.update_finished(job_id, Status::Stopped, None);
+        let _ = db.record_event(job_id, EventKind::Finished, Some("stopped"));
     }
 
     info!("Job {} stopped", job_id);
@@ -281,10 +858,23 @@ pub fn stop_job(state: &Arc<DaemonState>, job_id: &str, force: bool) -> Response
     Response::Ok
 }
 
+/// Ask a running service's supervisor to replace its process.
+pub fn restart_job(state: &Arc<DaemonState>, job_id: &str) -> Response {
+    let running = state.running_jobs.lock().unwrap();
+    let Some(job) = running.get(job_id) else {
+        return Response::Error(format!("Job {job_id} is not running"));
+    };
+    job.restart.notify_one();
+    info!("Job {} restart requested", job_id);
+    Response::Ok
+}
+
+/// Wait for a job to finish, or with `ready` for its readiness checks to pass.
 pub async fn wait_for_job(
     state: &Arc<DaemonState>,
     job_id: &str,
     timeout_secs: Option<u64>,
+    ready: bool,
 ) -> Response {
     let start = std::time::Instant::now();
     let timeout = timeout_secs.map(Duration::from_secs);
@@ -293,7 +883,24 @@ pub async fn wait_for_job(
         // Check if job exists and its status
         match state.get_job(job_id) {
             Ok(Some(job)) => {
-                if job.status.is_terminal() {
+                if ready {
+                    if job.ready_when.is_empty() {
+                        return Response::Error(format!(
+                            "Job {} has no readiness checks",
+                            job.short_id()
+                        ));
+                    }
+                    if job.ready_at.is_some() {
+                        return Response::Job(Box::new(job));
+                    }
+                    if job.status.is_terminal() {
+                        return Response::Error(format!(
+                            "Job {} exited before becoming ready ({})",
+                            job.short_id(),
+                            job.status
+                        ));
+                    }
+                } else if job.status.is_terminal() {
                     return Response::Job(Box::new(job));
                 }
             }
@@ -1,41 +1,71 @@
-use crate::core::{Database, Job, Paths, Status, kill_process_group};
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::core::{Config, Database, EventKind, Job, Paths, Status, process};
+use crate::daemon::scheduler::JobQueue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
-use tokio::sync::{oneshot, watch};
+use tokio::sync::{Notify, oneshot, watch};
 use tracing::warn;
 
+/// Ephemeral ports tried per name before giving up
+const PORT_ATTEMPTS: usize = 100;
+
 pub struct RunningJob {
     pub pid: u32,
+    /// See `core::process`; checked before signalling
+    pub fingerprint: Option<String>,
     pub stop_tx: watch::Sender<bool>,
     pub completion_tx: Option<oneshot::Sender<()>>,
+    /// Wakes a service's supervisor to replace its process
+    pub restart: Arc<Notify>,
 }
 
 pub struct DaemonState {
     pub db: Mutex<Database>,
     pub paths: Paths,
+    /// Replaced wholesale by `jb daemon reload` / SIGHUP
+    pub config: RwLock<Config>,
     pub started_at: Instant,
     pub running_jobs: Mutex<HashMap<String, RunningJob>>,
+    /// Ports handed to live jobs, so two jobs never get the same one
+    pub reserved_ports: Mutex<HashSet<u16>>,
+    /// Jobs waiting on dependencies or a concurrency group
+    pub queue: Mutex<JobQueue>,
 }
 
 impl DaemonState {
-    pub fn new(paths: &Paths) -> anyhow::Result<Self> {
+    pub fn new(paths: &Paths, config: Config) -> anyhow::Result<Self> {
         let db = Database::open(paths)?;
 
-        // Recover orphaned jobs from previous daemon crash
-        Self::recover_orphaned_jobs(&db);
-
         Ok(Self {
             db: Mutex::new(db),
             paths: paths.clone(),
+            config: RwLock::new(config),
             started_at: Instant::now(),
             running_jobs: Mutex::new(HashMap::new()),
+            reserved_ports: Mutex::new(HashSet::new()),
+            queue: Mutex::new(JobQueue::default()),
         })
     }
 
-    /// Handle jobs stuck in "running" or "pending" state from previous daemon.
-    fn recover_orphaned_jobs(db: &Database) {
-        db.recover_orphans();
+    /// Re-read the config file and swap it in, returning what changed.
+    /// On error the current config stays in effect.
+    pub fn reload_config(&self) -> anyhow::Result<Vec<String>> {
+        let new = Config::load(&self.paths)?;
+        let mut config = self.config.write().unwrap();
+        // The HTTP listeners are only set up at startup
+        let changes = config
+            .diff(&new)
+            .into_iter()
+            .map(|c| {
+                if c.starts_with("http.") {
+                    format!("{c} (applies after `jb daemon restart`)")
+                } else {
+                    c
+                }
+            })
+            .collect();
+        *config = new;
+        Ok(changes)
     }
 
     pub fn uptime_secs(&self) -> u64 {
@@ -62,8 +92,51 @@ impl DaemonState {
         self.db.lock().unwrap().list(status, limit)
     }
 
-    /// Interrupt all running jobs on graceful shutdown.
+    /// Pick a free localhost port for each name and reserve it until released.
+    pub fn allocate_ports(&self, names: &[String]) -> std::io::Result<BTreeMap<String, u16>> {
+        let mut reserved = self.reserved_ports.lock().unwrap();
+        let mut ports = BTreeMap::new();
+
+        for name in names {
+            // The OS hands out an unused ephemeral port; retry if we already gave it away
+            let mut found = None;
+            for _ in 0..PORT_ATTEMPTS {
+                let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+                let port = listener.local_addr()?.port();
+                if !reserved.contains(&port) && !ports.values().any(|&p| p == port) {
+                    found = Some(port);
+                    break;
+                }
+            }
+            let Some(port) = found else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!("no free port for {name} after {PORT_ATTEMPTS} tries"),
+                ));
+            };
+            ports.insert(name.clone(), port);
+        }
+
+        reserved.extend(ports.values());
+        Ok(ports)
+    }
+
+    /// Mark ports allocated by a previous daemon as taken.
+    pub fn reserve_ports(&self, ports: &BTreeMap<String, u16>) {
+        self.reserved_ports.lock().unwrap().extend(ports.values());
+    }
+
+    pub fn release_ports(&self, ports: &BTreeMap<String, u16>) {
+        let mut reserved = self.reserved_ports.lock().unwrap();
+        for port in ports.values() {
+            reserved.remove(port);
+        }
+    }
+
+    /// Interrupt all running and queued jobs on graceful shutdown.
+    /// Not called on `jb daemon restart --keep-jobs`, which leaves them for the next daemon.
     pub fn interrupt_running_jobs(&self) {
+        let queued = std::mem::take(&mut self.queue.lock().unwrap().pending);
         let mut running = self.running_jobs.lock().unwrap();
         let db = self.db.lock().unwrap();
 
@@ -72,9 +145,44 @@ impl DaemonState {
             // Signal the job to stop (will break out of select!)
             let _ = job.stop_tx.send(true);
             // Kill the entire process group (not just the shell wrapper)
-            kill_process_group(job.pid, false);
+            process::kill_if_same(job.pid, job.fingerprint.as_deref(), false);
             // Mark as interrupted in database
             let _ = db.update_finished(&id, Status::Interrupted, None);
+            let _ = db.record_event(&id, EventKind::Finished, Some("interrupted"));
+        }
+
+        for job in queued {
+            let _ = db.update_finished(&job.id, Status::Interrupted, None);
+            let _ = db.record_event(&job.id, EventKind::Finished, Some("interrupted"));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_and_release_ports() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let state = DaemonState::new(
+            &Paths::with_root(tmp.path().to_path_buf()),
+            Config::default(),
+        )
+        .unwrap();
+
+        let names = vec!["PORT".to_string(), "DB_PORT".to_string()];
+        let first = state.allocate_ports(&names).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_ne!(first["PORT"], first["DB_PORT"]);
+
+        // Reserved ports are never handed out twice
+        let second = state.allocate_ports(&names).unwrap();
+        assert!(second.values().all(|p| !first.values().any(|q| q == p)));
+
+        state.release_ports(&first);
+        let reserved = state.reserved_ports.lock().unwrap();
+        assert_eq!(reserved.len(), 2);
+        assert!(second.values().all(|p| reserved.contains(p)));
+    }
+}
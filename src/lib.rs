@@ -0,0 +1,45 @@
+//! Client library for [jb](https://github.com/nijaru/jb), the background job
+//! manager.
+//!
+//! [`DaemonClient`] talks to the jb daemon over its Unix socket, starting the
+//! daemon if needed. It is what the `jb` CLI itself uses.
+//!
+//! ```no_run
+//! use jb::{DaemonClient, RunSpec};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let client = DaemonClient::connect_or_start().await?;
+//!
+//! let job = client
+//!     .run(RunSpec {
+//!         command: "cargo test".into(),
+//!         cwd: std::env::current_dir()?.display().to_string(),
+//!         ..RunSpec::default()
+//!     })
+//!     .await?;
+//!
+//! let mut logs = client.logs(&job.id).await?;
+//! while let Some(output) = logs.next().await? {
+//!     print!("{}", String::from_utf8_lossy(&output));
+//! }
+//!
+//! let job = client.wait(&job.id, None).await?;
+//! println!("{} exited with {:?}", job.id, job.exit_code);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The [`client`] and [`core`] types are stable; `commands` and `daemon` are
+//! the CLI and daemon internals and may change in any release.
+
+pub mod client;
+pub mod core;
+
+#[doc(hidden)]
+pub mod commands;
+#[doc(hidden)]
+pub mod daemon;
+
+pub use client::{DaemonClient, DaemonError, DaemonInfo, EventStream, LogStream};
+pub use core::ipc::{BatchSpec, FlowSpec, RunSpec, StepSpec};
+pub use core::{Event, EventKind, Group, Job, RunWhen, Status};
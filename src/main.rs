@@ -1,12 +1,8 @@
-mod client;
-mod commands;
-mod core;
-mod daemon;
-
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::Shell;
-use core::UserError;
+use jb::core::UserError;
+use jb::{DaemonError, commands, core};
 
 #[derive(Parser)]
 #[command(name = "jb")]
@@ -21,11 +17,98 @@ struct Cli {
     json: bool,
 }
 
+/// Readiness checks shared by `run` and `service start`
+#[derive(Args)]
+struct ReadyArgs {
+    /// Mark ready when output matches this regex
+    #[arg(long, value_name = "REGEX")]
+    ready_when_log: Option<String>,
+
+    /// Mark ready when localhost accepts connections on this port
+    #[arg(long, value_name = "PORT")]
+    ready_when_port: Option<u16>,
+
+    /// Mark ready when this http:// URL returns 2xx/3xx
+    #[arg(long, value_name = "URL")]
+    ready_when_http: Option<String>,
+
+    /// Mark ready when this file exists (relative to cwd)
+    #[arg(long, value_name = "PATH")]
+    ready_when_file: Option<std::path::PathBuf>,
+}
+
+impl ReadyArgs {
+    fn into_checks(self) -> Vec<core::ReadyCheck> {
+        [
+            self.ready_when_log.map(core::ReadyCheck::Log),
+            self.ready_when_port.map(core::ReadyCheck::Port),
+            self.ready_when_http.map(core::ReadyCheck::Http),
+            self.ready_when_file.map(core::ReadyCheck::File),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Periodic health check for `service start`
+#[derive(Args)]
+struct HealthArgs {
+    /// Health check: command that exits 0 when healthy (run in the service's cwd)
+    #[arg(long, value_name = "CMD", conflicts_with_all = ["health_port", "health_http"])]
+    health_cmd: Option<String>,
+
+    /// Health check: localhost port accepting connections
+    #[arg(long, value_name = "PORT", conflicts_with = "health_http")]
+    health_port: Option<u16>,
+
+    /// Health check: http:// URL returning 2xx/3xx
+    #[arg(long, value_name = "URL")]
+    health_http: Option<String>,
+
+    /// Time between health checks
+    #[arg(long, default_value = "10s")]
+    health_interval: String,
+
+    /// Time allowed for each check
+    #[arg(long, default_value = "5s")]
+    health_timeout: String,
+
+    /// Consecutive failures before the service is unhealthy
+    #[arg(long, default_value_t = 3)]
+    health_retries: u32,
+
+    /// When unhealthy: restart, stop, or mark (record only)
+    #[arg(long, default_value = "restart")]
+    health_action: String,
+}
+
+impl HealthArgs {
+    fn into_check(self) -> Result<Option<core::HealthCheck>> {
+        let probe = match (self.health_cmd, self.health_port, self.health_http) {
+            (Some(cmd), _, _) => core::HealthProbe::Command(cmd),
+            (_, Some(port), _) => core::HealthProbe::Port(port),
+            (_, _, Some(url)) => core::HealthProbe::Http(url),
+            _ => return Ok(None),
+        };
+        Ok(Some(core::HealthCheck {
+            probe,
+            interval_secs: core::parse_duration(&self.health_interval)?,
+            timeout_secs: core::parse_duration(&self.health_timeout)?,
+            threshold: self.health_retries,
+            action: self
+                .health_action
+                .parse()
+                .map_err(|e: anyhow::Error| UserError::new(e.to_string()))?,
+        }))
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Start a background job
     Run {
-        /// Command to execute
+        /// Command to execute, or @TASK to run a task from .jb.toml
         command: String,
 
         /// Human-readable job name
@@ -44,6 +127,21 @@ enum Commands {
         #[arg(short = 'k', long)]
         key: Option<String>,
 
+        /// Queue priority: high, normal, low or a number (higher starts first)
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<String>,
+
+        /// Collect matching files on exit (repeatable, glob relative to cwd)
+        #[arg(long = "artifact", value_name = "PATTERN")]
+        artifacts: Vec<String>,
+
+        /// Allocate a free local port and export it as this env var (repeatable)
+        #[arg(long = "port", value_name = "NAME")]
+        ports: Vec<String>,
+
+        #[command(flatten)]
+        ready: ReadyArgs,
+
         /// Wait for job to complete (silent)
         #[arg(short, long)]
         wait: bool,
@@ -64,7 +162,7 @@ enum Commands {
         #[arg(long)]
         failed: bool,
 
-        /// Number of jobs to show (default: 10)
+        /// Number of jobs to show (default: list.limit, 10)
         #[arg(short = 'n', long)]
         limit: Option<usize>,
 
@@ -95,6 +193,68 @@ enum Commands {
         /// Pipe output through pager (less -R)
         #[arg(long)]
         pager: bool,
+
+        /// Only output written after this cursor; prints the next cursor
+        #[arg(long, value_name = "CURSOR", conflicts_with_all = ["tail", "follow", "new"])]
+        since_cursor: Option<String>,
+
+        /// Only output this caller hasn't seen yet (cursor kept in the DB)
+        #[arg(long, conflicts_with_all = ["tail", "follow"])]
+        new: bool,
+
+        /// Caller identity for --new (default: $JB_CALLER or "default")
+        #[arg(long, requires = "new")]
+        caller: Option<String>,
+    },
+
+    /// Summarize a job: exit info, error excerpts, diagnostics and last lines
+    Summary {
+        /// Job ID or name
+        id: String,
+
+        /// Number of trailing lines to include
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+
+        /// Lines of context around each error
+        #[arg(short = 'C', long, default_value_t = 2)]
+        context: usize,
+    },
+
+    /// Search job logs with a regex
+    Grep {
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Case-insensitive match
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Only jobs from this project (default: current)
+        #[arg(short, long, num_args = 0..=1, default_missing_value = ".")]
+        project: Option<std::path::PathBuf>,
+
+        /// Only jobs whose name matches (glob, e.g. 'test-*')
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Only jobs with this status
+        #[arg(short, long)]
+        status: Option<String>,
+
+        /// Only jobs created within this duration (e.g., 1h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+    },
+
+    /// Change the priority of a queued job
+    Reprioritize {
+        /// Job ID or name
+        id: String,
+
+        /// high, normal, low or a number (higher starts first)
+        #[arg(allow_negative_numbers = true)]
+        priority: String,
     },
 
     /// Stop a running job
@@ -107,14 +267,74 @@ enum Commands {
         force: bool,
     },
 
-    /// Wait for a job to complete
+    /// Wait for jobs to complete
     Wait {
-        /// Job ID or name
-        id: String,
+        /// Job IDs or names
+        #[arg(required_unless_present_any = ["name", "project"])]
+        ids: Vec<String>,
+
+        /// Wait until every job is done (the default)
+        #[arg(long, conflicts_with = "any")]
+        all: bool,
+
+        /// Return as soon as one job is done
+        #[arg(long)]
+        any: bool,
+
+        /// Wait on the latest job of each name matching this glob (e.g. 'test-*')
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Wait on running and queued jobs in this project (default: current)
+        #[arg(short, long, num_args = 0..=1, default_missing_value = ".")]
+        project: Option<std::path::PathBuf>,
 
         /// Timeout duration (e.g., 5m, 1h)
         #[arg(short, long)]
         timeout: Option<String>,
+
+        /// Return once the job's readiness checks pass instead of at exit
+        #[arg(long)]
+        ready: bool,
+    },
+
+    /// Show job lifecycle events (started, ready, finished)
+    Events {
+        /// Job ID or name (omit for all jobs)
+        id: Option<String>,
+
+        /// Number of recent events to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        limit: usize,
+
+        /// Keep printing new events as they happen
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Show ports allocated to a job (NAME=PORT, or just the port for one name)
+    Port {
+        /// Job ID or name
+        id: String,
+
+        /// Port variable name (omit to list all)
+        name: Option<String>,
+    },
+
+    /// Show the JSON result a job wrote to $JB_RESULT_FILE
+    Result {
+        /// Job ID or name
+        id: String,
+    },
+
+    /// List or extract files collected from a job
+    Artifacts {
+        /// Job ID or name
+        id: String,
+
+        /// Copy artifacts into DIR (default: current directory)
+        #[arg(short = 'x', long, value_name = "DIR", num_args = 0..=1, default_missing_value = ".")]
+        extract: Option<std::path::PathBuf>,
     },
 
     /// Re-run a job
@@ -123,11 +343,97 @@ enum Commands {
         id: String,
     },
 
+    /// Supervise long-running processes that restart when they exit
+    #[command(subcommand)]
+    Service(ServiceCommands),
+
+    /// Start many commands at once as a group
+    Batch {
+        /// Commands to run
+        commands: Vec<String>,
+
+        /// Name for the group
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Read commands from a file, one per line (`-` for stdin)
+        #[arg(short, long)]
+        file: Option<std::path::PathBuf>,
+
+        /// Read a JSON array from stdin: commands, or {"command", "name", "cwd", "timeout", "env"}
+        #[arg(long)]
+        stdin: bool,
+
+        /// Timeout for each job (e.g., 30s, 5m, 1h)
+        #[arg(short, long)]
+        timeout: Option<String>,
+
+        /// Queue priority for each job: high, normal, low or a number (higher starts first)
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<String>,
+
+        /// Wait for every job to finish
+        #[arg(short, long)]
+        wait: bool,
+    },
+
+    /// Run a command template once per combination of variable values
+    Matrix {
+        /// Command with {NAME} placeholders, e.g. "cargo test --features {feat}"
+        command: String,
+
+        /// Values for a placeholder, e.g. feat=a,b,c (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUES", required = true)]
+        vars: Vec<String>,
+
+        /// Name for the group, and prefix for job names (default: jobs are unnamed)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Timeout for each job (e.g., 30s, 5m, 1h)
+        #[arg(short, long)]
+        timeout: Option<String>,
+
+        /// Queue priority for each job: high, normal, low or a number (higher starts first)
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<String>,
+
+        /// Print the group ID and return instead of waiting for the results
+        #[arg(long)]
+        no_wait: bool,
+    },
+
+    /// Inspect and control groups started by `jb batch`
+    #[command(subcommand)]
+    Group(GroupCommands),
+
+    /// Run pipelines of dependent steps from a TOML file
+    #[command(subcommand)]
+    Flow(FlowCommands),
+
+    /// Run a task from the project's .jb.toml (`jb task list` to list them)
+    Task {
+        /// Task name, or `list`
+        name: Option<String>,
+
+        /// Wait for the task to complete (silent)
+        #[arg(short, long)]
+        wait: bool,
+
+        /// Follow the task's output until it completes
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Show or change settings in ~/.jb/config.toml
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
     /// Remove old jobs (default: older than 7d)
     Clean {
-        /// Age threshold (e.g., 1d, 12h)
-        #[arg(short = 't', long, default_value = "7d")]
-        older_than: String,
+        /// Age threshold (e.g., 1d, 12h; default: retention.max_age, 7d)
+        #[arg(short = 't', long)]
+        older_than: Option<String>,
 
         /// Filter: completed, failed, stopped, interrupted
         #[arg(long)]
@@ -138,9 +444,14 @@ enum Commands {
         all: bool,
     },
 
-    /// Run the daemon (internal use)
-    #[command(hide = true)]
-    Daemon,
+    /// Manage the background daemon
+    Daemon {
+        #[command(subcommand)]
+        command: Option<DaemonCommands>,
+    },
+
+    /// Serve the Model Context Protocol on stdin/stdout, for agent frameworks
+    Mcp,
 
     /// Generate shell completions
     Completions {
@@ -153,6 +464,155 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration
+    Show,
+    /// Print one setting, e.g. `list.limit`
+    Get { key: String },
+    /// Change one setting in the config file
+    Set { key: String, value: String },
+    /// List the available settings
+    Keys,
+}
+
+#[derive(Subcommand)]
+enum GroupCommands {
+    /// Progress of a group's jobs
+    Status {
+        /// Group ID or name
+        group: String,
+    },
+    /// Stop a group's running and queued jobs
+    Stop {
+        /// Group ID or name
+        group: String,
+
+        /// Force kill (SIGKILL instead of SIGTERM)
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Wait for a group's jobs to finish
+    Wait {
+        /// Group ID or name
+        group: String,
+
+        /// Return as soon as one job is done
+        #[arg(long)]
+        any: bool,
+
+        /// Timeout duration (e.g., 5m, 1h)
+        #[arg(short, long)]
+        timeout: Option<String>,
+    },
+    /// Output of a group's jobs, each line prefixed by its job
+    Logs {
+        /// Group ID or name
+        group: String,
+
+        /// Keep printing output until every job has exited
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FlowCommands {
+    /// Start a pipeline's steps; the daemon runs each once its dependencies allow
+    Run {
+        /// Pipeline file
+        file: std::path::PathBuf,
+
+        /// Name for the flow (default: `name` in the file, or the file name)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Queue priority for each step: high, normal, low or a number (higher starts first)
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<String>,
+
+        /// Wait for every step and show the result
+        #[arg(short, long)]
+        wait: bool,
+    },
+    /// A flow's steps, level by level, with what each depends on
+    Status {
+        /// Flow (group) ID or name
+        flow: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonCommands {
+    /// Re-read config.toml and apply it without restarting
+    Reload,
+    /// Stop the daemon and start a new one (e.g. after upgrading jb)
+    Restart {
+        /// Leave running jobs alone; the new daemon adopts them
+        #[arg(long)]
+        keep_jobs: bool,
+    },
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once
+enum ServiceCommands {
+    /// Start a supervised service
+    Start {
+        /// Service name
+        name: String,
+
+        /// Command to execute
+        command: String,
+
+        /// When to restart: always, on-failure, never
+        #[arg(long, default_value = "on-failure")]
+        restart: String,
+
+        /// Give up after this many automatic restarts (default: unlimited)
+        #[arg(long)]
+        max_restarts: Option<u32>,
+
+        /// Delay before each automatic restart (e.g., 2s, 1m)
+        #[arg(long, default_value = "1s")]
+        restart_delay: String,
+
+        /// Allocate a free local port and export it as this env var (repeatable)
+        #[arg(long = "port", value_name = "NAME")]
+        ports: Vec<String>,
+
+        #[command(flatten)]
+        ready: ReadyArgs,
+
+        #[command(flatten)]
+        health: HealthArgs,
+    },
+
+    /// Stop a service (no further restarts)
+    Stop {
+        /// Service name or job ID
+        name: String,
+
+        /// Force kill (SIGKILL instead of SIGTERM)
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Restart a running service now
+    Restart {
+        /// Service name or job ID
+        name: String,
+    },
+
+    /// List running services
+    #[command(visible_alias = "ls")]
+    List {
+        /// Include services that have exited
+        #[arg(short, long)]
+        all: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt()
@@ -160,6 +620,8 @@ async fn main() {
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive(tracing::Level::WARN.into()),
         )
+        // stdout belongs to the command (and is the protocol channel for `jb mcp`)
+        .with_writer(std::io::stderr)
         .init();
 
     if let Err(e) = run().await {
@@ -168,6 +630,11 @@ async fn main() {
             eprintln!("Error: {user_err}");
             std::process::exit(1);
         }
+        // The daemon refusing a request is just as clean, with any context added
+        if e.downcast_ref::<DaemonError>().is_some() {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        }
         // For other errors, use anyhow's default formatting
         eprintln!("Error: {e:?}");
         std::process::exit(1);
@@ -177,6 +644,15 @@ async fn main() {
 async fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    // A broken config is reported by the commands that need it, so `jb config set` can fix it
+    if let Ok(config) = core::Config::load(&core::Paths::new()) {
+        match config.ui.color {
+            core::config::ColorMode::Always => colored::control::set_override(true),
+            core::config::ColorMode::Never => colored::control::set_override(false),
+            core::config::ColorMode::Auto => {}
+        }
+    }
+
     match cli.command {
         Commands::Run {
             command,
@@ -184,11 +660,28 @@ async fn run() -> Result<()> {
             timeout,
             context,
             key,
+            priority,
+            artifacts,
+            ports,
+            ready,
             wait,
             follow,
         } => {
-            commands::run::execute(command, name, timeout, context, key, wait, follow, cli.json)
-                .await
+            commands::run::execute(
+                command,
+                name,
+                timeout,
+                context,
+                key,
+                priority,
+                artifacts,
+                ports,
+                ready.into_checks(),
+                wait,
+                follow,
+                cli.json,
+            )
+            .await
         }
         Commands::List {
             status,
@@ -202,16 +695,179 @@ async fn run() -> Result<()> {
             tail,
             follow,
             pager,
-        } => commands::logs::execute(&id, tail, follow, pager),
+            since_cursor,
+            new,
+            caller,
+        } => {
+            let cursor = if new {
+                let caller = caller
+                    .or_else(|| std::env::var("JB_CALLER").ok())
+                    .unwrap_or_else(|| "default".to_string());
+                Some(commands::logs::Cursor::Caller(caller))
+            } else {
+                since_cursor.map(commands::logs::Cursor::Token)
+            };
+            commands::logs::execute(&id, tail, follow, pager, cursor, cli.json)
+        }
+        Commands::Summary { id, lines, context } => {
+            commands::summary::execute(&id, lines, context, cli.json)
+        }
+        Commands::Grep {
+            pattern,
+            ignore_case,
+            project,
+            name,
+            status,
+            since,
+        } => commands::grep::execute(
+            &pattern,
+            ignore_case,
+            project,
+            name,
+            status,
+            since,
+            cli.json,
+        ),
+        Commands::Reprioritize { id, priority } => {
+            commands::reprioritize::execute(id, &priority, cli.json).await
+        }
         Commands::Stop { id, force } => commands::stop::execute(id, force, cli.json).await,
-        Commands::Wait { id, timeout } => commands::wait::execute(id, timeout).await,
+        Commands::Wait {
+            ids,
+            all,
+            any,
+            name,
+            project,
+            timeout,
+            ready,
+        } => {
+            let targets = commands::wait::Targets {
+                ids,
+                name,
+                project,
+                group: None,
+            };
+            if targets.is_single() && !all && !any {
+                let id = targets.ids.into_iter().next().expect("one ID");
+                commands::wait::execute(id, timeout, ready, cli.json).await
+            } else {
+                commands::wait::execute_many(targets, any, timeout, ready, cli.json).await
+            }
+        }
+        Commands::Events { id, limit, follow } => {
+            commands::events::execute(id.as_deref(), limit, follow, cli.json).await
+        }
+        Commands::Port { id, name } => commands::port::execute(&id, name.as_deref(), cli.json),
+        Commands::Result { id } => commands::result::execute(&id, cli.json),
+        Commands::Artifacts { id, extract } => {
+            commands::artifacts::execute(&id, extract.as_deref(), cli.json)
+        }
         Commands::Retry { id } => commands::retry::execute(id, cli.json).await,
+        Commands::Task { name, wait, follow } => match name.as_deref() {
+            None | Some("list") => commands::task::list(cli.json),
+            Some(name) => {
+                let overrides = commands::run::spec_in_current_dir(String::new())?;
+                commands::task::run(name, overrides, wait, follow, cli.json).await
+            }
+        },
+        Commands::Service(cmd) => match cmd {
+            ServiceCommands::Start {
+                name,
+                command,
+                restart,
+                max_restarts,
+                restart_delay,
+                ports,
+                ready,
+                health,
+            } => {
+                commands::service::start(
+                    name,
+                    command,
+                    &restart,
+                    max_restarts,
+                    &restart_delay,
+                    ports,
+                    ready.into_checks(),
+                    health.into_check()?,
+                    cli.json,
+                )
+                .await
+            }
+            ServiceCommands::Stop { name, force } => {
+                commands::stop::execute(name, force, cli.json).await
+            }
+            ServiceCommands::Restart { name } => commands::service::restart(&name, cli.json).await,
+            ServiceCommands::List { all } => commands::service::list(all, cli.json),
+        },
+        Commands::Batch {
+            commands,
+            name,
+            file,
+            stdin,
+            timeout,
+            priority,
+            wait,
+        } => {
+            commands::batch::execute(
+                commands, name, file, stdin, timeout, priority, wait, cli.json,
+            )
+            .await
+        }
+        Commands::Matrix {
+            command,
+            vars,
+            name,
+            timeout,
+            priority,
+            no_wait,
+        } => {
+            commands::matrix::execute(command, vars, name, timeout, priority, no_wait, cli.json)
+                .await
+        }
+        Commands::Flow(cmd) => match cmd {
+            FlowCommands::Run {
+                file,
+                name,
+                priority,
+                wait,
+            } => commands::flow::run(&file, name, priority, wait, cli.json).await,
+            FlowCommands::Status { flow } => commands::flow::status(&flow, cli.json).map(|_| ()),
+        },
+        Commands::Group(cmd) => match cmd {
+            GroupCommands::Status { group } => commands::group::status(&group, cli.json),
+            GroupCommands::Stop { group, force } => {
+                commands::group::stop(&group, force, cli.json).await
+            }
+            GroupCommands::Wait {
+                group,
+                any,
+                timeout,
+            } => commands::group::wait(group, any, timeout, cli.json).await,
+            GroupCommands::Logs { group, follow } => commands::group::logs(&group, follow).await,
+        },
+        Commands::Config(cmd) => match cmd {
+            ConfigCommands::Show => commands::config::show(cli.json),
+            ConfigCommands::Get { key } => commands::config::get(&key, cli.json),
+            ConfigCommands::Set { key, value } => commands::config::set(&key, &value, cli.json),
+            ConfigCommands::Keys => commands::config::keys(cli.json),
+        },
         Commands::Clean {
             older_than,
             status,
             all,
-        } => commands::clean::execute(&older_than, status, all),
-        Commands::Daemon => commands::daemon::execute().await,
-        Commands::Completions { shell, install } => commands::completions::execute(shell, install),
+        } => commands::clean::execute(older_than, status, all),
+        Commands::Daemon { command } => match command {
+            // Bare `jb daemon` runs the daemon itself; the client starts it this way
+            None => commands::daemon::execute().await,
+            Some(DaemonCommands::Reload) => commands::daemon::reload(cli.json).await,
+            Some(DaemonCommands::Restart { keep_jobs }) => {
+                commands::daemon::restart(keep_jobs, cli.json).await
+            }
+        },
+        Commands::Mcp => commands::mcp::execute().await,
+        Commands::Completions { shell, install } => {
+            commands::completions::execute(Cli::command(), shell, install)
+        }
     }
 }